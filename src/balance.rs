@@ -0,0 +1,448 @@
+use crate::boss_script::Wave;
+use ggez::Context;
+use std::io::Read;
+
+pub const BALANCE_PATH: &str = "/balance.ron";
+// How often debug builds check balance.ron for changes, in frames
+pub const BALANCE_RELOAD_FRAMES: usize = 30;
+
+// Every number the game is tuned with, loaded from balance.ron when the game starts so they can
+// be tried out without a rebuild. Anything left out of the file keeps the default below, and
+// those defaults are what the game has always played with
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename = "Balance", default, deny_unknown_fields)]
+pub struct Balance {
+    pub player: PlayerBalance,
+    pub projectiles: ProjectileBalance,
+    pub enemies: EnemyBalance,
+    pub bosses: BossBalance,
+    pub structures: StructureBalance,
+    pub modes: ModeBalance,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlayerBalance {
+    pub max_health: usize,
+    pub max_energy: usize,
+    pub initial_energy: usize,
+    pub speed: usize,
+    pub melee_damage: usize,
+    pub slam_damage: usize,
+    // what a shot does to a boss, enemies take the shot's own damage
+    pub boss_shot_damage: usize,
+    pub heal_return: usize,
+    pub invisibility_duration: usize,
+
+    pub shot_cost: usize,
+    pub teleport_cost: usize,
+    pub heal_cost: usize,
+    pub fire_cost: usize,
+    pub slam_cost: usize,
+    pub lightning_cost: usize,
+    pub invisibility_cost: usize,
+    pub missile_cost: usize,
+
+    pub shot_cooldown: usize,
+    pub teleport_cooldown: usize,
+    pub fire_cooldown: usize,
+    pub slam_cooldown: usize,
+    pub lightning_cooldown: usize,
+    // counted from when invisibility wears off
+    pub invisibility_cooldown: usize,
+    pub missile_cooldown: usize,
+}
+
+impl Default for PlayerBalance {
+    fn default() -> Self {
+        PlayerBalance {
+            max_health: 100,
+            max_energy: 100,
+            initial_energy: 100,
+            speed: 1,
+            melee_damage: 30,
+            slam_damage: 50,
+            boss_shot_damage: 10,
+            heal_return: 10,
+            invisibility_duration: 10,
+            shot_cost: 1,
+            teleport_cost: 5,
+            heal_cost: 20,
+            fire_cost: 30,
+            slam_cost: 10,
+            lightning_cost: 25,
+            invisibility_cost: 30,
+            missile_cost: 75,
+            shot_cooldown: 1,
+            teleport_cooldown: 1,
+            fire_cooldown: 10,
+            slam_cooldown: 10,
+            lightning_cooldown: 5,
+            invisibility_cooldown: 25,
+            missile_cooldown: 20,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectileBalance {
+    pub shot_damage: usize,
+    pub shot_speed: usize,
+    pub missile_damage: usize,
+    pub missile_speed: usize,
+    pub lightning_damage: usize,
+    pub lightning_speed: usize,
+    // how many tiles the strike reaches out from where it lands
+    pub lightning_size: i16,
+    // damage of each stage of the flame as it spreads out, closest first
+    pub fire_damage: [usize; 4],
+    pub fire_speed: usize,
+}
+
+impl Default for ProjectileBalance {
+    fn default() -> Self {
+        ProjectileBalance {
+            shot_damage: 20,
+            shot_speed: 1,
+            missile_damage: 100,
+            missile_speed: 2,
+            lightning_damage: 80,
+            lightning_speed: 0,
+            lightning_size: 2,
+            fire_damage: [60, 45, 30, 15],
+            fire_speed: 1,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+// Every field has to be given when overriding a kind of enemy, since the defaults differ per kind
+pub struct EnemyStats {
+    pub health: usize,
+    pub speed: usize,
+    // energy the player gets for killing one
+    pub energy_return: usize,
+    pub damage: usize,
+}
+
+impl EnemyStats {
+    const fn new(health: usize, speed: usize, energy_return: usize, damage: usize) -> Self {
+        EnemyStats {
+            health,
+            speed,
+            energy_return,
+            damage,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnemyBalance {
+    pub chaser: EnemyStats,
+    pub bomber: EnemyStats,
+    pub knight: EnemyStats,
+    pub shooter: EnemyStats,
+    pub major: EnemyStats,
+    pub minor_boss: EnemyStats,
+    pub major_boss: EnemyStats,
+    // what a bomber's blast does to structures
+    pub bomber_structure_damage: usize,
+}
+
+impl Default for EnemyBalance {
+    fn default() -> Self {
+        EnemyBalance {
+            chaser: EnemyStats::new(50, 1, 3, 3),
+            bomber: EnemyStats::new(25, 2, 5, 20),
+            knight: EnemyStats::new(100, 1, 12, 0),
+            shooter: EnemyStats::new(25, 1, 7, 0),
+            major: EnemyStats::new(200, 1, 25, 10),
+            minor_boss: EnemyStats::new(1000, 1, 100, 0),
+            major_boss: EnemyStats::new(2000, 1, 100, 10),
+            bomber_structure_damage: 100,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct BossBalance {
+    pub health: usize,
+    pub major_health: usize,
+    pub collision_damage: usize,
+    pub laser_damage: usize,
+    pub column_laser_damage: usize,
+    pub asteroid_damage: usize,
+    pub stun_well_stun_time: usize,
+    pub vulnerable_time: usize,
+    // what lasers and asteroids do to structures
+    pub attack_structure_damage: usize,
+    // what a boss running into or over a structure does to it
+    pub crush_structure_damage: usize,
+}
+
+impl Default for BossBalance {
+    fn default() -> Self {
+        BossBalance {
+            health: 1000,
+            major_health: 5000,
+            collision_damage: 10,
+            laser_damage: 5,
+            column_laser_damage: 20,
+            asteroid_damage: 10,
+            stun_well_stun_time: 2,
+            vulnerable_time: 15,
+            attack_structure_damage: 25,
+            crush_structure_damage: 100,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct StructureBalance {
+    pub wall_health: usize,
+    pub spike_trap_health: usize,
+    pub turret_health: usize,
+    pub wall_cost: usize,
+    pub spike_trap_cost: usize,
+    pub turret_cost: usize,
+    pub spike_trap_damage: usize,
+    // how much health a spike trap loses every time an enemy gets hurt by it
+    pub spike_trap_wear: usize,
+    pub turret_cooldown: usize,
+    // max distance (in tiles) a turret will look for enemies to shoot at
+    pub turret_range: i32,
+}
+
+impl Default for StructureBalance {
+    fn default() -> Self {
+        StructureBalance {
+            wall_health: 100,
+            spike_trap_health: 40,
+            turret_health: 60,
+            wall_cost: 2,
+            spike_trap_cost: 5,
+            turret_cost: 15,
+            spike_trap_damage: 15,
+            spike_trap_wear: 10,
+            turret_cooldown: 3,
+            turret_range: 10,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+// Boss rush and the arena
+pub struct ModeBalance {
+    // the energy the player has at the start of every boss in a boss rush, and of the arena
+    pub loadout_energy: usize,
+    pub arena_first_wave: Wave,
+    // added on to the wave every time one is cleared
+    pub arena_wave_growth: Wave,
+    // one more major enemy joins every this many waves, 0 for never
+    pub arena_major_every: usize,
+    // turns the player gets between clearing a wave and the next one showing up
+    pub arena_wave_delay: usize,
+}
+
+impl Default for ModeBalance {
+    fn default() -> Self {
+        ModeBalance {
+            loadout_energy: 100,
+            arena_first_wave: Wave {
+                majors: 0,
+                chasers: 4,
+                bombers: 0,
+            },
+            arena_wave_growth: Wave {
+                majors: 0,
+                chasers: 2,
+                bombers: 1,
+            },
+            arena_major_every: 3,
+            arena_wave_delay: 5,
+        }
+    }
+}
+
+impl Balance {
+    // Loads balance.ron from the resources folder. Falls back to the defaults if it is missing,
+    // broken or has numbers the game can't work with, and says why
+    pub fn load(ctx: &Context) -> Balance {
+        let text = match Self::read(ctx) {
+            Some(text) => text,
+            None => {
+                println!("Couldn't open {}, using the default balance", BALANCE_PATH);
+                return Balance::default();
+            }
+        };
+        match Self::parse(&text) {
+            Ok(balance) => balance,
+            Err(errors) => {
+                println!("{} is invalid, using the default balance:", BALANCE_PATH);
+                for error in errors {
+                    println!("    {}", error);
+                }
+                Balance::default()
+            }
+        }
+    }
+
+    pub fn read(ctx: &Context) -> Option<String> {
+        let mut text = String::new();
+        ctx.fs
+            .open(BALANCE_PATH)
+            .and_then(|mut file| Ok(file.read_to_string(&mut text)?))
+            .ok()?;
+        Some(text)
+    }
+
+    pub fn parse(text: &str) -> Result<Balance, Vec<String>> {
+        let balance: Balance = ron::from_str(text).map_err(|e| vec![e.to_string()])?;
+        let errors = balance.validate();
+        if errors.is_empty() {
+            Ok(balance)
+        } else {
+            Err(errors)
+        }
+    }
+
+    // Everything in here that would break the game, like things with no health or a lightning
+    // strike bigger than the world
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut check = |ok: bool, message: &str| {
+            if !ok {
+                errors.push(message.to_string());
+            }
+        };
+        let player = &self.player;
+        check(player.max_health > 0, "player.max_health has to be above 0");
+        check(player.max_energy > 0, "player.max_energy has to be above 0");
+        check(
+            player.initial_energy <= player.max_energy,
+            "player.initial_energy can't be more than player.max_energy",
+        );
+        check(player.speed > 0, "player.speed has to be above 0");
+        for (name, cost) in [
+            ("shot_cost", player.shot_cost),
+            ("teleport_cost", player.teleport_cost),
+            ("heal_cost", player.heal_cost),
+            ("fire_cost", player.fire_cost),
+            ("slam_cost", player.slam_cost),
+            ("lightning_cost", player.lightning_cost),
+            ("invisibility_cost", player.invisibility_cost),
+            ("missile_cost", player.missile_cost),
+        ] {
+            check(
+                cost <= player.max_energy,
+                &format!("player.{} can't be more than player.max_energy", name),
+            );
+        }
+
+        let projectiles = &self.projectiles;
+        check(
+            (0..crate::WORLD_SIZE.0.min(crate::WORLD_SIZE.1)).contains(&projectiles.lightning_size),
+            "projectiles.lightning_size has to fit inside a world",
+        );
+
+        let enemies = &self.enemies;
+        for (name, stats) in [
+            ("chaser", enemies.chaser),
+            ("bomber", enemies.bomber),
+            ("knight", enemies.knight),
+            ("shooter", enemies.shooter),
+            ("major", enemies.major),
+            ("minor_boss", enemies.minor_boss),
+            ("major_boss", enemies.major_boss),
+        ] {
+            check(
+                stats.health > 0,
+                &format!("enemies.{}.health has to be above 0", name),
+            );
+            check(
+                stats.speed > 0,
+                &format!("enemies.{}.speed has to be above 0", name),
+            );
+        }
+
+        let bosses = &self.bosses;
+        check(bosses.health > 0, "bosses.health has to be above 0");
+        check(
+            bosses.major_health > 0,
+            "bosses.major_health has to be above 0",
+        );
+        // a boss that's never vulnerable can't be beaten, and its turn counters would run out
+        check(
+            bosses.vulnerable_time > 0,
+            "bosses.vulnerable_time has to be above 0",
+        );
+
+        let structures = &self.structures;
+        check(
+            structures.wall_health > 0,
+            "structures.wall_health has to be above 0",
+        );
+        check(
+            structures.spike_trap_health > 0,
+            "structures.spike_trap_health has to be above 0",
+        );
+        check(
+            structures.turret_health > 0,
+            "structures.turret_health has to be above 0",
+        );
+        check(
+            structures.turret_range >= 0,
+            "structures.turret_range can't be negative",
+        );
+
+        let modes = &self.modes;
+        check(
+            modes.loadout_energy <= player.max_energy,
+            "modes.loadout_energy can't be more than player.max_energy",
+        );
+        check(
+            modes.arena_first_wave.size() > 0,
+            "modes.arena_first_wave needs at least one enemy",
+        );
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_balance_parses() {
+        let text = include_str!("../resources/balance.ron");
+        assert_eq!(Balance::parse(text).err(), None);
+        assert!(Balance::default().validate().is_empty());
+    }
+
+    #[test]
+    fn broken_balance_is_rejected() {
+        assert!(Balance::parse("(player: (max_health: ))").is_err());
+        // anything left out is filled in from the defaults, and still checked
+        assert_eq!(
+            Balance::parse("(player: (max_health: 0))").err(),
+            Some(vec!["player.max_health has to be above 0".to_string()])
+        );
+
+        let mut balance = Balance::default();
+        balance.player.initial_energy = balance.player.max_energy + 1;
+        balance.bosses.vulnerable_time = 0;
+        assert_eq!(
+            balance.validate(),
+            vec![
+                "player.initial_energy can't be more than player.max_energy",
+                "bosses.vulnerable_time has to be above 0",
+            ]
+        );
+    }
+}
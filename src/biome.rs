@@ -0,0 +1,264 @@
+use crate::{
+    random, tile,
+    utils::Position,
+    world::{World, BOSS_ROOMS, FINAL_BOSS_ROOM},
+    BOARD_SIZE, WORLD_SIZE,
+};
+use rand_chacha::ChaCha8Rng;
+
+// The biomes a region of the board can grow into. Volcanic worlds only ever surround the final boss
+const REGION_BIOMES: [Biome; 5] = [
+    Biome::Plains,
+    Biome::Forest,
+    Biome::Desert,
+    Biome::Tundra,
+    Biome::Swamp,
+];
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+// What kind of land a world is, which decides the color of its ground, what grows on it, what its
+// lakes are filled with and which enemies live there. Boss rooms look the same in every biome
+pub enum Biome {
+    #[default]
+    Plains,
+    Forest,
+    Desert,
+    Tundra,
+    Swamp,
+    Volcanic,
+}
+
+// How likely each kind of enemy is to be the one spawned, out of all three together
+pub struct SpawnTable {
+    pub chasers: u32,
+    pub bombers: u32,
+    pub majors: u32,
+}
+
+impl Biome {
+    pub fn name(self) -> &'static str {
+        match self {
+            Biome::Plains => "Plains",
+            Biome::Forest => "Forest",
+            Biome::Desert => "Desert",
+            Biome::Tundra => "Tundra",
+            Biome::Swamp => "Swamp",
+            Biome::Volcanic => "Volcanic",
+        }
+    }
+
+    // The color of the ground, wherever there's no terrain on it
+    pub fn floor(self) -> [f32; 4] {
+        match self {
+            Biome::Plains => tile::GRASS,
+            Biome::Forest => tile::FOREST_FLOOR,
+            Biome::Desert => tile::SAND,
+            Biome::Tundra => tile::SNOW,
+            Biome::Swamp => tile::SWAMP_FLOOR,
+            Biome::Volcanic => tile::ASH,
+        }
+    }
+
+    // What lakes are filled with
+    pub fn lake(self) -> [f32; 4] {
+        match self {
+            Biome::Tundra => tile::ICE,
+            Biome::Volcanic => tile::LAVA,
+            _ => tile::WATER,
+        }
+    }
+
+    // The patches the biome is covered in, if any, and the range of how many tiles each one has
+    fn feature(self) -> Option<([f32; 4], (i16, i16))> {
+        match self {
+            Biome::Forest => Some((tile::TREE, (3, 10))),
+            Biome::Desert => Some((tile::CACTUS, (1, 3))),
+            Biome::Tundra => Some((tile::ICE, (4, 12))),
+            Biome::Swamp => Some((tile::MUD, (6, 16))),
+            Biome::Plains | Biome::Volcanic => None,
+        }
+    }
+
+    // Plains spawn the same mix of enemies every world did before there were biomes
+    pub fn spawn_table(self) -> SpawnTable {
+        let (chasers, bombers, majors) = match self {
+            Biome::Plains => (4, 3, 3),
+            Biome::Forest => (6, 2, 2),
+            Biome::Desert => (3, 5, 2),
+            Biome::Tundra => (3, 2, 5),
+            Biome::Swamp => (5, 3, 2),
+            Biome::Volcanic => (2, 4, 4),
+        };
+        SpawnTable {
+            chasers,
+            bombers,
+            majors,
+        }
+    }
+
+    // Splits the board into regions grown out of a few random worlds, each with a random biome.
+    // The worlds around the final boss are always volcanic, and the world the player starts in is
+    // always plains
+    pub fn gen_biomes(world: &mut World, rng: &mut ChaCha8Rng, regions: usize) {
+        let worlds = (BOARD_SIZE.0 / WORLD_SIZE.0) as usize;
+        let centers: Vec<(Position, Biome)> = (0..regions)
+            .map(|_| {
+                let center = Position::new(
+                    random::rand_range(rng, 0, worlds as i16) as usize,
+                    random::rand_range(rng, 0, worlds as i16) as usize,
+                );
+                let biome =
+                    REGION_BIOMES[random::rand_range(rng, 0, REGION_BIOMES.len() as i16) as usize];
+                (center, biome)
+            })
+            .collect();
+        for y in 0..worlds {
+            for x in 0..worlds {
+                let world_pos = Position::new(x, y);
+                let distance = |other: Position| {
+                    (other.x as i32 - x as i32).abs() + (other.y as i32 - y as i32).abs()
+                };
+                let near_final_boss = (FINAL_BOSS_ROOM.x as i32 - x as i32).abs() <= 1
+                    && (FINAL_BOSS_ROOM.y as i32 - y as i32).abs() <= 1;
+                world.biomes[y][x] = if near_final_boss {
+                    Biome::Volcanic
+                } else if world_pos == world.world_position {
+                    Biome::Plains
+                } else {
+                    centers
+                        .iter()
+                        .min_by_key(|(center, _)| distance(*center))
+                        .map_or(Biome::Plains, |(_, biome)| *biome)
+                };
+            }
+        }
+    }
+
+    // Scatters patches of each biome's feature over its worlds. The outermost tiles are left clear
+    // so nothing grows over the way into the next world
+    pub fn gen_features(world: &mut World, rng: &mut ChaCha8Rng, per_world: usize) {
+        const DIRECTIONS: [[i16; 2]; 4] = [[0, 1], [0, -1], [1, 0], [-1, 0]];
+        let worlds = (BOARD_SIZE.0 / WORLD_SIZE.0) as usize;
+        for y in 0..worlds {
+            for x in 0..worlds {
+                if BOSS_ROOMS.contains(&Position::new(x, y)) {
+                    continue;
+                }
+                let (feature, size) = match world.biomes[y][x].feature() {
+                    Some(feature) => feature,
+                    None => continue,
+                };
+                for _ in 0..per_world {
+                    let mut pos = (
+                        random::rand_range(rng, 1, WORLD_SIZE.0 - 1),
+                        random::rand_range(rng, 1, WORLD_SIZE.1 - 1),
+                    );
+                    for _ in 0..random::rand_range(rng, size.0, size.1 + 1) {
+                        let tile = Position::new(pos.0 as usize, pos.1 as usize);
+                        if !world.terrain_map[y][x].contains_key(&tile)
+                            && !world.entity_map[y][x].contains_key(&tile)
+                        {
+                            world.terrain_map[y][x].insert(tile, feature);
+                        }
+                        let step = DIRECTIONS[random::rand_range(rng, 0, 4) as usize];
+                        pos = (
+                            (pos.0 + step[0]).clamp(1, WORLD_SIZE.0 - 2),
+                            (pos.1 + step[1]).clamp(1, WORLD_SIZE.1 - 2),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    const SEEDS: [u64; 4] = [0, 1, 42, 1234];
+
+    fn world_with_biomes(seed: u64) -> World {
+        let mut world = World::empty();
+        Biome::gen_biomes(&mut world, &mut ChaCha8Rng::seed_from_u64(seed), 8);
+        world
+    }
+
+    #[test]
+    fn final_boss_is_surrounded_by_volcanoes() {
+        for seed in SEEDS {
+            let world = world_with_biomes(seed);
+            let worlds = (BOARD_SIZE.0 / WORLD_SIZE.0) as usize;
+            for y in 0..worlds {
+                for x in 0..worlds {
+                    let near_final_boss =
+                        FINAL_BOSS_ROOM.x.abs_diff(x) <= 1 && FINAL_BOSS_ROOM.y.abs_diff(y) <= 1;
+                    assert_eq!(
+                        world.biomes[y][x] == Biome::Volcanic,
+                        near_final_boss,
+                        "the world at {:?} is {} with seed {}",
+                        Position::new(x, y),
+                        world.biomes[y][x].name(),
+                        seed
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn player_starts_on_the_plains() {
+        for seed in SEEDS {
+            let world = world_with_biomes(seed);
+            let start = world.world_position;
+            assert_eq!(
+                world.biomes[start.y][start.x],
+                Biome::Plains,
+                "seed {}",
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn features_only_grow_where_they_belong() {
+        for seed in SEEDS {
+            let mut world = world_with_biomes(seed);
+            Biome::gen_features(&mut world, &mut ChaCha8Rng::seed_from_u64(seed), 6);
+            let worlds = (BOARD_SIZE.0 / WORLD_SIZE.0) as usize;
+            for y in 0..worlds {
+                for x in 0..worlds {
+                    let biome = world.biomes[y][x];
+                    let terrain = &world.terrain_map[y][x];
+                    if BOSS_ROOMS.contains(&Position::new(x, y)) {
+                        assert!(
+                            terrain.is_empty(),
+                            "a boss room grew {} with seed {}",
+                            biome.name(),
+                            seed
+                        );
+                        continue;
+                    }
+                    for (pos, tile) in terrain {
+                        assert_eq!(
+                            Some(*tile),
+                            biome.feature().map(|(feature, _)| feature),
+                            "something else grew in a {} world with seed {}",
+                            biome.name(),
+                            seed
+                        );
+                        assert!(
+                            (1..WORLD_SIZE.0 as usize - 1).contains(&pos.x)
+                                && (1..WORLD_SIZE.1 as usize - 1).contains(&pos.y),
+                            "{} grew over the edge of a world at {:?} with seed {}",
+                            biome.name(),
+                            pos,
+                            seed
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,654 @@
+use crate::{
+    boss_script::{self, Attack, BossScript, Wave},
+    combat_log::{Actor, GameEvent},
+    direction::Direction,
+    random,
+    structure::Structure,
+    telegraph::Telegraphs,
+    tile,
+    tileset::TileBatch,
+    utils::{Boss, BossPhase, Position},
+    world::World,
+    WORLD_SIZE,
+};
+use ggez::{graphics::Canvas, Context};
+use rand_chacha::ChaCha8Rng;
+use std::cmp::max;
+
+// How a boss fights. Everything about a boss that isn't shared by every boss (where it is, its
+// health, its shield) lives in the implementation, so a boss can be put in any room
+pub trait BossBehavior {
+    fn name(&self) -> &'static str;
+
+    fn color(&self) -> [f32; 4];
+
+    // The final boss is bigger, hits wider and has more health
+    fn is_major(&self) -> bool {
+        false
+    }
+
+    // Called on the turn the player walks into the boss's room, before the boss takes its turn
+    fn on_enter(&mut self, _world: &mut World, _index: usize, _rng: &mut ChaCha8Rng) {}
+
+    // Everything the boss does on one of the player's turns in its room
+    fn take_turn(&mut self, world: &mut World, index: usize, rng: &mut ChaCha8Rng);
+
+    // Called whenever an attack lands on hit_pos inside the boss, whether or not it can be hurt
+    fn on_damaged(&mut self, _world: &mut World, _index: usize, _hit_pos: Position) {}
+
+    // Draws the boss's attacks and their warnings, the boss itself looks the same for every boss
+    fn draw(&mut self, world: &mut World, index: usize, ctx: &Context, canvas: &mut Canvas);
+
+    // Marks every tile the boss is about to hurt with how many turns it has left
+    fn telegraphs(&self, world: &World, index: usize, telegraphs: &mut Telegraphs) {
+        Boss::hazard_telegraphs(world, index, telegraphs);
+    }
+
+    // Whether attacks landing on the boss right now hurt it
+    fn is_vulnerable(&self, boss: &Boss) -> bool {
+        boss.vulnerable_time != 0
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+// Every boss there is, kept as an enum so the boss's own state can be saved with the world. A new
+// boss only needs a variant here and an arm in each of the two functions below
+pub enum BossKind {
+    // any boss described in bosses.ron
+    Scripted(ScriptedBoss),
+}
+
+impl Default for BossKind {
+    // stands in for the boss in saves from before bosses kept their own state, until
+    // Boss::restore_kinds works out which one it was
+    fn default() -> Self {
+        BossKind::Scripted(ScriptedBoss::default())
+    }
+}
+
+impl BossKind {
+    // Whether this is only standing in for a boss that wasn't saved, see default
+    pub fn is_missing(&self) -> bool {
+        match self {
+            BossKind::Scripted(boss) => boss.script.is_empty(),
+        }
+    }
+
+    pub fn behavior(&self) -> &dyn BossBehavior {
+        match self {
+            BossKind::Scripted(boss) => boss,
+        }
+    }
+
+    pub fn behavior_mut(&mut self) -> &mut dyn BossBehavior {
+        match self {
+            BossKind::Scripted(boss) => boss,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Default)]
+// A boss that fights however its script in bosses.ron says. The script itself isn't saved, only
+// which one it is and how far through it the boss has got
+pub struct ScriptedBoss {
+    script: String,
+    name: String,
+    color: [f32; 4],
+    major: bool,
+    phase: usize,
+    // one for each attack of the current phase
+    attacks: Vec<AttackState>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+// What one attack of a boss is keeping track of between turns
+struct AttackState {
+    // turns until the attack goes off again
+    cooldown: usize,
+    // turns until a chasing boss takes its next step
+    move_delay: usize,
+    // the charge a chasing boss is winding up or just finished
+    rush: Option<Rush>,
+    // weak spots left to hit before the boss can be hurt
+    shield: usize,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq)]
+struct Rush {
+    direction: Direction,
+    // how far the lane reaches from the boss to the wall
+    length: usize,
+    // turns left before the boss charges
+    telegraph: usize,
+    // the charge happened this turn, the lane is left showing where the boss came from
+    done: bool,
+}
+
+impl AttackState {
+    fn new(attack: &Attack) -> Self {
+        let mut state = AttackState {
+            cooldown: 0,
+            move_delay: 0,
+            rush: None,
+            shield: 0,
+        };
+        match *attack {
+            Attack::Lasers { cooldown, .. }
+            | Attack::Asteroids { cooldown, .. }
+            | Attack::StunWells { cooldown, .. }
+            | Attack::Blackout { cooldown, .. } => state.cooldown = cooldown,
+            Attack::Chase {
+                move_delay,
+                rush_cooldown,
+                ..
+            } => {
+                state.move_delay = move_delay;
+                state.cooldown = rush_cooldown;
+            }
+            Attack::WeakSpots { shield_hits } => state.shield = shield_hits,
+            // the first wave comes as soon as the room is empty
+            Attack::ColumnLaser { .. } | Attack::Reinforcements { .. } | Attack::Guards { .. } => {}
+        }
+        state
+    }
+
+    // Counts down, returning whether the attack goes off this turn
+    fn ready(&mut self, cooldown: usize) -> bool {
+        if self.cooldown == 0 {
+            self.cooldown = cooldown;
+            true
+        } else {
+            self.cooldown -= 1;
+            false
+        }
+    }
+}
+
+impl ScriptedBoss {
+    pub fn new(script: &str, boss_script: &BossScript) -> Self {
+        ScriptedBoss {
+            script: script.to_string(),
+            name: boss_script.name.clone(),
+            color: boss_script.color,
+            major: boss_script.major,
+            phase: 0,
+            attacks: boss_script.phases[0]
+                .attacks
+                .iter()
+                .map(AttackState::new)
+                .collect(),
+        }
+    }
+
+    // The phase the boss should be in for how hurt it is. Bosses never go back to an earlier phase
+    fn update_phase(&mut self, world: &mut World, index: usize, script: &BossScript) {
+        let boss = &world.bosses[index];
+        let max_health = boss
+            .max_health(world.difficulty, &world.balance.bosses)
+            .max(1);
+        let percent = boss.health * 100 / max_health;
+        let phase = script
+            .phases
+            .iter()
+            .rposition(|phase| percent <= phase.from_health)
+            .unwrap_or(0)
+            .max(self.phase)
+            .min(script.phases.len() - 1);
+        let attacks = &script.phases[phase].attacks;
+        if phase != self.phase {
+            self.phase = phase;
+            world.events.push(GameEvent::BossPhaseChanged {
+                boss: self.name(),
+                phase: BossPhase::Stage(phase + 1),
+            });
+        } else if attacks.len() == self.attacks.len() {
+            return;
+        }
+        // a new phase, or a script that was changed while the game was running
+        self.attacks = attacks.iter().map(AttackState::new).collect();
+    }
+
+    fn attack(
+        &mut self,
+        world: &mut World,
+        index: usize,
+        rng: &mut ChaCha8Rng,
+        attack: &Attack,
+        attack_index: usize,
+    ) {
+        let state = &mut self.attacks[attack_index];
+        match *attack {
+            Attack::Lasers {
+                amount,
+                cooldown,
+                telegraph,
+                linger,
+            } => {
+                if state.ready(cooldown) {
+                    Boss::generate_lasers(world, amount, telegraph, linger, rng);
+                }
+            }
+            Attack::Asteroids {
+                cooldown,
+                telegraph,
+                linger,
+            } => {
+                if state.ready(cooldown) {
+                    Boss::generate_asteroid(world, telegraph, linger);
+                }
+            }
+            Attack::ColumnLaser { telegraph } => {
+                Boss::generate_column_laser(world, index, telegraph)
+            }
+            Attack::StunWells {
+                cooldown,
+                telegraph,
+                linger,
+            } => {
+                if state.ready(cooldown) {
+                    Boss::generate_stun_well(world, index, telegraph, linger, rng);
+                }
+            }
+            Attack::Chase {
+                move_delay,
+                rush_cooldown,
+                telegraph,
+            } => Self::chase_player(world, index, state, move_delay, rush_cooldown, telegraph),
+            Attack::Blackout {
+                cooldown,
+                telegraph,
+            } => Self::generate_safe_spot(world, index, state, cooldown, telegraph, rng),
+            Attack::WeakSpots { shield_hits } => {
+                Self::generate_vulnerable_spot(world, index, state, shield_hits, rng)
+            }
+            Attack::Reinforcements { wave, cooldown } => {
+                if world.enemies_map[world.world_position.y][world.world_position.x].is_empty()
+                    && state.cooldown == 0
+                {
+                    Boss::spawn_wave(world, rng, index, &wave);
+                    state.cooldown = cooldown;
+                } else if state.cooldown != 0 {
+                    state.cooldown -= 1;
+                }
+            }
+            Attack::Guards { wave, exposed } => Self::guard(world, index, &wave, exposed, rng),
+        }
+    }
+
+    // Once a wave is cleared the boss is open for a while, then calls in the next one
+    fn guard(world: &mut World, index: usize, wave: &Wave, exposed: usize, rng: &mut ChaCha8Rng) {
+        if !world.enemies_map[world.world_position.y][world.world_position.x].is_empty() {
+            return;
+        }
+        let boss = &mut world.bosses[index];
+        if boss.vulnerable_time == 0 && boss.boss_can_attack {
+            boss.vulnerable_time = exposed;
+            boss.boss_can_attack = false;
+        } else if boss.vulnerable_time != 0 {
+            boss.vulnerable_time -= 1;
+        } else {
+            boss.boss_can_attack = true;
+            Boss::spawn_wave(world, rng, index, wave);
+        }
+    }
+
+    fn chase_player(
+        world: &mut World,
+        index: usize,
+        state: &mut AttackState,
+        move_delay: usize,
+        rush_cooldown: usize,
+        telegraph: usize,
+    ) {
+        if state.rush.is_some_and(|rush| rush.done) {
+            state.rush = None;
+        }
+        let boss_pos = world.bosses[index].position;
+        let boss_delta = (
+            boss_pos.x as i32 - world.player.pos.x as i32,
+            boss_pos.y as i32 - world.player.pos.y as i32,
+        );
+        if (state.rush.is_some() || (boss_delta.0.abs() <= 3 || boss_delta.1.abs() <= 3))
+            && state.cooldown == 0
+        {
+            Self::rush_player(world, index, state, boss_delta, rush_cooldown, telegraph);
+        } else if world.bosses[index].boss_can_attack && state.move_delay == 0 {
+            let mut boss_pos = world.bosses[index].position;
+            if boss_delta.0.abs() > boss_delta.1.abs() {
+                if boss_delta.0 < 0 {
+                    boss_pos.x += 1;
+                    Boss::move_boss(world, index, boss_pos, Direction::East);
+                } else {
+                    boss_pos.x -= 1;
+                    Boss::move_boss(world, index, boss_pos, Direction::West);
+                }
+            } else {
+                if boss_delta.1 < 0 {
+                    boss_pos.y += 1;
+                    Boss::move_boss(world, index, boss_pos, Direction::South);
+                } else {
+                    boss_pos.y -= 1;
+                    Boss::move_boss(world, index, boss_pos, Direction::North);
+                }
+            }
+            state.move_delay = move_delay;
+            if state.cooldown != 0 {
+                state.cooldown -= 1;
+            }
+        } else {
+            if world.bosses[index].vulnerable_time != 0 {
+                world.bosses[index].vulnerable_time -= 1;
+                if world.bosses[index].vulnerable_time == 0 {
+                    world.bosses[index].boss_can_attack = true;
+                }
+            } else {
+                state.move_delay = state.move_delay.saturating_sub(1);
+                if state.cooldown != 0 {
+                    state.cooldown -= 1;
+                }
+            }
+        }
+    }
+
+    fn rush_player(
+        world: &mut World,
+        index: usize,
+        state: &mut AttackState,
+        (x_delta, y_delta): (i32, i32),
+        rush_cooldown: usize,
+        telegraph: usize,
+    ) {
+        let boss_pos = world.bosses[index].position;
+        let offset = world.bosses[index].offset;
+        let Some(rush) = &mut state.rush else {
+            let (direction, length) = if x_delta.abs() > y_delta.abs() {
+                if x_delta < 0 {
+                    (Direction::East, WORLD_SIZE.0 as usize - 1 - boss_pos.x)
+                } else {
+                    (Direction::West, boss_pos.x)
+                }
+            } else {
+                if y_delta < 0 {
+                    (Direction::South, WORLD_SIZE.1 as usize - 1 - boss_pos.y)
+                } else {
+                    (Direction::North, boss_pos.y)
+                }
+            };
+            // the boss always takes at least a turn to wind up
+            state.rush = Some(Rush {
+                direction,
+                length,
+                telegraph: world.difficulty.telegraph(telegraph).max(1),
+                done: false,
+            });
+            return;
+        };
+        rush.telegraph = rush.telegraph.saturating_sub(1);
+        if rush.telegraph > 0 {
+            return;
+        }
+
+        let direction = rush.direction;
+        let in_lane = |pos: Position| Self::in_lane(boss_pos, offset - 1, direction, pos);
+        //Anything built in the lane the boss charges down gets crushed
+        Structure::damage_where(
+            world,
+            world.world_position,
+            world.balance.bosses.crush_structure_damage,
+            in_lane,
+        );
+        if in_lane(world.player.pos) {
+            let name = world.bosses[index].name();
+            world
+                .player
+                .damage(world.balance.player.max_health, Actor::Boss(name));
+        } else {
+            let boss = &mut world.bosses[index];
+            match direction {
+                Direction::North => boss.position.y = offset,
+                Direction::South => boss.position.y = WORLD_SIZE.1 as usize - offset - 1,
+                Direction::West => boss.position.x = offset,
+                Direction::East => boss.position.x = WORLD_SIZE.0 as usize - 1 - offset,
+            }
+        }
+        rush.done = true;
+        state.cooldown = rush_cooldown;
+        world.bosses[index].vulnerable_time = world.balance.bosses.vulnerable_time;
+        world.bosses[index].boss_can_attack = false;
+    }
+
+    // Whether pos is in the lane a boss of the given half width charges down
+    fn in_lane(boss_pos: Position, len: usize, direction: Direction, pos: Position) -> bool {
+        match direction {
+            Direction::North => {
+                pos.x <= boss_pos.x + len && pos.x + len >= boss_pos.x && pos.y <= boss_pos.y
+            }
+            Direction::South => {
+                pos.x <= boss_pos.x + len && pos.x + len >= boss_pos.x && pos.y >= boss_pos.y
+            }
+            Direction::West => {
+                pos.y <= boss_pos.y + len && pos.y + len >= boss_pos.y && pos.x <= boss_pos.x
+            }
+            Direction::East => {
+                pos.y <= boss_pos.y + len && pos.y + len >= boss_pos.y && pos.x >= boss_pos.x
+            }
+        }
+    }
+
+    fn draw_rush(world: &World, index: usize, rush: Rush, batch: &mut TileBatch) {
+        let color = if rush.done {
+            tile::FIRE_TERTIARY
+        } else {
+            Boss::laser_color(rush.telegraph)
+        };
+        let width = world.bosses[index].offset as i32 - 1;
+        for i in 0..rush.length as i32 {
+            for j in -width..=width {
+                let mut x = world.bosses[index].position.x as i32;
+                let mut y = world.bosses[index].position.y as i32;
+                // after the rush the lane points back the way the boss came
+                let along = if rush.done { -i } else { i };
+                match rush.direction {
+                    Direction::North => {
+                        x += j;
+                        y -= along;
+                    }
+                    Direction::South => {
+                        x += j;
+                        y += along;
+                    }
+                    Direction::West => {
+                        x -= along;
+                        y += j;
+                    }
+                    Direction::East => {
+                        x += along;
+                        y += j;
+                    }
+                }
+                if (0..WORLD_SIZE.0 as i32).contains(&x) && (0..WORLD_SIZE.1 as i32).contains(&y) {
+                    batch.push(Position::new(x as usize, y as usize), color);
+                }
+            }
+        }
+    }
+
+    fn generate_safe_spot(
+        world: &mut World,
+        index: usize,
+        state: &mut AttackState,
+        cooldown: usize,
+        telegraph: usize,
+        rng: &mut ChaCha8Rng,
+    ) {
+        if let Some(spot) = &mut world.encounter_mut().safe_spot {
+            if spot.2 == 0 {
+                world.encounter_mut().safe_spot = None;
+                state.cooldown = cooldown;
+                world.encounter_mut().in_blackout = false;
+            } else {
+                spot.2 -= 1;
+                if spot.2 == 0 {
+                    world.encounter_mut().in_blackout = true;
+                }
+            }
+        } else if state.cooldown == 0 {
+            let mut size: usize = 1; //3x3
+            if Boss::coin_flip(rng) {
+                size = 2; //5x5
+            }
+            let player_x = world.player.pos.x as i16;
+            let player_y = world.player.pos.y as i16;
+
+            let mut pos = world.bosses[index].position;
+            while Boss::area_touches_boss(world, pos, size) {
+                let x = random::rand_range(rng, max(0, player_x - 5), player_x + 5) as usize;
+                let y = random::rand_range(rng, max(0, player_y - 5), player_y + 5) as usize;
+                pos = Position::new(x, y);
+            }
+
+            world.encounter_mut().safe_spot = Some((pos, size, world.difficulty.telegraph(telegraph)));
+        } else {
+            state.cooldown -= 1;
+        }
+    }
+
+    fn generate_vulnerable_spot(
+        world: &mut World,
+        index: usize,
+        state: &mut AttackState,
+        shield_hits: usize,
+        rng: &mut ChaCha8Rng,
+    ) {
+        if world.encounter().vulnerable_spot.is_some() {
+            return;
+        }
+        if world.bosses[index].vulnerable_time == 0 {
+            let boss_pos = world.bosses[index].position;
+            let offset = world.bosses[index].offset - 1;
+            let on_vert = Boss::coin_flip(rng); // On horizontal?
+            let pos_side = Boss::coin_flip(rng); //On top or left?
+            let spot = random::rand_range(rng, 0, offset as i16 * 2 - 1) as usize;
+            let mut vulnerable_spot = Position::new(0, 0);
+            if on_vert {
+                vulnerable_spot.y = boss_pos.y - offset + spot;
+                if pos_side {
+                    vulnerable_spot.x = boss_pos.x - offset;
+                } else {
+                    vulnerable_spot.x = boss_pos.x + offset;
+                }
+            } else {
+                vulnerable_spot.x = boss_pos.x - offset + spot;
+                if pos_side {
+                    vulnerable_spot.y = boss_pos.y - offset;
+                } else {
+                    vulnerable_spot.y = boss_pos.y + offset;
+                }
+            }
+            world.encounter_mut().vulnerable_spot = Some(vulnerable_spot);
+
+            if state.shield == 0 {
+                world.bosses[index].vulnerable_time = world.balance.bosses.vulnerable_time;
+                state.shield = shield_hits;
+                world.encounter_mut().vulnerable_spot = None
+            }
+        } else {
+            world.bosses[index].vulnerable_time -= 1;
+        }
+    }
+}
+
+impl BossBehavior for ScriptedBoss {
+    fn name(&self) -> &'static str {
+        boss_script::intern(&self.name)
+    }
+
+    fn color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    fn is_major(&self) -> bool {
+        self.major
+    }
+
+    // A guarded boss has its first wave waiting for the player
+    fn on_enter(&mut self, world: &mut World, index: usize, rng: &mut ChaCha8Rng) {
+        let Some(script) = world.boss_scripts.get(&self.script).cloned() else {
+            return;
+        };
+        if !world.enemies_map[world.world_position.y][world.world_position.x].is_empty() {
+            return;
+        }
+        let phase = &script.phases[self.phase.min(script.phases.len() - 1)];
+        for attack in &phase.attacks {
+            if let Attack::Guards { wave, .. } = attack {
+                world.bosses[index].boss_can_attack = true;
+                Boss::spawn_wave(world, rng, index, wave);
+                return;
+            }
+        }
+    }
+
+    fn take_turn(&mut self, world: &mut World, index: usize, rng: &mut ChaCha8Rng) {
+        // a boss whose script has since been taken out of bosses.ron just stands there
+        let Some(script) = world.boss_scripts.get(&self.script).cloned() else {
+            return;
+        };
+        self.update_phase(world, index, &script);
+        Boss::tick_hazards(world);
+        for (attack_index, attack) in script.phases[self.phase].attacks.iter().enumerate() {
+            self.attack(world, index, rng, attack, attack_index);
+        }
+        Boss::check_hazards(world, index);
+    }
+
+    // Hitting a weak spot knocks a point off the shield
+    fn on_damaged(&mut self, world: &mut World, _index: usize, hit_pos: Position) {
+        if world.encounter().vulnerable_spot != Some(hit_pos) {
+            return;
+        }
+        world.encounter_mut().vulnerable_spot = None;
+        let Some(phase) = world
+            .boss_scripts
+            .get(&self.script)
+            .and_then(|script| script.phases.get(self.phase))
+        else {
+            return;
+        };
+        for (attack, state) in phase.attacks.iter().zip(&mut self.attacks) {
+            if let Attack::WeakSpots { .. } = attack {
+                state.shield = state.shield.saturating_sub(1);
+            }
+        }
+    }
+
+    fn telegraphs(&self, world: &World, index: usize, telegraphs: &mut Telegraphs) {
+        let boss = &world.bosses[index];
+        for state in &self.attacks {
+            if let Some(rush) = state.rush.filter(|rush| !rush.done) {
+                telegraphs.warn_where(rush.telegraph, |pos| {
+                    Self::in_lane(boss.position, boss.offset - 1, rush.direction, pos)
+                });
+            }
+        }
+        Boss::hazard_telegraphs(world, index, telegraphs);
+    }
+
+    fn draw(&mut self, world: &mut World, index: usize, ctx: &Context, canvas: &mut Canvas) {
+        Boss::draw_stun_wells(world, canvas);
+        let mut column = TileBatch::new(ctx, None);
+        Boss::draw_laser_column(world, index, &mut column);
+        column.finish(canvas);
+        Boss::draw_asteroids(world, canvas);
+        Boss::draw_lasers(world, canvas);
+        let mut rushes = TileBatch::new(ctx, None);
+        for state in &self.attacks {
+            if let Some(rush) = state.rush {
+                Self::draw_rush(world, index, rush, &mut rushes);
+            }
+        }
+        rushes.finish(canvas);
+        // the blackout covers everything else up
+        Boss::draw_safe_spot(world, canvas);
+    }
+}
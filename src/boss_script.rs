@@ -0,0 +1,288 @@
+use crate::world::BOSS_ROOM_SCRIPTS;
+use ggez::Context;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::sync::Mutex;
+
+pub const BOSS_SCRIPTS_PATH: &str = "/bosses.ron";
+// The scripts the game ships with, used whenever bosses.ron can't be read or is broken
+const DEFAULT_BOSS_SCRIPTS: &str = include_str!("../resources/bosses.ron");
+
+// How every boss fights, by the name the world gives each boss room in BOSS_ROOM_SCRIPTS
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct BossScripts(pub HashMap<String, BossScript>);
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BossScript {
+    // what the log calls the boss
+    pub name: String,
+    pub color: [f32; 4],
+    // major bosses are bigger, hit wider and use the major health from balance.ron
+    #[serde(default)]
+    pub major: bool,
+    pub phases: Vec<Phase>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Phase {
+    // the phase starts once the boss is down to this percentage of its health, the first phase
+    // has to start at 100
+    pub from_health: usize,
+    // run in this order every turn
+    pub attacks: Vec<Attack>,
+}
+
+// Everything a boss can do on its turn. Cooldowns, telegraphs and lingers are all in turns, a
+// telegraph being how long the attack is shown before it hurts
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub enum Attack {
+    // whole rows and columns of the room
+    Lasers {
+        amount: usize,
+        cooldown: usize,
+        telegraph: usize,
+        linger: usize,
+    },
+    // dropped wherever the player is standing
+    Asteroids {
+        cooldown: usize,
+        telegraph: usize,
+        linger: usize,
+    },
+    // the boss slides to the wall the player is closest to behind a wide laser, then walks back
+    // to the middle and lets its guard down. The laser is shown where it starts before it fires
+    ColumnLaser { telegraph: usize },
+    StunWells {
+        cooldown: usize,
+        telegraph: usize,
+        linger: usize,
+    },
+    // walks after the player, and charges across the room when they line up with it
+    Chase {
+        move_delay: usize,
+        rush_cooldown: usize,
+        telegraph: usize,
+    },
+    // a safe spot shows up near the player, and once its countdown runs out the lights go out and
+    // anyone outside of it dies
+    Blackout { cooldown: usize, telegraph: usize },
+    // the boss can only be hurt after hitting the weak spots that show up on its edge enough times
+    WeakSpots { shield_hits: usize },
+    // a wave of enemies every time the room has been cleared and the cooldown is up
+    Reinforcements { wave: Wave, cooldown: usize },
+    // a wave of enemies guards the boss, which is exposed for a while each time a wave is
+    // cleared before calling in the next one. The first wave is waiting when the player walks in
+    Guards { wave: Wave, exposed: usize },
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(default, deny_unknown_fields)]
+// The enemies a boss calls in at once, spawned in this order
+pub struct Wave {
+    pub majors: usize,
+    pub chasers: usize,
+    pub bombers: usize,
+}
+
+impl Wave {
+    pub fn size(&self) -> usize {
+        self.majors + self.chasers + self.bombers
+    }
+}
+
+impl Default for BossScripts {
+    fn default() -> Self {
+        ron::from_str(DEFAULT_BOSS_SCRIPTS).expect("the built in bosses.ron is broken")
+    }
+}
+
+impl BossScripts {
+    // Falls back to the scripts the game was built with if bosses.ron is missing or broken
+    pub fn load(ctx: &Context) -> BossScripts {
+        let text = match Self::read(ctx) {
+            Some(text) => text,
+            None => {
+                println!(
+                    "Couldn't open {}, using the default boss scripts",
+                    BOSS_SCRIPTS_PATH
+                );
+                return BossScripts::default();
+            }
+        };
+        match Self::parse(&text) {
+            Ok(scripts) => scripts,
+            Err(errors) => {
+                println!(
+                    "{} is invalid, using the default boss scripts:",
+                    BOSS_SCRIPTS_PATH
+                );
+                for error in errors {
+                    println!("    {}", error);
+                }
+                BossScripts::default()
+            }
+        }
+    }
+
+    pub fn read(ctx: &Context) -> Option<String> {
+        let mut text = String::new();
+        ctx.fs
+            .open(BOSS_SCRIPTS_PATH)
+            .and_then(|mut file| Ok(file.read_to_string(&mut text)?))
+            .ok()?;
+        Some(text)
+    }
+
+    pub fn parse(text: &str) -> Result<BossScripts, Vec<String>> {
+        let scripts: BossScripts = ron::from_str(text).map_err(|e| vec![e.to_string()])?;
+        let errors = scripts.validate();
+        if errors.is_empty() {
+            Ok(scripts)
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn get(&self, script: &str) -> Option<&BossScript> {
+        self.0.get(script)
+    }
+
+    // Every boss room needs a script, and every script needs phases the boss can actually get to
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for script in BOSS_ROOM_SCRIPTS {
+            if !self.0.contains_key(script) {
+                errors.push(format!("there is no script called {}", script));
+            }
+        }
+        for (key, script) in &self.0 {
+            match script.phases.first() {
+                Some(phase) if phase.from_health == 100 => {}
+                Some(_) => errors.push(format!("{}'s first phase has to start at 100", key)),
+                None => errors.push(format!("{} needs at least one phase", key)),
+            }
+            for pair in script.phases.windows(2) {
+                if pair[1].from_health >= pair[0].from_health {
+                    errors.push(format!(
+                        "{}'s phases have to start at lower and lower health",
+                        key
+                    ));
+                }
+            }
+            for phase in &script.phases {
+                for attack in &phase.attacks {
+                    match attack {
+                        Attack::Lasers { amount: 0, .. } => {
+                            errors.push(format!("{} has lasers with an amount of 0", key))
+                        }
+                        Attack::Chase { move_delay: 0, .. } => {
+                            errors.push(format!("{} has a chase with a move_delay of 0", key))
+                        }
+                        Attack::WeakSpots { shield_hits: 0, .. } => {
+                            errors.push(format!("{} has weak spots with 0 shield_hits", key))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        errors
+    }
+}
+
+// Boss names show up in combat events, which only hold static strings. Each different name is
+// only ever leaked once
+pub fn intern(name: &str) -> &'static str {
+    static NAMES: Mutex<Option<HashSet<&'static str>>> = Mutex::new(None);
+    let mut names = NAMES.lock().unwrap();
+    let names = names.get_or_insert_with(HashSet::new);
+    if let Some(interned) = names.get(name) {
+        return interned;
+    }
+    let interned: &'static str = Box::leak(name.to_string().into_boxed_str());
+    names.insert(interned);
+    interned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The shipped scripts with one of them changed
+    fn with_script(key: &str, change: impl FnOnce(&mut BossScript)) -> BossScripts {
+        let mut scripts = BossScripts::default();
+        change(scripts.0.get_mut(key).unwrap());
+        scripts
+    }
+
+    #[test]
+    fn shipped_scripts_parse() {
+        let text = include_str!("../resources/bosses.ron");
+        assert_eq!(BossScripts::parse(text).err(), None);
+    }
+
+    #[test]
+    fn every_boss_room_needs_a_script() {
+        let mut scripts = BossScripts::default();
+        scripts.0.remove("chasing");
+        assert_eq!(
+            scripts.validate(),
+            vec!["there is no script called chasing"]
+        );
+        assert!(BossScripts::parse("{}").is_err());
+    }
+
+    #[test]
+    fn phases_have_to_be_reachable() {
+        let scripts = with_script("chasing", |script| script.phases.clear());
+        assert_eq!(scripts.validate(), vec!["chasing needs at least one phase"]);
+
+        let scripts = with_script("chasing", |script| script.phases[0].from_health = 90);
+        assert_eq!(
+            scripts.validate(),
+            vec!["chasing's first phase has to start at 100"]
+        );
+
+        let scripts = with_script("chasing", |script| {
+            let mut phase = script.phases[0].clone();
+            phase.from_health = 100;
+            script.phases.push(phase);
+        });
+        assert_eq!(
+            scripts.validate(),
+            vec!["chasing's phases have to start at lower and lower health"]
+        );
+    }
+
+    #[test]
+    fn attacks_that_would_do_nothing_are_rejected() {
+        let scripts = with_script("blackout", |script| {
+            script.phases[0].attacks = vec![
+                Attack::Lasers {
+                    amount: 0,
+                    cooldown: 0,
+                    telegraph: 2,
+                    linger: 3,
+                },
+                Attack::Chase {
+                    move_delay: 0,
+                    rush_cooldown: 20,
+                    telegraph: 2,
+                },
+                Attack::WeakSpots { shield_hits: 0 },
+            ]
+        });
+        assert_eq!(
+            scripts.validate(),
+            vec![
+                "blackout has lasers with an amount of 0",
+                "blackout has a chase with a move_delay of 0",
+                "blackout has weak spots with 0 shield_hits",
+            ]
+        );
+    }
+}
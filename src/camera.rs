@@ -0,0 +1,144 @@
+use crate::{
+    utils::Position,
+    world::{World, BOSS_ROOMS},
+    BOARD_SIZE, TILE_SIZE, UNIVERSAL_OFFSET, WORLD_SIZE,
+};
+use ggez::glam::Vec2;
+
+// Fraction of the remaining distance the camera covers every frame while scrolling
+const CAMERA_SMOOTHING: f32 = 0.2;
+// Once the camera is this close (in tiles) to where it should be it just snaps there
+const CAMERA_SNAP_DISTANCE: f32 = 0.01;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CameraMode {
+    // Shows one world at a time and jumps to the next one when the player crosses an edge
+    Room,
+    // Follows the player across the whole board, drawing the neighbouring worlds around them
+    Continuous,
+}
+
+// Decides which part of the board ends up on screen. Purely visual, the simulation still works
+// one world at a time no matter where the camera is
+pub struct Camera {
+    pub mode: CameraMode,
+    // top left corner of the view in board tiles, can sit between tiles while scrolling
+    x: f32,
+    y: f32,
+}
+
+impl Camera {
+    // Starts out in the top left world, the first update moves it to wherever the player is
+    pub fn new() -> Self {
+        Camera {
+            mode: CameraMode::Room,
+            x: 0.0,
+            y: 0.0,
+        }
+    }
+
+    pub fn toggle_mode(&mut self, world: &World) {
+        self.mode = match self.mode {
+            CameraMode::Room => CameraMode::Continuous,
+            CameraMode::Continuous => CameraMode::Room,
+        };
+        self.snap(world);
+    }
+
+    // Where the top left corner of the view should be. Boss rooms are always shown whole since
+    // the player is locked inside them anyways
+    fn target(&self, world: &World) -> (f32, f32) {
+        let room = (
+            (world.world_position.x * WORLD_SIZE.0 as usize) as f32,
+            (world.world_position.y * WORLD_SIZE.1 as usize) as f32,
+        );
+        if self.mode == CameraMode::Room || BOSS_ROOMS.contains(&world.world_position) {
+            return room;
+        }
+        let center = (
+            room.0 + world.player.pos.x as f32 + 0.5 - WORLD_SIZE.0 as f32 / 2.0,
+            room.1 + world.player.pos.y as f32 + 0.5 - WORLD_SIZE.1 as f32 / 2.0,
+        );
+        (
+            center.0.clamp(0.0, (BOARD_SIZE.0 - WORLD_SIZE.0) as f32),
+            center.1.clamp(0.0, (BOARD_SIZE.1 - WORLD_SIZE.1) as f32),
+        )
+    }
+
+    pub fn snap(&mut self, world: &World) {
+        (self.x, self.y) = self.target(world);
+    }
+
+    // Moves the camera a bit closer to where it should be, returns whether it moved at all so
+    // the caller knows to redraw
+    pub fn update(&mut self, world: &World) -> bool {
+        let target = self.target(world);
+        if self.mode == CameraMode::Room {
+            let moved = (self.x, self.y) != target;
+            (self.x, self.y) = target;
+            return moved;
+        }
+        let delta = (target.0 - self.x, target.1 - self.y);
+        if delta == (0.0, 0.0) {
+            return false;
+        }
+        if delta.0.abs() < CAMERA_SNAP_DISTANCE && delta.1.abs() < CAMERA_SNAP_DISTANCE {
+            (self.x, self.y) = target;
+        } else {
+            self.x += delta.0 * CAMERA_SMOOTHING;
+            self.y += delta.1 * CAMERA_SMOOTHING;
+        }
+        true
+    }
+
+    // Every world that is at least partly on screen
+    pub fn worlds_in_view(&self) -> Vec<Position> {
+        let first = (
+            self.x as usize / WORLD_SIZE.0 as usize,
+            self.y as usize / WORLD_SIZE.1 as usize,
+        );
+        let last = (
+            (self.x.ceil() as usize).div_ceil(WORLD_SIZE.0 as usize)
+                .min((BOARD_SIZE.0 / WORLD_SIZE.0) as usize - 1),
+            (self.y.ceil() as usize).div_ceil(WORLD_SIZE.1 as usize)
+                .min((BOARD_SIZE.1 / WORLD_SIZE.1) as usize - 1),
+        );
+        let mut worlds = Vec::new();
+        for y in first.1..=last.1 {
+            for x in first.0..=last.0 {
+                worlds.push(Position::new(x, y));
+            }
+        }
+        worlds
+    }
+
+    // Where the top left corner of the screen is relative to a world, in pixels. Handing this to
+    // the viewport lets a world be drawn using its own local tile positions, the same way a world
+    // is drawn without the camera
+    pub fn offset(&self, world_pos: Position) -> Vec2 {
+        Vec2::new(
+            (self.x - (world_pos.x * WORLD_SIZE.0 as usize) as f32) * TILE_SIZE.0 as f32,
+            (self.y - (world_pos.y * WORLD_SIZE.1 as usize) as f32) * TILE_SIZE.1 as f32,
+        )
+    }
+
+    // Turns a pixel on screen (after the viewport has undone any scaling) into a tile of the world
+    // the player is in, if it is one
+    pub fn screen_to_tile(&self, world: &World, x: f32, y: f32) -> Option<Position> {
+        let board_x = self.x + x / TILE_SIZE.0 as f32;
+        let board_y = self.y + y / TILE_SIZE.1 as f32 - UNIVERSAL_OFFSET as f32;
+        if y < (UNIVERSAL_OFFSET * TILE_SIZE.1) as f32 || board_x < 0.0 || board_y < 0.0 {
+            return None;
+        }
+        let (board_x, board_y) = (board_x as usize, board_y as usize);
+        if board_x / WORLD_SIZE.0 as usize != world.world_position.x
+            || board_y / WORLD_SIZE.1 as usize != world.world_position.y
+        {
+            return None;
+        }
+        Some(Position::new(
+            board_x % WORLD_SIZE.0 as usize,
+            board_y % WORLD_SIZE.1 as usize,
+        ))
+    }
+}
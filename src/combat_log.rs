@@ -0,0 +1,239 @@
+use crate::{tileset::TileBatch, utils::BossPhase, utils::Position, SCREEN_SIZE};
+use ggez::{
+    glam::Vec2,
+    graphics::{self, Canvas},
+    Context,
+};
+use std::collections::VecDeque;
+
+// How many entries the log remembers before it starts forgetting the oldest ones
+const COMBAT_LOG_LENGTH: usize = 200;
+// How many entries fit in the panel at once
+const COMBAT_LOG_LINES: usize = 12;
+const COMBAT_LOG_LINE_HEIGHT: f32 = 16.0;
+const COMBAT_LOG_WIDTH: f32 = 420.0;
+const COMBAT_LOG_PADDING: f32 = 6.0;
+const COMBAT_LOG_BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.75];
+const COMBAT_LOG_TEXT: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const COMBAT_LOG_PLAYER_HURT: [f32; 4] = [1.0, 0.45, 0.45, 1.0];
+const COMBAT_LOG_KILL: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+const COMBAT_LOG_INFO: [f32; 4] = [0.6, 0.8, 1.0, 1.0];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+// Anything that can deal or take damage, with the names that show up in the log
+pub enum Actor {
+    Player,
+    // one of the player's attacks, or something the player built
+    PlayerAttack(&'static str),
+    Enemy(&'static str),
+    Boss(&'static str),
+    // boss attacks and other things that hurt the player without being an enemy themselves,
+    // named the way they should read in a sentence, like "an asteroid"
+    Hazard(&'static str),
+}
+
+impl Actor {
+    fn describe(&self) -> String {
+        match self {
+            Actor::Player => String::from("you"),
+            Actor::PlayerAttack(name) => format!("your {}", name),
+            Actor::Enemy(name) => format!("a {}", name),
+            Actor::Boss(name) => format!("the {}", name),
+            Actor::Hazard(name) => name.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+// Everything the simulation reports while a turn resolves. The world collects these during a
+// turn and the log picks them up once it's over
+pub enum GameEvent {
+    Damaged {
+        source: Actor,
+        target: Actor,
+        amount: usize,
+    },
+    Killed {
+        source: Actor,
+        target: Actor,
+    },
+    AbilityUsed {
+        ability: &'static str,
+    },
+    Stunned {
+        source: Actor,
+        turns: usize,
+    },
+    RoomEntered {
+        world_pos: Position,
+    },
+    BossPhaseChanged {
+        boss: &'static str,
+        phase: BossPhase,
+    },
+    // an arena wave, counting from 1
+    WaveStarted {
+        wave: usize,
+    },
+    KeyFound {
+        boss_key: bool,
+    },
+    DoorUnlocked {
+        boss_door: bool,
+    },
+}
+
+impl GameEvent {
+    pub fn message(&self) -> String {
+        let message = match self {
+            GameEvent::Damaged {
+                source,
+                target,
+                amount,
+            } => format!(
+                "{} hit {} for {}",
+                source.describe(),
+                target.describe(),
+                amount
+            ),
+            GameEvent::Killed {
+                source,
+                target: Actor::Player,
+            } => format!("you were killed by {}", source.describe()),
+            GameEvent::Killed { source, target } => {
+                format!("{} killed {}", source.describe(), target.describe())
+            }
+            GameEvent::AbilityUsed { ability } => format!("you used {}", ability),
+            GameEvent::Stunned { source, turns } => {
+                format!("{} stunned you for {} turns", source.describe(), turns)
+            }
+            GameEvent::RoomEntered { world_pos } => {
+                format!("you entered room ({}, {})", world_pos.x, world_pos.y)
+            }
+            GameEvent::BossPhaseChanged { boss, phase } => match phase {
+                BossPhase::Attacking => format!("the {} is attacking", boss),
+                BossPhase::Vulnerable => format!("the {} is vulnerable", boss),
+                BossPhase::Blackout => format!("the {} put out the lights", boss),
+                BossPhase::Stage(stage) => format!("the {} enters phase {}", boss, stage),
+            },
+            GameEvent::WaveStarted { wave } => format!("wave {} is coming", wave),
+            GameEvent::KeyFound { boss_key: false } => String::from("you found a key"),
+            GameEvent::KeyFound { boss_key: true } => String::from("you found the boss key"),
+            GameEvent::DoorUnlocked { boss_door: false } => String::from("you unlocked a door"),
+            GameEvent::DoorUnlocked { boss_door: true } => {
+                String::from("you unlocked the boss door")
+            }
+        };
+        let mut chars = message.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => message,
+        }
+    }
+
+    fn color(&self) -> [f32; 4] {
+        match self {
+            GameEvent::Damaged {
+                target: Actor::Player,
+                ..
+            }
+            | GameEvent::Stunned { .. } => COMBAT_LOG_PLAYER_HURT,
+            GameEvent::Killed { .. } => COMBAT_LOG_KILL,
+            GameEvent::RoomEntered { .. }
+            | GameEvent::BossPhaseChanged { .. }
+            | GameEvent::WaveStarted { .. }
+            | GameEvent::KeyFound { .. }
+            | GameEvent::DoorUnlocked { .. } => COMBAT_LOG_INFO,
+            _ => COMBAT_LOG_TEXT,
+        }
+    }
+}
+
+// Everything that happened recently, turn by turn, and the panel that shows it
+pub struct CombatLog {
+    // the turn each event happened on, oldest first
+    entries: VecDeque<(usize, GameEvent)>,
+    turn: usize,
+    // how many entries back from the newest one the panel is scrolled
+    scroll: usize,
+    pub open: bool,
+}
+
+impl CombatLog {
+    pub fn new() -> Self {
+        CombatLog {
+            entries: VecDeque::new(),
+            turn: 0,
+            scroll: 0,
+            open: false,
+        }
+    }
+
+    // Adds everything that happened during a turn. Jumps back to the newest entries so the
+    // player sees what just happened
+    pub fn record_turn(&mut self, events: Vec<GameEvent>) {
+        self.turn += 1;
+        for event in events {
+            self.entries.push_back((self.turn, event));
+        }
+        while self.entries.len() > COMBAT_LOG_LENGTH {
+            self.entries.pop_front();
+        }
+        self.scroll = 0;
+    }
+
+    // Positive amounts scroll back towards older entries
+    pub fn scroll(&mut self, lines: i32) {
+        let max_scroll = self.entries.len().saturating_sub(COMBAT_LOG_LINES) as i32;
+        self.scroll = (self.scroll as i32 + lines).clamp(0, max_scroll) as usize;
+    }
+
+    // The most recent time the player was killed, if they were
+    pub fn cause_of_death(&self) -> Option<String> {
+        self.entries
+            .iter()
+            .rev()
+            .find_map(|(_, event)| match event {
+                GameEvent::Killed {
+                    target: Actor::Player,
+                    ..
+                } => Some(event.message()),
+                _ => None,
+            })
+    }
+
+    //Draws the panel in the bottom left corner of the world, newest entries at the bottom
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas) {
+        let height = COMBAT_LOG_LINES as f32 * COMBAT_LOG_LINE_HEIGHT + 2.0 * COMBAT_LOG_PADDING;
+        let top = SCREEN_SIZE.1 - height - COMBAT_LOG_PADDING;
+        let mut background = TileBatch::new(ctx, None);
+        background.push_rect(
+            graphics::Rect::new(COMBAT_LOG_PADDING, top, COMBAT_LOG_WIDTH, height),
+            COMBAT_LOG_BACKGROUND,
+        );
+        background.finish(canvas);
+
+        let end = self.entries.len() - self.scroll.min(self.entries.len());
+        let start = end.saturating_sub(COMBAT_LOG_LINES);
+        for (line, (turn, event)) in self.entries.range(start..end).enumerate() {
+            canvas.draw(
+                &*(graphics::Text::new(format!("{:>4}  {}", turn, event.message())).set_scale(14.)),
+                graphics::DrawParam::from(Vec2::new(
+                    2.0 * COMBAT_LOG_PADDING,
+                    top + COMBAT_LOG_PADDING + line as f32 * COMBAT_LOG_LINE_HEIGHT,
+                ))
+                .color(event.color()),
+            );
+        }
+        if self.scroll > 0 {
+            canvas.draw(
+                &*(graphics::Text::new(format!("[{} newer]", self.scroll)).set_scale(12.)),
+                graphics::DrawParam::from(Vec2::new(
+                    COMBAT_LOG_WIDTH - 70.0,
+                    top + height - COMBAT_LOG_LINE_HEIGHT,
+                ))
+                .color(COMBAT_LOG_INFO),
+            );
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{utils::Position, TILE_SIZE, UNIVERSAL_OFFSET};
+use ggez::{
+    glam::Vec2,
+    graphics::{self, Canvas},
+};
+
+// How many frames a damage number stays on screen
+const DAMAGE_NUMBER_FRAMES: u32 = 40;
+// How far a damage number floats up over its whole life, in pixels
+const DAMAGE_NUMBER_RISE: f32 = 20.0;
+const DAMAGE_NUMBER_COLOR: [f32; 3] = [1.0, 0.9, 0.2];
+const BOSS_DAMAGE_NUMBER_COLOR: [f32; 3] = [1.0, 0.4, 0.1];
+
+#[derive(Debug, Clone, PartialEq)]
+// A number that pops up over something that got hit and floats up while it fades away. Purely
+// visual, so these are never saved
+pub struct DamageNumber {
+    // where the number starts, in tiles of the world it is in. Can sit between tiles so it ends up
+    // centered over big enemies
+    pos: Vec2,
+    pub world_pos: Position,
+    amount: usize,
+    frames_left: u32,
+    is_boss: bool,
+}
+
+impl DamageNumber {
+    pub fn new(pos: Vec2, world_pos: Position, amount: usize, is_boss: bool) -> Self {
+        DamageNumber {
+            pos,
+            world_pos,
+            amount,
+            frames_left: DAMAGE_NUMBER_FRAMES,
+            is_boss,
+        }
+    }
+
+    // The middle of a group of tiles, so a 3x3 enemy gets its number over its center
+    pub fn center(tiles: &[Position]) -> Vec2 {
+        let sum = tiles.iter().fold(Vec2::ZERO, |sum, pos| {
+            sum + Vec2::new(pos.x as f32, pos.y as f32)
+        });
+        sum / tiles.len().max(1) as f32
+    }
+
+    // The tile the number started over, used to hide numbers for things the player can't see
+    pub fn tile(&self) -> Position {
+        Position::new(self.pos.x.round() as usize, self.pos.y.round() as usize)
+    }
+
+    // Ages every number by a frame and drops the ones that have faded out. Returns whether
+    // anything changed and needs to be redrawn
+    pub fn update(numbers: &mut Vec<DamageNumber>) -> bool {
+        if numbers.is_empty() {
+            return false;
+        }
+        for number in numbers.iter_mut() {
+            number.frames_left = number.frames_left.saturating_sub(1);
+        }
+        numbers.retain(|number| number.frames_left > 0);
+        true
+    }
+
+    // Has to be drawn with the screen coordinates of the world the number is in
+    pub fn draw(&self, canvas: &mut Canvas) {
+        let life = self.frames_left as f32 / DAMAGE_NUMBER_FRAMES as f32;
+        let [r, g, b] = if self.is_boss {
+            BOSS_DAMAGE_NUMBER_COLOR
+        } else {
+            DAMAGE_NUMBER_COLOR
+        };
+        let dest = Vec2::new(
+            self.pos.x * TILE_SIZE.0 as f32,
+            (self.pos.y + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32
+                - DAMAGE_NUMBER_RISE * (1.0 - life),
+        );
+        canvas.draw(
+            &*(graphics::Text::new(format!("{}", self.amount)).set_scale(14.)),
+            graphics::DrawParam::from(dest).color([r, g, b, life]),
+        );
+    }
+}
@@ -0,0 +1,103 @@
+// How much each difficulty scales the game's balance by, relative to Normal
+struct Scales {
+    enemy_count: f32,
+    // damage enemies and bosses deal to the player
+    damage: f32,
+    boss_health: f32,
+    // how long the player gets warned before an attack lands
+    telegraph: f32,
+    // energy the player gets back for kills
+    energy_return: f32,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+// Picked on the title screen when starting a new game. Lives in the world so saves keep it
+pub enum Difficulty {
+    Story,
+    #[default]
+    Normal,
+    Hard,
+    Nightmare,
+}
+
+impl Difficulty {
+    fn scales(self) -> Scales {
+        match self {
+            Difficulty::Story => Scales {
+                enemy_count: 0.6,
+                damage: 0.5,
+                boss_health: 0.6,
+                telegraph: 1.5,
+                energy_return: 1.5,
+            },
+            Difficulty::Normal => Scales {
+                enemy_count: 1.0,
+                damage: 1.0,
+                boss_health: 1.0,
+                telegraph: 1.0,
+                energy_return: 1.0,
+            },
+            Difficulty::Hard => Scales {
+                enemy_count: 1.3,
+                damage: 1.5,
+                boss_health: 1.3,
+                telegraph: 0.8,
+                energy_return: 0.8,
+            },
+            Difficulty::Nightmare => Scales {
+                enemy_count: 1.6,
+                damage: 2.0,
+                boss_health: 1.6,
+                telegraph: 0.6,
+                energy_return: 0.6,
+            },
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Story => "Story",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Nightmare => "Nightmare",
+        }
+    }
+
+    // The one after this one, wrapping back around to Story, for cycling through on the title
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Story => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Nightmare,
+            Difficulty::Nightmare => Difficulty::Story,
+        }
+    }
+
+    pub fn enemy_count(self, count: usize) -> usize {
+        scale(count, self.scales().enemy_count)
+    }
+
+    pub fn damage(self, damage: usize) -> usize {
+        scale(damage, self.scales().damage)
+    }
+
+    pub fn boss_health(self, health: usize) -> usize {
+        scale(health, self.scales().boss_health)
+    }
+
+    pub fn telegraph(self, turns: usize) -> usize {
+        scale(turns, self.scales().telegraph)
+    }
+
+    pub fn energy_return(self, energy: usize) -> usize {
+        scale(energy, self.scales().energy_return)
+    }
+}
+
+// Rounds to the nearest whole number, but never scales something down to nothing (or up from it)
+fn scale(value: usize, factor: f32) -> usize {
+    if value == 0 {
+        return 0;
+    }
+    ((value as f32 * factor).round() as usize).max(1)
+}
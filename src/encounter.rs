@@ -0,0 +1,19 @@
+use crate::{direction::Direction, utils::Position};
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Default)]
+// Everything a boss fight leaves lying around its room. Each boss room has its own, so a fight
+// picks up where it was left when the player comes back, and is wiped when the boss dies
+pub struct Encounter {
+    // position, turns until it fires, duration left
+    pub lasers: Vec<(Position, usize, usize)>,
+    // position, turns until it lands, duration left
+    pub asteroids: Vec<(Position, usize, usize)>,
+    // position, the direction the laser will move, turns until it fires
+    pub column_laser: Option<(Position, Direction, usize)>,
+    // center position, size of the safe spot, turns until the blackout
+    pub safe_spot: Option<(Position, usize, usize)>,
+    pub vulnerable_spot: Option<Position>,
+    pub in_blackout: bool,
+    // position, turns until it stuns, size, duration left, whether the player is in it
+    pub stun_wells: Vec<(Position, usize, usize, usize, bool)>,
+}
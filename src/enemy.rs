@@ -1,7 +1,12 @@
 use crate::{
+    balance::EnemyBalance,
+    boss_script::Wave,
+    combat_log::Actor,
     direction::Direction,
     entity::Entity,
     projectile::Projectile,
+    random,
+    structure::Structure,
     tile::{self, PROJECTILE_PLAYER},
     utils::Position,
     utils::Boss,
@@ -9,49 +14,33 @@ use crate::{
     BOARD_SIZE, TILE_SIZE, UNIVERSAL_OFFSET, WORLD_SIZE,
 };
 use ggez::graphics::{self, Canvas};
+use rand_chacha::ChaCha8Rng;
 use std::{cmp::max, collections::HashMap, collections::LinkedList};
 
-const CHASING_ENEMY_HEALTH: usize = 50;
-const BOMBER_ENEMY_HEALTH: usize = 25;
-const KNIGHT_ENEMY_HEALTH: usize = 100;
-const SHOOTER_ENEMY_HEALTH: usize = 25;
-const MAJOR_ENEMY_HEALTH: usize = 200;
-const MINOR_BOSS_HEALTH: usize = 1000;
-const MAJOR_BOSS_HEALTH: usize = 2000;
 
-const PERMISSIBLE_TILES: [[f32; 4]; 4] = [
+const PERMISSIBLE_TILES: [[f32; 4]; 9] = [
     tile::GRASS,
+    tile::MUD,
+    tile::ICE,
+    tile::BRIDGE,
+    tile::FORD,
     tile::PROJECTILE_PLAYER,
     tile::LIGHTNING_SECONDARY,
     tile::LIGHTNING_INITIAL,
+    tile::SPIKE_TRAP,
 ];
-const PERMISSIBLE_TILES_DODGING: [[f32; 4]; 3] = [
+const PERMISSIBLE_TILES_DODGING: [[f32; 4]; 7] = [
     tile::GRASS,
+    tile::MUD,
+    tile::ICE,
+    tile::BRIDGE,
+    tile::FORD,
     tile::LIGHTNING_INITIAL,
     tile::LIGHTNING_SECONDARY,
 ];
 const PERMISSIBLE_TILES_BOSS: [[f32; 4]; 0] = [];
-
-const CHASING_ENEMY_SPEED: usize = 1;
-const BOMBER_ENEMY_SPEED: usize = 2;
-const KNIGHT_ENEMY_SPEED: usize = 1;
-const SHOOTER_ENEMY_SPEED: usize = 1;
-const MAJOR_ENEMY_SPEED: usize = 1;
-const MINOR_BOSS_SPEED: usize = 1;
-const MAJOR_BOSS_SPEED: usize = 1;
-
-const CHASING_ENEMY_ENERGY_RETURN: usize = 3;
-const BOMBER_ENEMY_ENERGY_RETURN: usize = 5;
-const KNIGHT_ENEMY_ENERGY_RETURN: usize = 12;
-const SHOOTER_ENEMY_ENERGY_RETURN: usize = 7;
-const MAJOR_ENEMY_ENERGY_RETURN: usize = 25;
-const MINOR_BOSS_ENERGY_RETURN: usize = 100;
-const MAJOR_BOSS_ENERGY_RETURN: usize = 100;
-
-const BOMBER_DAMAGE: usize = 20;
-const CHASER_DAMAGE: usize = 3;
-const MAJOR_DAMAGE: usize = 10;
-const MAJOR_BOSS_DAMAGE: usize = 10;
+// Random spots tried for each enemy of a wave before giving up on it
+const SPAWN_TRIES: usize = 200;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
 // This is basically the same as the enemy for now, but I am just testing an enemy system
@@ -78,6 +67,18 @@ pub struct Enemy {
     // Stores enemy health: for enemy death and such
     health: usize,
 
+    // What the health started at, for the health bars. Old saves don't have it, see max_health()
+    #[serde(default)]
+    max_health: usize,
+
+    // Damage taken since the world last turned it into damage numbers, and who dealt it
+    #[serde(skip)]
+    pub recent_hits: Vec<(Actor, usize)>,
+
+    // Whatever brought the health down to 0, so the log can say who killed it
+    #[serde(skip)]
+    pub killed_by: Option<Actor>,
+
     pub world_pos: Position,
 
     resistance: f32,
@@ -87,6 +88,10 @@ pub struct Enemy {
     is_boss: bool,
 
     pub movement_cooldown: bool,
+
+    // Waded into water or a ford, so the next turn only pulls it free. Only majors wade
+    #[serde(default)]
+    pub stuck: bool,
 }
 
 impl Enemy {
@@ -107,46 +112,50 @@ impl Enemy {
             color,
             attack_damage,
             health,
+            max_health: health,
+            recent_hits: Vec::new(),
+            killed_by: None,
             resistance: 1.0,
             world_pos,
             can_dodge_projectiles,
             is_boss: boss,
             movement_cooldown: false,
+            stuck: false,
         };
         temp
     }
 
-    pub fn bomber(x: usize, y: usize, world_pos: Position) -> Self {
+    pub fn bomber(x: usize, y: usize, world_pos: Position, balance: &EnemyBalance) -> Self {
         let mut pos = Vec::new();
         pos.push(Position::new(x, y));
         Enemy::new(
             pos,
-            BOMBER_ENEMY_SPEED,
+            balance.bomber.speed,
             tile::BOMBER_ENEMY,
             world_pos,
-            BOMBER_ENEMY_HEALTH,
+            balance.bomber.health,
             true,
             false,
-            BOMBER_DAMAGE
+            balance.bomber.damage
         )
     }
 
-    pub fn chasing(x: usize, y: usize, world_pos: Position) -> Self {
+    pub fn chasing(x: usize, y: usize, world_pos: Position, balance: &EnemyBalance) -> Self {
         let mut pos = Vec::new();
         pos.push(Position::new(x, y));
         Enemy::new(
             pos,
-            CHASING_ENEMY_SPEED,
+            balance.chaser.speed,
             tile::CHASING_ENEMY,
             world_pos,
-            CHASING_ENEMY_HEALTH,
+            balance.chaser.health,
             true,
             false,
-            CHASER_DAMAGE,
+            balance.chaser.damage,
         )
     }
 
-    pub fn major_enemy(x: usize, y: usize, world_pos: Position) -> Self {
+    pub fn major_enemy(x: usize, y: usize, world_pos: Position, balance: &EnemyBalance) -> Self {
         let mut pos = Vec::new();
         for i in 0..3 {
             for j in 0..3 {
@@ -155,58 +164,106 @@ impl Enemy {
         }
         Enemy::new(
             pos,
-            MAJOR_ENEMY_SPEED,
+            balance.major.speed,
             tile::MAJOR_ENEMY,
             world_pos,
-            MAJOR_ENEMY_HEALTH,
+            balance.major.health,
             true,
             false,
-           MAJOR_DAMAGE,
+            balance.major.damage,
         )
     }
 
-    pub fn shooting_enemy(x: usize, y: usize, world_pos: Position) -> Self {
+    // Puts every enemy of a wave somewhere random in the player's world, anywhere blocked doesn't
+    // rule out. Enemies that can't find room after SPAWN_TRIES spots are left out of the wave, so
+    // a wave bigger than the room can hold doesn't hang the game
+    pub fn spawn_wave(
+        world: &mut World,
+        rng: &mut ChaCha8Rng,
+        wave: &Wave,
+        blocked: impl Fn(&World, Position) -> bool,
+    ) {
+        let world_pos = world.world_position;
+        for i in 0..wave.size() {
+            let spot = (0..SPAWN_TRIES).find_map(|_| {
+                let x = random::rand_range(rng, 3, WORLD_SIZE.0 - 5) as usize;
+                let y = random::rand_range(rng, 3, WORLD_SIZE.1 - 5) as usize;
+                let pos = Position::new(x, y);
+                (!blocked(world, pos)).then_some(pos)
+            });
+            let pos = match spot {
+                Some(pos) => pos,
+                None => continue,
+            };
+            if i < wave.majors {
+                world.enemies_map[world_pos.y][world_pos.x].push(Enemy::major_enemy(
+                    pos.x,
+                    pos.y,
+                    world_pos, &world.balance.enemies),);
+                for h in 0..3 {
+                    for j in 0..3 {
+                        world.entity_map[world_pos.y][world_pos.x].insert(
+                            Position::new(pos.x + h, pos.y + j), (tile::MAJOR_ENEMY, Entity::Enemy));
+                    }
+                }
+            } else if i < wave.majors + wave.chasers {
+                world.enemies_map[world_pos.y][world_pos.x].push(Enemy::chasing(
+                    pos.x,
+                    pos.y,
+                    world_pos, &world.balance.enemies),);
+                world.entity_map[world_pos.y][world_pos.x].insert(pos, (tile::CHASING_ENEMY, Entity::Enemy));
+            } else {
+                world.enemies_map[world_pos.y][world_pos.x].push(Enemy::bomber(
+                    pos.x,
+                    pos.y,
+                    world_pos, &world.balance.enemies),);
+                world.entity_map[world_pos.y][world_pos.x].insert(pos, (tile::BOMBER_ENEMY, Entity::Enemy));
+            }
+        }
+    }
+
+    pub fn shooting_enemy(x: usize, y: usize, world_pos: Position, balance: &EnemyBalance) -> Self {
         let mut pos = Vec::new();
         pos.push(Position::new(x, y));
         Enemy::new(
             pos,
-            SHOOTER_ENEMY_SPEED,
+            balance.shooter.speed,
             tile::SHOOTER_ENEMY,
             world_pos,
-            SHOOTER_ENEMY_HEALTH,
+            balance.shooter.health,
             true,
             false,
             0,
         )
     }
 
-    pub fn knight(x: usize, y: usize, world_pos: Position) -> Self {
+    pub fn knight(x: usize, y: usize, world_pos: Position, balance: &EnemyBalance) -> Self {
         let mut pos = Vec::new();
         pos.push(Position::new(x, y));
         Enemy::new(
             pos,
-            KNIGHT_ENEMY_SPEED,
+            balance.knight.speed,
             tile::KNIGHT_ENEMY,
             world_pos,
-            KNIGHT_ENEMY_HEALTH,
+            balance.knight.health,
             true,
             false,
             0,
         )
     }
 
-    pub fn major_boss(x: usize, y: usize, world_pos: Position) -> Self {
+    pub fn major_boss(x: usize, y: usize, world_pos: Position, balance: &EnemyBalance) -> Self {
         let mut pos = Vec::new();
         pos.push(Position::new(x, y));
         Enemy::new(
             pos,
-            MAJOR_BOSS_SPEED,
+            balance.major_boss.speed,
             tile::MAJOR_BOSS,
             world_pos,
-            MAJOR_BOSS_HEALTH,
+            balance.major_boss.health,
             true,
             false,
-            MAJOR_BOSS_DAMAGE
+            balance.major_boss.damage
         )
     }
 
@@ -214,12 +271,36 @@ impl Enemy {
         self.health
     }
 
-    pub fn damage(&mut self, damage: usize) {
+    pub fn max_health(&self) -> usize {
+        self.max_health.max(self.health)
+    }
+
+    // What the log calls this enemy. Shooters and knights look the same as chasers, so they
+    // can't be told apart either
+    pub fn name(&self) -> &'static str {
+        match self.color {
+            tile::BOMBER_ENEMY | tile::BOMBER_ENEMY_ACTIVATED | tile::BOMBER_ENEMY_DEACTIVATED => {
+                "bomber"
+            }
+            tile::MAJOR_ENEMY => "major enemy",
+            _ => "chaser",
+        }
+    }
+
+    pub fn damage(&mut self, damage: usize, source: Actor) {
         // potentially modify the damage done with the multiplier
+        let was_alive = self.health > 0;
         self.health = max(0, self.health as i32 - damage as i32) as usize;
+        self.recent_hits.push((source, damage));
+        if was_alive && self.health == 0 {
+            self.killed_by = Some(source);
+        }
     }
 
     pub fn update(world: &mut World) {
+        world.update_vision();
+        // before anything dies, so the hit that killed it still shows up
+        world.collect_hits();
         for index in
             (0..world.enemies_map[world.world_position.y][world.world_position.x].len()).rev()
         {
@@ -230,30 +311,43 @@ impl Enemy {
             {
                 Enemy::kill(world, index);
             } else {
-                if world.player.is_visible() {
+                if world.player.is_visible() && Self::can_see_player(index, world) {
                     Self::move_enemy(index, world);
                 }
             }
         }
     }
 
+    // Enemies only go after the player when the player could see them back, so hiding behind
+    // mountains and walls (or in a blackout) shakes them off. Bombers that have already started
+    // exploding finish regardless
+    fn can_see_player(index: usize, world: &World) -> bool {
+        let enemy = &world.enemies_map[world.world_position.y][world.world_position.x][index];
+        enemy.color == tile::BOMBER_ENEMY_ACTIVATED
+            || enemy.color == tile::BOMBER_ENEMY_DEACTIVATED
+            || enemy.pos.iter().any(|pos| world.visible_tiles.contains(pos))
+    }
+
     pub fn kill(world: &mut World, index: usize) {
         // for now all it does is remove the tile on the world "board"
         let delta =
             match world.enemies_map[world.world_position.y][world.world_position.x][index].color {
-                tile::CHASING_ENEMY => CHASING_ENEMY_ENERGY_RETURN,
-                tile::BOMBER_ENEMY => BOMBER_ENEMY_ENERGY_RETURN,
-                tile::BOMBER_ENEMY_ACTIVATED => BOMBER_ENEMY_ENERGY_RETURN,
-                tile::BOMBER_ENEMY_DEACTIVATED => BOMBER_ENEMY_ENERGY_RETURN,
-                tile::MAJOR_ENEMY => MAJOR_ENEMY_ENERGY_RETURN,
-                tile::SHOOTER_ENEMY => SHOOTER_ENEMY_ENERGY_RETURN,
-                tile::KNIGHT_ENEMY => KNIGHT_ENEMY_ENERGY_RETURN,
-                tile::LASER_BOSS => MINOR_BOSS_ENERGY_RETURN,
-                tile::MAJOR_BOSS => MAJOR_BOSS_ENERGY_RETURN,
+                tile::CHASING_ENEMY => world.balance.enemies.chaser.energy_return,
+                tile::BOMBER_ENEMY => world.balance.enemies.bomber.energy_return,
+                tile::BOMBER_ENEMY_ACTIVATED => world.balance.enemies.bomber.energy_return,
+                tile::BOMBER_ENEMY_DEACTIVATED => world.balance.enemies.bomber.energy_return,
+                tile::MAJOR_ENEMY => world.balance.enemies.major.energy_return,
+                tile::SHOOTER_ENEMY => world.balance.enemies.shooter.energy_return,
+                tile::KNIGHT_ENEMY => world.balance.enemies.knight.energy_return,
+                tile::LASER_BOSS => world.balance.enemies.minor_boss.energy_return,
+                tile::MAJOR_BOSS => world.balance.enemies.major_boss.energy_return,
                 tile::BOMBER_ENEMY_ACTIVATED => 0,
                 _ => unreachable!("Cannot be anything other than the enemy tiles"),
-            } as i32;
-        world.player.change_energy(delta);
+            };
+        world.player.change_energy(
+            world.difficulty.energy_return(delta) as i32,
+            world.balance.player.max_energy,
+        );
         let enemy = &mut world.enemies_map[world.world_position.y][world.world_position.x][index]; 
         let pos = &mut enemy.pos;
         for tile in pos {
@@ -334,7 +428,7 @@ impl Enemy {
             }
         }
         if new_pos.contains(&world.player.pos) {
-            world.player.damage(world.enemies_map[world.world_position.y][world.world_position.x][index].attack_damage);
+            world.player.damage(world.difficulty.damage(world.enemies_map[world.world_position.y][world.world_position.x][index].attack_damage), Actor::Enemy(world.enemies_map[world.world_position.y][world.world_position.x][index].name()));
         } else {
             let mut index_proj: i32 = 0;
             for _ in 0..world.projectiles.len() {
@@ -343,7 +437,7 @@ impl Enemy {
                         && world.enemies_map[world.world_position.y][world.world_position.x][index].world_pos
                             == world.projectiles[index_proj as usize].world_pos
                     {
-                        world.enemies_map[world.world_position.y][world.world_position.x][index].damage(world.projectiles[index_proj as usize].damage);
+                        world.enemies_map[world.world_position.y][world.world_position.x][index].damage(world.projectiles[index_proj as usize].damage, Actor::PlayerAttack(world.projectiles[index_proj as usize].attack_name()));
                         Projectile::kill(index_proj as usize, world);
                         index_proj -= 1;
                     }
@@ -382,7 +476,7 @@ impl Enemy {
                         return;
                     }
                     if new_pos == world.player.pos {
-                        world.player.damage(world.enemies_map[world.world_position.y][world.world_position.x][index].attack_damage);
+                        world.player.damage(world.difficulty.damage(world.enemies_map[world.world_position.y][world.world_position.x][index].attack_damage), Actor::Enemy(world.enemies_map[world.world_position.y][world.world_position.x][index].name()));
                     } else {
                         let mut index_proj: i32 = 0;
                         for _ in 0..world.projectiles.len() {
@@ -396,7 +490,7 @@ impl Enemy {
                                         == world.projectiles[index_proj as usize].world_pos
                                 {
                                     world.enemies_map[world.world_position.y][world.world_position.x][index]
-                                        .damage(world.projectiles[index_proj as usize].damage);
+                                        .damage(world.projectiles[index_proj as usize].damage, Actor::PlayerAttack(world.projectiles[index_proj as usize].attack_name()));
                                     Projectile::kill(index_proj as usize, world);
                                     index_proj -= 1;
                                 }
@@ -424,7 +518,7 @@ impl Enemy {
                         return;
                     }
                     if new_pos == world.player.pos {
-                        world.player.damage(world.enemies_map[world.world_position.y][world.world_position.x][index].attack_damage);
+                        world.player.damage(world.difficulty.damage(world.enemies_map[world.world_position.y][world.world_position.x][index].attack_damage), Actor::Enemy(world.enemies_map[world.world_position.y][world.world_position.x][index].name()));
                     } else {
                         let mut index_proj: i32 = 0;
                         for _ in 0..world.projectiles.len() {
@@ -436,7 +530,7 @@ impl Enemy {
                                         == world.projectiles[index_proj as usize].world_pos
                                 {
                                     world.enemies_map[world.world_position.y][world.world_position.x][index]
-                                        .damage(world.projectiles[index_proj as usize].damage);
+                                        .damage(world.projectiles[index_proj as usize].damage, Actor::PlayerAttack(world.projectiles[index_proj as usize].attack_name()));
                                     Projectile::kill(index_proj as usize, world);
                                     index_proj -= 1;
                                 }
@@ -457,9 +551,12 @@ impl Enemy {
                         &mut world.entity_map[world.world_position.y][world.world_position.x];
                     curr_world.insert(cur_pos, (world.enemies_map[world.world_position.y][world.world_position.x][index].color, Entity::Enemy));
                     if Self::player_within_spaces(&cur_pos, &world, 2) {
-                        world.player.damage(world.enemies_map[world.world_position.y][world.world_position.x][index].attack_damage);
+                        world.player.damage(world.difficulty.damage(world.enemies_map[world.world_position.y][world.world_position.x][index].attack_damage), Actor::Hazard("a bomber's blast"));
                     }
                     Self::create_bomber_explosion(index, world);
+                    Structure::damage_where(world, world.world_position, world.balance.enemies.bomber_structure_damage, |pos| {
+                        (pos.x as i16 - cur_pos.x as i16).abs() + (pos.y as i16 - cur_pos.y as i16).abs() <= 2
+                    });
                 } else if Self::match_color(
                     &world.enemies_map[world.world_position.y][world.world_position.x][index].color,
                     &tile::BOMBER_ENEMY_DEACTIVATED,
@@ -473,6 +570,10 @@ impl Enemy {
                     &world.enemies_map[world.world_position.y][world.world_position.x][index].color,
                     &tile::MAJOR_ENEMY
                 ) {
+                    if world.enemies_map[world.world_position.y][world.world_position.x][index].stuck {
+                        world.enemies_map[world.world_position.y][world.world_position.x][index].stuck = false;
+                        return;
+                    }
                     let del_x = world.enemies_map[world.world_position.y][world.world_position.x][index].pos[4].x as i16 - world.player.pos.x as i16;
                     let del_y = world.enemies_map[world.world_position.y][world.world_position.x][index].pos[4].y as i16 - world.player.pos.y as i16;
 
@@ -501,7 +602,12 @@ impl Enemy {
                             || new_y < 0 || new_y >= WORLD_SIZE.1 {
                             return;
                         } else if new_pos == world.player.pos {
-                            world.player.damage(world.enemies_map[world.world_position.y][world.world_position.x][index].attack_damage);
+                            world.player.damage(world.difficulty.damage(world.enemies_map[world.world_position.y][world.world_position.x][index].attack_damage), Actor::Enemy(world.enemies_map[world.world_position.y][world.world_position.x][index].name()));
+                            return;
+                        } else if Structure::get(world, new_pos, world.world_position).is_some() {
+                            // majors smash through structures instead of walking around them
+                            let damage = world.enemies_map[world.world_position.y][world.world_position.x][index].attack_damage;
+                            Structure::damage_at(world, new_pos, world.world_position, damage);
                             return;
                         }
 
@@ -515,7 +621,7 @@ impl Enemy {
                                         == world.projectiles[index_proj as usize].world_pos
                                 {
                                     world.enemies_map[world.world_position.y][world.world_position.x][index]
-                                        .damage(world.projectiles[index_proj as usize].damage);
+                                        .damage(world.projectiles[index_proj as usize].damage, Actor::PlayerAttack(world.projectiles[index_proj as usize].attack_name()));
                                     Projectile::kill(index_proj as usize, world);
                                     index_proj -= 1;
                                 }
@@ -547,6 +653,13 @@ impl Enemy {
                             world.enemies_map[world.world_position.y][world.world_position.x][index].pos[positions.len()-i-1] = new_pos;
                         }
                     }
+                    // majors wade straight into rivers, but like the player in a ford they lose a turn
+                    // getting out
+                    let terrain = &world.terrain_map[world.world_position.y][world.world_position.x];
+                    let enemy = &mut world.enemies_map[world.world_position.y][world.world_position.x][index];
+                    enemy.stuck = enemy.pos.iter().any(|pos| {
+                        matches!(terrain.get(pos).copied(), Some(tile::WATER) | Some(tile::FORD))
+                    });
                 } else {
                     break;
                 }
@@ -675,17 +788,18 @@ impl Enemy {
             let info = terrain_map[position_info.1.y][position_info.1.x].get(&position_info.0);
             if let Some(info_under) = info {
                 if can_dodge_projectiles {
-                    if PERMISSIBLE_TILES_DODGING.contains(&info_under) {
-                        return true;
+                    if !PERMISSIBLE_TILES_DODGING.contains(&info_under) {
+                        return false;
                     }
                 } else {
-                    if PERMISSIBLE_TILES.contains(&info_under) {
-                        return true;
+                    if !PERMISSIBLE_TILES.contains(&info_under) {
+                        return false;
                     }
                 }
             }
-            return false;
-        } else if entity_map[position_info.1.y][position_info.1.x].contains_key(&position_info.0) {
+        }
+        // terrain like mud, ice and bridges can be walked on, as long as nobody else is standing there
+        if entity_map[position_info.1.y][position_info.1.x].contains_key(&position_info.0) {
             let info = entity_map[position_info.1.y][position_info.1.x].get(&position_info.0);
             if let Some(info_under) = info {
                 if can_dodge_projectiles {
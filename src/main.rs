@@ -3,24 +3,45 @@ use std::env;
 use std::path;
 use std::fs::{OpenOptions, self};
 
+mod balance;
+mod biome;
+mod boss;
+mod boss_script;
+mod camera;
+mod combat_log;
+mod damage_number;
+mod difficulty;
 mod direction;
+mod encounter;
 mod enemy;
 mod entity;
+mod mode;
 mod player;
 mod projectile;
 mod random;
+mod render;
+mod river;
 mod state;
+mod structure;
+mod telegraph;
+mod terrain;
 mod tile;
+mod tileset;
+mod undo;
 mod utils;
+mod viewport;
+mod vision;
+mod wing;
 mod world;
+mod worldgen;
 
 use crate::state::State;
 
 // Constants that determine tile size and world size, where the world is a 2 dimensional array of
 // tiles
 
-//Offset to leave extra space on top of screen for health/energy indicators
-pub const UNIVERSAL_OFFSET: i16 = 5;
+//Offset to leave extra space on top of screen for health/energy indicators and the ability bar
+pub const UNIVERSAL_OFFSET: i16 = 8;
 
 // Define the world size which is (width, height)
 pub const WORLD_SIZE: (i16, i16) = (50, 50);
@@ -38,22 +59,32 @@ pub const SCREEN_SIZE: (f32, f32) = (
 );
 
 fn main() -> GameResult {
-    let resource_dir = if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
-        let mut path = path::PathBuf::from(manifest_dir);
-        path.push("songs");
-        path
-    } else {
-        path::PathBuf::from("./songs")
+    // Folders next to Cargo.toml when run through cargo, otherwise in the working directory
+    let resource_dir = |name: &str| {
+        if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
+            let mut path = path::PathBuf::from(manifest_dir);
+            path.push(name);
+            path
+        } else {
+            path::PathBuf::from(format!("./{}", name))
+        }
     };
     // Here we use a ContextBuilder to setup metadata about our game. First the title and author
     let (mut ctx, events_loop) = ggez::ContextBuilder::new("Rust Game", "Ishan Kar")
         // Next we set up the window. This title will be displayed in the title bar of the window.
         .window_setup(ggez::conf::WindowSetup::default().title("RUST!!"))
-        // Now we get to set the size of the window, which we use our SCREEN_SIZE constant from earlier to help with
-        .window_mode(ggez::conf::WindowMode::default().dimensions(SCREEN_SIZE.0, SCREEN_SIZE.1))
+        // Now we get to set the size of the window, which we use our SCREEN_SIZE constant from earlier to help with.
+        // The window can be resized freely, the game gets scaled to fit it
+        .window_mode(
+            ggez::conf::WindowMode::default()
+                .dimensions(SCREEN_SIZE.0, SCREEN_SIZE.1)
+                .resizable(true),
+        )
         // And finally we attempt to build the context and create the window. If it fails, we panic with the message
         // "Failed to build ggez context"
-        .add_resource_path(resource_dir)
+        .add_resource_path(resource_dir("songs"))
+        // the tileset and its manifest
+        .add_resource_path(resource_dir("resources"))
         .build()?;
 
     // let state = if (save) {
@@ -0,0 +1,378 @@
+use crate::{
+    balance::ModeBalance,
+    boss_script::Wave,
+    combat_log::GameEvent,
+    difficulty::Difficulty,
+    enemy::Enemy,
+    entity::Entity,
+    player::Player,
+    tile,
+    utils::{Boss, Position},
+    world::{World, BOSS_ROOMS},
+    WORLD_SIZE,
+};
+use rand_chacha::ChaCha8Rng;
+use std::{collections::HashMap, fs};
+
+pub const HIGH_SCORES_PATH: &str = "./serialization/high_scores";
+// The order a boss rush goes through BOSS_ROOMS in, saving the final boss for last
+const BOSS_RUSH_ORDER: [usize; 5] = [0, 1, 3, 4, 2];
+// The arena is built over the room a new game starts in
+const ARENA_ROOM: Position = Position::new(0, 0);
+// Arena enemies never show up closer to the player than this many tiles
+const ARENA_SPAWN_CLEARANCE: i32 = 10;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+// What kind of game is being played, picked on the title screen
+pub enum GameMode {
+    // the whole overworld, with the bosses fought in whatever order the player gets to them
+    #[default]
+    Adventure,
+    // every boss one after the other, starting each one with the same loadout
+    BossRush,
+    // bigger and bigger waves of enemies in a room with no way out
+    Arena,
+}
+
+impl GameMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Adventure => "Adventure",
+            GameMode::BossRush => "Boss Rush",
+            GameMode::Arena => "Arena",
+        }
+    }
+
+    // The one after this one, for cycling through on the title screen
+    pub fn next(self) -> Self {
+        match self {
+            GameMode::Adventure => GameMode::BossRush,
+            GameMode::BossRush => GameMode::Arena,
+            GameMode::Arena => GameMode::Adventure,
+        }
+    }
+
+    // Boss rushes and the arena are scored, and can't be undone
+    pub fn is_challenge(self) -> bool {
+        self != GameMode::Adventure
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Default)]
+// How far a boss rush or arena game has got. Lives in the world so saves keep it
+pub struct Challenge {
+    pub mode: GameMode,
+    // bosses beaten in a boss rush, waves cleared in the arena
+    pub stage: usize,
+    pub turns: usize,
+    // turns until the next arena wave shows up, None while one is being fought
+    next_wave: Option<usize>,
+    // whether the score has been written down, so a finished game only counts once
+    pub recorded: bool,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+// How far a finished boss rush or arena game got
+pub struct HighScore {
+    pub stage: usize,
+    pub turns: usize,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+// The best boss rush and arena games played at each difficulty, kept next to the save
+pub struct HighScores {
+    best: HashMap<(GameMode, Difficulty), HighScore>,
+    // the file couldn't be read, so it's left alone instead of being saved over
+    #[serde(skip)]
+    unreadable: bool,
+}
+
+impl Challenge {
+    // Turns a freshly generated world into the start of a game of the given mode
+    pub fn start(world: &mut World, mode: GameMode, rng: &mut ChaCha8Rng) {
+        world.challenge = Challenge {
+            mode,
+            ..Challenge::default()
+        };
+        match mode {
+            GameMode::Adventure => {}
+            GameMode::BossRush => Self::next_boss(world, rng),
+            GameMode::Arena => {
+                Self::build_arena(world);
+                world.challenge.next_wave = Some(world.balance.modes.arena_wave_delay);
+            }
+        }
+    }
+
+    // Moves the game along once everything else in the turn has happened
+    pub fn update(world: &mut World, rng: &mut ChaCha8Rng) {
+        if !world.challenge.mode.is_challenge() || Self::is_over(world) {
+            return;
+        }
+        world.challenge.turns += 1;
+        match world.challenge.mode {
+            GameMode::Adventure => {}
+            GameMode::BossRush => {
+                let room = BOSS_ROOMS[BOSS_RUSH_ORDER[world.challenge.stage]];
+                if world.boss_defeated[room.y][room.x] {
+                    world.challenge.stage += 1;
+                    if !world.challenge.won() {
+                        Self::next_boss(world, rng);
+                    }
+                }
+            }
+            GameMode::Arena => Self::update_arena(world, rng),
+        }
+    }
+
+    pub fn is_over(world: &World) -> bool {
+        world.challenge.mode.is_challenge()
+            && (!world.player.is_alive() || world.challenge.won())
+    }
+
+    // Only a boss rush can be won, the arena goes on until the player dies
+    pub fn won(&self) -> bool {
+        self.mode == GameMode::BossRush && self.stage == BOSS_RUSH_ORDER.len()
+    }
+
+    pub fn score(&self) -> HighScore {
+        HighScore {
+            stage: self.stage,
+            turns: self.turns,
+        }
+    }
+
+    // What the corner of the screen says while the game is going
+    pub fn describe(&self) -> String {
+        match self.mode {
+            GameMode::Adventure => String::new(),
+            GameMode::BossRush => format!(
+                "Boss {} of {}, turn {}",
+                (self.stage + 1).min(BOSS_RUSH_ORDER.len()),
+                BOSS_RUSH_ORDER.len(),
+                self.turns
+            ),
+            GameMode::Arena => format!("Wave {}, turn {}", self.stage + 1, self.turns),
+        }
+    }
+
+    // Moves the player into the room of the next boss of the rush, with a fresh loadout
+    fn next_boss(world: &mut World, rng: &mut ChaCha8Rng) {
+        let room = BOSS_ROOMS[BOSS_RUSH_ORDER[world.challenge.stage]];
+        let pos = Self::entry_spot(world, room);
+        Self::move_player(world, room, pos);
+        world.events.push(GameEvent::RoomEntered { world_pos: room });
+        if World::toggle_doors(&mut world.terrain_map, room, pos, world.boss_defeated) {
+            world.terrain_changed();
+        }
+        Boss::enter(world, rng);
+    }
+
+    // The open tile closest to where the player walks into a boss room from the south, since a
+    // lava lake can cover that spot
+    fn entry_spot(world: &World, room: Position) -> Position {
+        let entry = Position::new(WORLD_SIZE.0 as usize / 2, WORLD_SIZE.1 as usize - 4);
+        (1..WORLD_SIZE.1 as usize - 1)
+            .flat_map(|y| (1..WORLD_SIZE.0 as usize - 1).map(move |x| Position::new(x, y)))
+            .filter(|pos| {
+                !world.terrain_map[room.y][room.x].contains_key(pos)
+                    && !world.entity_map[room.y][room.x].contains_key(pos)
+            })
+            .min_by_key(|pos| pos.x.abs_diff(entry.x) + pos.y.abs_diff(entry.y))
+            .unwrap_or(entry)
+    }
+
+    // Takes the player out of wherever they are and puts them down at pos in room, with the
+    // loadout every challenge starts from
+    fn move_player(world: &mut World, room: Position, pos: Position) {
+        let old_room = world.world_position;
+        world.entity_map[old_room.y][old_room.x].remove(&world.player.pos);
+        world.world_position = room;
+        world.player = Player::with_loadout(
+            &world.balance.player,
+            pos,
+            world.balance.modes.loadout_energy,
+        );
+        world.entity_map[room.y][room.x].insert(pos, (world.player.color, Entity::Player));
+        world.update_vision();
+    }
+
+    // Clears out the arena room and walls it in all the way round
+    fn build_arena(world: &mut World) {
+        let room = ARENA_ROOM;
+        world.enemies_map[room.y][room.x].clear();
+        world.entity_map[room.y][room.x].clear();
+        world.structures_map[room.y][room.x].clear();
+        world.atmosphere_map[room.y][room.x].clear();
+        let terrain = &mut world.terrain_map[room.y][room.x];
+        terrain.clear();
+        for x in 0..WORLD_SIZE.0 as usize {
+            for y in 0..WORLD_SIZE.1 as usize {
+                if x == 0
+                    || y == 0
+                    || x == WORLD_SIZE.0 as usize - 1
+                    || y == WORLD_SIZE.1 as usize - 1
+                {
+                    terrain.insert(Position::new(x, y), tile::WALL);
+                }
+            }
+        }
+        world.terrain_changed();
+        let center = Position::new(WORLD_SIZE.0 as usize / 2, WORLD_SIZE.1 as usize / 2);
+        Self::move_player(world, room, center);
+    }
+
+    // Once a wave is cleared the player gets a few turns to breathe before the next one
+    fn update_arena(world: &mut World, rng: &mut ChaCha8Rng) {
+        match world.challenge.next_wave {
+            Some(0) => {
+                let wave = Self::arena_wave(&world.balance.modes, world.challenge.stage);
+                Enemy::spawn_wave(world, rng, &wave, Self::arena_blocked);
+                world.challenge.next_wave = None;
+                world.events.push(GameEvent::WaveStarted {
+                    wave: world.challenge.stage + 1,
+                });
+            }
+            Some(turns) => world.challenge.next_wave = Some(turns - 1),
+            None => {
+                if world.enemies_map[ARENA_ROOM.y][ARENA_ROOM.x].is_empty() {
+                    world.challenge.stage += 1;
+                    world.challenge.next_wave = Some(world.balance.modes.arena_wave_delay);
+                }
+            }
+        }
+    }
+
+    // The wave that comes after cleared waves have been beaten
+    fn arena_wave(balance: &ModeBalance, cleared: usize) -> Wave {
+        let first = balance.arena_first_wave;
+        let growth = balance.arena_wave_growth;
+        let extra_majors = match balance.arena_major_every {
+            0 => 0,
+            every => (cleared + 1) / every,
+        };
+        Wave {
+            majors: first.majors + growth.majors * cleared + extra_majors,
+            chasers: first.chasers + growth.chasers * cleared,
+            bombers: first.bombers + growth.bombers * cleared,
+        }
+    }
+
+    // Arena enemies keep away from the player and never land on anything, majors take up the
+    // 3x3 square below and to the right of where they are put
+    fn arena_blocked(world: &World, pos: Position) -> bool {
+        let player = world.player.pos;
+        if (pos.x as i32 - player.x as i32).abs() < ARENA_SPAWN_CLEARANCE
+            && (pos.y as i32 - player.y as i32).abs() < ARENA_SPAWN_CLEARANCE
+        {
+            return true;
+        }
+        (0..3).any(|i| {
+            (0..3).any(|j| {
+                let tile = Position::new(pos.x + i, pos.y + j);
+                world.terrain_map[ARENA_ROOM.y][ARENA_ROOM.x].contains_key(&tile)
+                    || world.entity_map[ARENA_ROOM.y][ARENA_ROOM.x].contains_key(&tile)
+            })
+        })
+    }
+}
+
+impl HighScore {
+    // Getting further is what counts, doing it in fewer turns breaks ties
+    fn beats(self, other: HighScore) -> bool {
+        self.stage > other.stage || (self.stage == other.stage && self.turns < other.turns)
+    }
+
+    pub fn describe(self, mode: GameMode) -> String {
+        match mode {
+            GameMode::Adventure => String::new(),
+            GameMode::BossRush => format!(
+                "{} of {} bosses in {} turns",
+                self.stage,
+                BOSS_RUSH_ORDER.len(),
+                self.turns
+            ),
+            GameMode::Arena => format!("{} waves in {} turns", self.stage, self.turns),
+        }
+    }
+}
+
+impl HighScores {
+    // No high scores yet if the file is missing. A broken one is kept as it is, so fixing it by
+    // hand doesn't lose the scores in it
+    pub fn load() -> HighScores {
+        let Ok(text) = fs::read_to_string(HIGH_SCORES_PATH) else {
+            return HighScores::default();
+        };
+        match ron::from_str(&text) {
+            Ok(high_scores) => high_scores,
+            Err(error) => {
+                println!(
+                    "{} is invalid, new high scores won't be saved: {}",
+                    HIGH_SCORES_PATH, error
+                );
+                HighScores {
+                    unreadable: true,
+                    ..HighScores::default()
+                }
+            }
+        }
+    }
+
+    pub fn best(&self, mode: GameMode, difficulty: Difficulty) -> Option<HighScore> {
+        self.best.get(&(mode, difficulty)).copied()
+    }
+
+    // Keeps the score if it's the best one yet for its mode and difficulty, returning whether it
+    // was. Adventures don't have scores
+    pub fn record(&mut self, mode: GameMode, difficulty: Difficulty, score: HighScore) -> bool {
+        if !mode.is_challenge()
+            || self
+                .best(mode, difficulty)
+                .is_some_and(|best| !score.beats(best))
+        {
+            return false;
+        }
+        self.best.insert((mode, difficulty), score);
+        if self.unreadable {
+            return true;
+        }
+        if let Err(error) = fs::write(HIGH_SCORES_PATH, ron::to_string(self).unwrap()) {
+            println!("Couldn't save the high scores: {}", error);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{balance::Balance, boss_script::BossScripts, worldgen::WorldGen};
+    use rand::SeedableRng;
+
+    #[test]
+    fn boss_rush_starts_on_open_ground() {
+        for (name, preset) in &WorldGen::default().0 {
+            for seed in 0..10 {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                let mut world = World::new(
+                    &mut rng,
+                    Difficulty::default(),
+                    &Balance::default(),
+                    &BossScripts::default(),
+                    preset,
+                );
+                Challenge::start(&mut world, GameMode::BossRush, &mut rng);
+                let room = world.world_position;
+                assert_eq!(
+                    world.terrain_map[room.y][room.x].get(&world.player.pos),
+                    None,
+                    "the boss rush starts on terrain in {} with seed {}",
+                    name,
+                    seed
+                );
+            }
+        }
+    }
+}
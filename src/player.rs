@@ -1,9 +1,13 @@
 use crate::{
+    balance::{Balance, PlayerBalance},
+    combat_log::{Actor, GameEvent},
     direction::Direction,
     enemy::{self, Enemy},
     entity::Entity,
     projectile::Projectile,
+    structure::{Structure, StructureKind},
     tile,
+    tileset::TileBatch,
     utils::Boss,
     utils::Position,
     world::World,
@@ -13,26 +17,14 @@ use crate::{
 
 use std::cmp::{max, min};
 
+use ggez::glam::Vec2;
 use ggez::graphics::{self, Canvas};
 use ggez::input::keyboard::{KeyCode, KeyInput};
 use ggez::winit::event::VirtualKeyCode;
+use ggez::Context;
 use rand_chacha::ChaCha8Rng;
 
-// Can change easily
-pub const MAX_PLAYER_HEALTH: usize = 100;
-const MAX_PLAYER_ENERGY: usize = 100;
-const PLAYER_MELEE_DAMAGE: usize = 30;
-const PLAYER_SLAM_DAMAGE: usize = 50;
-const TELEPORTATION_COST: usize = 5;
-const HEAL_COST: usize = 20;
-const FIRE_COST: usize = 30;
-const SLAM_COST: usize = 10;
-const HEAL_ABILITY_RETURN: usize = 10;
-const LIGHTNING_COST: usize = 25;
-const INVISIBILITY_COST: usize = 30;
-const TRACKING_PROJECTILE_COST: usize = 75;
-
-const INVISIBILITY_DURATION: usize = 10;
+
 
 const MELEE_ATTACK_KEYCODE: VirtualKeyCode = KeyCode::M;
 
@@ -48,20 +40,29 @@ const LIGHTNING_KEYCODE: VirtualKeyCode = KeyCode::L;
 const SLAM_KEYCODE: VirtualKeyCode = KeyCode::Z;
 const FLAME_KEYCODE: VirtualKeyCode = KeyCode::F;
 const BUILD_KEYCODE: VirtualKeyCode = KeyCode::B;
+const SELECT_WALL_KEYCODE: VirtualKeyCode = KeyCode::Key1;
+const SELECT_SPIKE_TRAP_KEYCODE: VirtualKeyCode = KeyCode::Key2;
+const SELECT_TURRET_KEYCODE: VirtualKeyCode = KeyCode::Key3;
 const TRACKING_MISSILE_KEYCODE: VirtualKeyCode = KeyCode::X;
 const PROJECTILE_ATTACK_KEYCODE: VirtualKeyCode = KeyCode::Space;
-const PLAYER_PROJECTILE_SPEED: usize = 1;
-pub const PLAYER_PROJECTILE_DAMAGE: usize = 10;
-const PLAYER_INITIAL_SPEED: usize = 1;
-const PLAYER_INITIAL_ENERGY: usize = 100;
-const PERMISSIBLE_TILES: [[f32; 4]; 1] = [tile::GRASS];
-const LIGHTNING_COOLDOWN: usize = 5;
-const TELEPORT_COOLDOWN: usize = 1;
-const FIRE_COOLDOWN: usize = 10;
-const SLAM_COOLDOWN: usize = 10;
-const PROJECTILE_COOLDOWN: usize = 1;
-const INVISIBILITY_COOLDOWN: usize = 25 + INVISIBILITY_DURATION;
-const TRACKING_PROJECTILE_COOLDOWN: usize = 20;
+const PERMISSIBLE_TILES: [[f32; 4]; 5] = [
+    tile::GRASS,
+    tile::MUD,
+    tile::ICE,
+    tile::BRIDGE,
+    tile::FORD,
+];
+
+// Ability bar under the hearts and energy, in pixels
+const ABILITY_BAR_Y: f32 = 82.0;
+const ABILITY_SLOT_SIZE: (f32, f32) = (84.0, 36.0);
+const ABILITY_SLOT_GAP: f32 = 4.0;
+const ABILITY_READY: [f32; 4] = [0.8, 0.85, 0.95, 1.0];
+const ABILITY_UNAVAILABLE: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+// darkens the part of the slot that is still cooling down
+const ABILITY_COOLDOWN_OVERLAY: [f32; 4] = [0.0, 0.0, 0.0, 0.35];
+const ABILITY_TEXT: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+const ABILITY_TEXT_UNAVAILABLE: [f32; 4] = [0.3, 0.3, 0.3, 1.0];
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
 // This is with the covered tile model, but we could use the static/dynamic board paradighm or
@@ -92,6 +93,10 @@ pub struct Player {
     // This is the position queued by mouse clicks, used for teleportation, etc
     pub queued_position: Option<Position>,
 
+    // The tile under the mouse, only used to preview what the click abilities would do
+    #[serde(skip)]
+    pub hovered_position: Option<Position>,
+
     // duration of visibility: 0 means visible, N > 0 means invisible for N more turns
     visible: i16,
 
@@ -105,6 +110,33 @@ pub struct Player {
     tracking_projectile_cooldown: i16,
     pub stun_timer: usize,
     is_alive: bool,
+
+    // Stepped into mud or a ford, so the next step only pulls the player free
+    #[serde(default)]
+    pub stuck: bool,
+
+    // What gets placed when the player builds
+    #[serde(default)]
+    pub selected_structure: StructureKind,
+
+    // Damage taken since the world last reported it, and who dealt it
+    #[serde(skip)]
+    pub recent_hits: Vec<(Actor, usize)>,
+
+    // Whatever dealt the killing blow
+    #[serde(skip)]
+    pub killed_by: Option<Actor>,
+}
+
+// What the ability bar shows for one ability
+struct AbilitySlot {
+    key: VirtualKeyCode,
+    name: &'static str,
+    cost: usize,
+    // turns left before it can be used again, 0 or less means it's ready
+    cooldown: i16,
+    // what the cooldown gets set to when the ability is used
+    max_cooldown: usize,
 }
 
 impl Player {
@@ -116,8 +148,12 @@ impl Player {
         self.health
     }
 
-    pub fn damage(&mut self, damage: usize) {
+    pub fn damage(&mut self, damage: usize, source: Actor) {
+        self.recent_hits.push((source, damage));
         if (self.health as i32 - damage as i32 <= 0) {
+            if self.is_alive {
+                self.killed_by = Some(source);
+            }
             self.is_alive = false;
             return;
         }
@@ -128,15 +164,16 @@ impl Player {
         self.visible <= 0
     }
 
-    pub fn new() -> Self {
+    pub fn new(balance: &PlayerBalance) -> Self {
         let temp = Self {
             pos: Position::new(0, 0),
             direction: Direction::South,
-            speed: PLAYER_INITIAL_SPEED,
+            speed: balance.speed,
             color: tile::PLAYER,
-            health: MAX_PLAYER_HEALTH,
-            energy: PLAYER_INITIAL_ENERGY,
+            health: balance.max_health,
+            energy: balance.initial_energy,
             queued_position: None,
+            hovered_position: None,
             visible: 0,
             projectile_cooldown: 0,
             slam_cooldown: 0,
@@ -147,12 +184,24 @@ impl Player {
             tracking_projectile_cooldown: 0,
             stun_timer: 0,
             is_alive: true,
+            stuck: false,
+            selected_structure: StructureKind::Wall,
+            recent_hits: Vec::new(),
+            killed_by: None,
         };
         temp
     }
 
+    // A fresh player standing at pos with a set amount of energy, for boss rushes and the arena
+    pub fn with_loadout(balance: &PlayerBalance, pos: Position, energy: usize) -> Self {
+        let mut player = Self::new(balance);
+        player.pos = pos;
+        player.energy = energy;
+        player
+    }
+
     //Draws hearts on open space above the screen
-    pub fn draw_health(&self, canvas: &mut graphics::Canvas) {
+    pub fn draw_health(&self, hud: &mut TileBatch) {
         let outline = [
             (2, 0),
             (3, 0),
@@ -190,24 +239,22 @@ impl Player {
         for i in 0..5 {
             //Draw one heart each time in the loop
             for coord in outline {
-                canvas.draw(
-                    &graphics::Quad,
-                    graphics::DrawParam::new()
-                        .dest_rect(graphics::Rect::new_i32(
-                            ((coord.0) as i32 + 1) * 5 + i * 70, //x coordinate of each outline pixel from array
-                            ((coord.1) as i32 + 2) * 5, //y coordinate of each outline pixel from array
-                            5,
-                            5,
-                        ))
-                        .color([0.0, 0.0, 0.0, 1.0]), //Color of outline
-                )
+                hud.push_rect(
+                    graphics::Rect::new_i32(
+                        ((coord.0) as i32 + 1) * 5 + i * 70, //x coordinate of each outline pixel from array
+                        ((coord.1) as i32 + 2) * 5, //y coordinate of each outline pixel from array
+                        5,
+                        5,
+                    ),
+                    [0.0, 0.0, 0.0, 1.0], //Color of outline
+                );
             }
-            Self::color_heart(&self, canvas, outline, i); //Color in the heart
+            Self::color_heart(&self, hud, outline, i); //Color in the heart
         }
     }
 
     //Draws energy symbols on space above screen, works exactly the same as draw_health() except has different outline positions
-    pub fn draw_energy(&self, canvas: &mut graphics::Canvas) {
+    pub fn draw_energy(&self, hud: &mut TileBatch) {
         let outline = [
             (3, 0),
             (4, 0),
@@ -249,29 +296,22 @@ impl Player {
         ];
         for i in 0..5 {
             for coord in outline {
-                canvas.draw(
-                    &graphics::Quad,
-                    graphics::DrawParam::new()
-                        .dest_rect(graphics::Rect::new_i32(
-                            ((coord.0) as i32 + 85) * 5 + i * 53,
-                            ((coord.1) as i32 + 2) * 5,
-                            5,
-                            5,
-                        ))
-                        .color([0.0, 0.0, 0.0, 1.0]),
-                )
+                hud.push_rect(
+                    graphics::Rect::new_i32(
+                        ((coord.0) as i32 + 85) * 5 + i * 53,
+                        ((coord.1) as i32 + 2) * 5,
+                        5,
+                        5,
+                    ),
+                    [0.0, 0.0, 0.0, 1.0],
+                );
             }
-            Self::color_energy(&self, canvas, outline, i);
+            Self::color_energy(&self, hud, outline, i);
         }
     }
 
     //Colors in the hearts based on current health
-    pub fn color_heart(
-        &self,
-        canvas: &mut graphics::Canvas,
-        outline: [(usize, usize); 32],
-        iteration: i32,
-    ) {
+    pub fn color_heart(&self, hud: &mut TileBatch, outline: [(usize, usize); 32], iteration: i32) {
         let master_heart_color: [f32; 4]; //True value for specific heart, used so half hearts can be colored correctly
         let stage4 = [0.145, 0.682, 0.745, 1.0];
         let stage3 = [0.2, 0.8, 0.2, 1.0];
@@ -321,16 +361,14 @@ impl Player {
                                 //For the three white pixels :)
                                 temp_heart_color = [1.0, 1.0, 1.0, 1.0];
                             }
-                            canvas.draw(
-                                &graphics::Quad,
-                                graphics::DrawParam::new()
-                                    .dest_rect(graphics::Rect::new_i32(
-                                        ((pos.0) as i32 + 1) * 5 + iteration * 70,
-                                        ((pos.1) as i32 + 2) * 5,
-                                        5,
-                                        5,
-                                    ))
-                                    .color(temp_heart_color),
+                            hud.push_rect(
+                                graphics::Rect::new_i32(
+                                    ((pos.0) as i32 + 1) * 5 + iteration * 70,
+                                    ((pos.1) as i32 + 2) * 5,
+                                    5,
+                                    5,
+                                ),
+                                temp_heart_color,
                             );
                             temp_heart_color = master_heart_color;
                         }
@@ -343,12 +381,7 @@ impl Player {
 
     //Colors in the energies based on current energy
     //Works exactly the same as color_heart(), but instead the half energy uses half the height, not the width
-    pub fn color_energy(
-        &self,
-        canvas: &mut graphics::Canvas,
-        outline: [(usize, usize); 37],
-        iteration: i32,
-    ) {
+    pub fn color_energy(&self, hud: &mut TileBatch, outline: [(usize, usize); 37], iteration: i32) {
         let master_energy_color: [f32; 4];
         let stage4 = [0.0, 0.1, 0.3, 1.0];
         let stage3 = [0.15, 0.2, 0.85, 1.0];
@@ -388,16 +421,14 @@ impl Player {
                         }
                         while outline[i].0 + offset != outline[i + 1].0 {
                             let pos = (outline[i].0 + offset, outline[i].1);
-                            canvas.draw(
-                                &graphics::Quad,
-                                graphics::DrawParam::new()
-                                    .dest_rect(graphics::Rect::new_i32(
-                                        ((pos.0) as i32 + 85) * 5 + iteration * 53,
-                                        ((pos.1) as i32 + 2) * 5,
-                                        5,
-                                        5,
-                                    ))
-                                    .color(temp_energy_color),
+                            hud.push_rect(
+                                graphics::Rect::new_i32(
+                                    ((pos.0) as i32 + 85) * 5 + iteration * 53,
+                                    ((pos.1) as i32 + 2) * 5,
+                                    5,
+                                    5,
+                                ),
+                                temp_energy_color,
                             );
                             offset += 1;
                         }
@@ -407,6 +438,142 @@ impl Player {
         }
     }
 
+    fn ability_slots(&self, balance: &Balance) -> [AbilitySlot; 9] {
+        let slot = |key, name, cost, cooldown, max_cooldown| AbilitySlot {
+            key,
+            name,
+            cost,
+            cooldown,
+            max_cooldown,
+        };
+        let build_name = match self.selected_structure {
+            StructureKind::Wall => "Wall",
+            StructureKind::SpikeTrap => "Spikes",
+            StructureKind::Turret => "Turret",
+        };
+        [
+            slot(
+                PROJECTILE_ATTACK_KEYCODE,
+                "Shoot",
+                balance.player.shot_cost,
+                self.projectile_cooldown,
+                balance.player.shot_cooldown,
+            ),
+            slot(HEAL_KEYCODE, "Heal", balance.player.heal_cost, 0, 0),
+            slot(
+                TELEPORT_KEYCODE,
+                "Teleport",
+                balance.player.teleport_cost,
+                self.teleport_cooldown,
+                balance.player.teleport_cooldown,
+            ),
+            slot(
+                LIGHTNING_KEYCODE,
+                "Lightning",
+                balance.player.lightning_cost,
+                self.lightning_cooldown,
+                balance.player.lightning_cooldown,
+            ),
+            slot(
+                SLAM_KEYCODE,
+                "Slam",
+                balance.player.slam_cost,
+                self.slam_cooldown,
+                balance.player.slam_cooldown,
+            ),
+            slot(
+                FLAME_KEYCODE,
+                "Flame",
+                balance.player.fire_cost,
+                self.fire_cooldown,
+                balance.player.fire_cooldown,
+            ),
+            slot(
+                INVISIBILITY_KEYCODE,
+                "Invisible",
+                balance.player.invisibility_cost,
+                self.invisiblity_cooldown,
+                (balance.player.invisibility_cooldown + balance.player.invisibility_duration),
+            ),
+            slot(
+                TRACKING_MISSILE_KEYCODE,
+                "Missile",
+                balance.player.missile_cost,
+                self.tracking_projectile_cooldown,
+                balance.player.missile_cooldown,
+            ),
+            slot(
+                BUILD_KEYCODE,
+                build_name,
+                self.selected_structure.cost(&balance.structures),
+                0,
+                0,
+            ),
+        ]
+    }
+
+    //Draws a row of slots under the hearts and energy, one for each ability, with its key and
+    //energy cost. Abilities that can't be used right now are greyed out, and the ones cooling down
+    //are covered from the top by how much of the cooldown is left, along with the turns left
+    pub fn draw_ability_bar(&self, ctx: &Context, canvas: &mut Canvas, balance: &Balance) {
+        let slots = self.ability_slots(balance);
+        let mut batch = TileBatch::new(ctx, None);
+        let mut labels = Vec::new();
+        for (i, slot) in slots.iter().enumerate() {
+            let x = ABILITY_SLOT_GAP / 2.0 + i as f32 * (ABILITY_SLOT_SIZE.0 + ABILITY_SLOT_GAP);
+            let ready = slot.cooldown <= 0 && self.energy >= slot.cost;
+            batch.push_rect(
+                graphics::Rect::new(x, ABILITY_BAR_Y, ABILITY_SLOT_SIZE.0, ABILITY_SLOT_SIZE.1),
+                if ready {
+                    ABILITY_READY
+                } else {
+                    ABILITY_UNAVAILABLE
+                },
+            );
+            if slot.cooldown > 0 {
+                let left = (slot.cooldown as f32 / slot.max_cooldown.max(1) as f32).min(1.0);
+                batch.push_rect(
+                    graphics::Rect::new(
+                        x,
+                        ABILITY_BAR_Y,
+                        ABILITY_SLOT_SIZE.0,
+                        ABILITY_SLOT_SIZE.1 * left,
+                    ),
+                    ABILITY_COOLDOWN_OVERLAY,
+                );
+            }
+            labels.push((x, ready));
+        }
+        batch.finish(canvas);
+
+        //The text goes on top of the slots
+        for (slot, (x, ready)) in slots.iter().zip(labels) {
+            let color = if ready {
+                ABILITY_TEXT
+            } else {
+                ABILITY_TEXT_UNAVAILABLE
+            };
+            canvas.draw(
+                &*(graphics::Text::new(format!("{:?} {}", slot.key, slot.name)).set_scale(13.)),
+                graphics::DrawParam::from(Vec2::new(x + 3.0, ABILITY_BAR_Y + 3.0)).color(color),
+            );
+            canvas.draw(
+                &*(graphics::Text::new(format!("{} energy", slot.cost)).set_scale(12.)),
+                graphics::DrawParam::from(Vec2::new(x + 3.0, ABILITY_BAR_Y + 20.0)).color(color),
+            );
+            if slot.cooldown > 0 {
+                canvas.draw(
+                    &*(graphics::Text::new(format!("{}", slot.cooldown)).set_scale(16.)),
+                    graphics::DrawParam::from(Vec2::new(
+                        x + ABILITY_SLOT_SIZE.0 - 20.0,
+                        ABILITY_BAR_Y + 18.0,
+                    ))
+                    .color(graphics::Color::WHITE),
+                );
+            }
+        }
+    }
+
     // eventually this should be the functionality to like shoot projectiles and stuff but for now
     // it just handles like arrow keys
     // Returns if the move should consume a turn
@@ -417,7 +584,7 @@ impl Player {
                     // make sure moving doesn't change direction
                     let old_direction = world.player.direction;
                     world.player.direction = Direction::South;
-                    World::travel(world, Entity::Player, None);
+                    Self::walk(world);
                     world.player.projectile_cooldown -= 1;
                     world.player.slam_cooldown -= 1;
                     world.player.fire_cooldown -= 1;
@@ -431,7 +598,7 @@ impl Player {
                 KeyCode::Up => {
                     let old_direction = world.player.direction;
                     world.player.direction = Direction::North;
-                    World::travel(world, Entity::Player, None);
+                    Self::walk(world);
                     world.player.projectile_cooldown -= 1;
                     world.player.slam_cooldown -= 1;
                     world.player.fire_cooldown -= 1;
@@ -445,7 +612,7 @@ impl Player {
                 KeyCode::Left => {
                     let old_direction = world.player.direction;
                     world.player.direction = Direction::West;
-                    World::travel(world, Entity::Player, None);
+                    Self::walk(world);
                     world.player.projectile_cooldown -= 1;
                     world.player.slam_cooldown -= 1;
                     world.player.fire_cooldown -= 1;
@@ -459,7 +626,7 @@ impl Player {
                 KeyCode::Right => {
                     let old_direction = world.player.direction;
                     world.player.direction = Direction::East;
-                    World::travel(world, Entity::Player, None);
+                    Self::walk(world);
                     world.player.projectile_cooldown -= 1;
                     world.player.slam_cooldown -= 1;
                     world.player.fire_cooldown -= 1;
@@ -522,6 +689,7 @@ impl Player {
                 // Arbitrarily chosen for attack, can change later
                 MELEE_ATTACK_KEYCODE => {
                     Player::melee_attack(world);
+                    world.events.push(GameEvent::AbilityUsed { ability: "melee" });
                     world.player.projectile_cooldown -= 1;
                     world.player.slam_cooldown -= 1;
                     world.player.fire_cooldown -= 1;
@@ -532,10 +700,13 @@ impl Player {
                     world.player.visible -= 1;
                 }
                 PROJECTILE_ATTACK_KEYCODE => {
-                    if world.player.energy > 0 && world.player.projectile_cooldown <= 0 {
+                    if world.player.energy >= world.balance.player.shot_cost
+                        && world.player.projectile_cooldown <= 0
+                    {
                         Player::projectile_attack(world);
-                        world.player.energy -= 1;
-                        world.player.projectile_cooldown = PROJECTILE_COOLDOWN as i16;
+                        world.events.push(GameEvent::AbilityUsed { ability: "projectile" });
+                        world.player.energy -= world.balance.player.shot_cost;
+                        world.player.projectile_cooldown = world.balance.player.shot_cooldown as i16;
                         world.player.slam_cooldown -= 1;
                         world.player.fire_cooldown -= 1;
                         world.player.lightning_cooldown -= 1;
@@ -548,9 +719,10 @@ impl Player {
                     }
                 }
                 HEAL_KEYCODE => {
-                    if world.player.energy >= HEAL_COST && world.player.health < 100 {
-                        world.player.health += HEAL_ABILITY_RETURN;
-                        world.player.energy -= HEAL_COST;
+                    if world.player.energy >= world.balance.player.heal_cost && world.player.health < world.balance.player.max_health {
+                        world.player.health += world.balance.player.heal_return;
+                        world.player.energy -= world.balance.player.heal_cost;
+                        world.events.push(GameEvent::AbilityUsed { ability: "heal" });
                         world.player.projectile_cooldown -= 1;
                         world.player.slam_cooldown -= 1;
                         world.player.fire_cooldown -= 1;
@@ -563,8 +735,22 @@ impl Player {
                         return false;
                     }
                 }
+                // Choosing what to build doesn't take a turn
+                SELECT_WALL_KEYCODE => {
+                    world.player.selected_structure = StructureKind::Wall;
+                    return false;
+                }
+                SELECT_SPIKE_TRAP_KEYCODE => {
+                    world.player.selected_structure = StructureKind::SpikeTrap;
+                    return false;
+                }
+                SELECT_TURRET_KEYCODE => {
+                    world.player.selected_structure = StructureKind::Turret;
+                    return false;
+                }
                 BUILD_KEYCODE => {
-                    if world.player.energy > 2 && Player::build(world) {
+                    if Player::build(world) {
+                        world.events.push(GameEvent::AbilityUsed { ability: "build" });
                         world.player.projectile_cooldown -= 1;
                         world.player.slam_cooldown -= 1;
                         world.player.fire_cooldown -= 1;
@@ -578,15 +764,16 @@ impl Player {
                     }
                 }
                 LIGHTNING_KEYCODE => {
-                    if world.player.energy >= LIGHTNING_COST
+                    if world.player.energy >= world.balance.player.lightning_cost
                         && world.player.lightning_cooldown <= 0
                         && world.player.queued_position.is_some()
                     {
                         Player::lightning(world);
+                        world.events.push(GameEvent::AbilityUsed { ability: "lightning" });
                         world.player.projectile_cooldown -= 1;
                         world.player.slam_cooldown -= 1;
                         world.player.fire_cooldown -= 1;
-                        world.player.lightning_cooldown = LIGHTNING_COOLDOWN as i16;
+                        world.player.lightning_cooldown = world.balance.player.lightning_cooldown as i16;
                         world.player.teleport_cooldown -= 1;
                         world.player.tracking_projectile_cooldown -= 1;
                         world.player.invisiblity_cooldown -= 1;
@@ -598,16 +785,17 @@ impl Player {
 
                 // TODO FINISH COSTS REFACTORING
                 TELEPORT_KEYCODE => {
-                    if world.player.energy >= TELEPORTATION_COST
+                    if world.player.energy >= world.balance.player.teleport_cost
                         && world.player.queued_position.is_some()
                         && world.player.teleport_cooldown <= 0
                     {
                         Self::teleport(world);
+                        world.events.push(GameEvent::AbilityUsed { ability: "teleport" });
                         world.player.projectile_cooldown -= 1;
                         world.player.slam_cooldown -= 1;
                         world.player.fire_cooldown -= 1;
                         world.player.lightning_cooldown -= 1;
-                        world.player.teleport_cooldown = TELEPORT_COOLDOWN as i16;
+                        world.player.teleport_cooldown = world.balance.player.teleport_cooldown as i16;
                         world.player.tracking_projectile_cooldown -= 1;
                         world.player.invisiblity_cooldown -= 1;
                         world.player.visible -= 1;
@@ -616,11 +804,12 @@ impl Player {
                     }
                 }
                 SLAM_KEYCODE => {
-                    if world.player.slam_cooldown <= 0 && world.player.energy >= SLAM_COST {
-                        world.player.change_energy(-(SLAM_COST as i32));
+                    if world.player.slam_cooldown <= 0 && world.player.energy >= world.balance.player.slam_cost {
+                        world.player.change_energy(-(world.balance.player.slam_cost as i32), world.balance.player.max_energy);
                         Self::slam(world);
+                        world.events.push(GameEvent::AbilityUsed { ability: "slam" });
                         world.player.projectile_cooldown -= 1;
-                        world.player.slam_cooldown = SLAM_COOLDOWN as i16;
+                        world.player.slam_cooldown = world.balance.player.slam_cooldown as i16;
                         world.player.fire_cooldown -= 1;
                         world.player.lightning_cooldown -= 1;
                         world.player.teleport_cooldown -= 1;
@@ -632,12 +821,13 @@ impl Player {
                     }
                 }
                 FLAME_KEYCODE => {
-                    if world.player.fire_cooldown <= 0 && world.player.energy >= FIRE_COST {
-                        world.player.change_energy(-(FIRE_COST as i32));
+                    if world.player.fire_cooldown <= 0 && world.player.energy >= world.balance.player.fire_cost {
+                        world.player.change_energy(-(world.balance.player.fire_cost as i32), world.balance.player.max_energy);
                         Self::fire_attack(world);
+                        world.events.push(GameEvent::AbilityUsed { ability: "fire" });
                         world.player.projectile_cooldown -= 1;
                         world.player.slam_cooldown -= 1;
-                        world.player.fire_cooldown = FIRE_COOLDOWN as i16;
+                        world.player.fire_cooldown = world.balance.player.fire_cooldown as i16;
                         world.player.lightning_cooldown -= 1;
                         world.player.teleport_cooldown -= 1;
                         world.player.tracking_projectile_cooldown -= 1;
@@ -648,12 +838,13 @@ impl Player {
                     }
                 }
                 INVISIBILITY_KEYCODE => {
-                    if world.player.energy >= INVISIBILITY_COST
+                    if world.player.energy >= world.balance.player.invisibility_cost
                         && world.player.invisiblity_cooldown <= 0
                     {
-                        world.player.visible = INVISIBILITY_DURATION as i16;
-                        world.player.invisiblity_cooldown = INVISIBILITY_COOLDOWN as i16;
-                        world.player.change_energy(-(INVISIBILITY_COST as i32));
+                        world.player.visible = world.balance.player.invisibility_duration as i16;
+                        world.player.invisiblity_cooldown = (world.balance.player.invisibility_cooldown + world.balance.player.invisibility_duration) as i16;
+                        world.player.change_energy(-(world.balance.player.invisibility_cost as i32), world.balance.player.max_energy);
+                        world.events.push(GameEvent::AbilityUsed { ability: "invisibility" });
                         world.player.projectile_cooldown -= 1;
                         world.player.slam_cooldown -= 1;
                         world.player.fire_cooldown -= 1;
@@ -665,15 +856,16 @@ impl Player {
                     }
                 }
                 TRACKING_MISSILE_KEYCODE => {
-                    if world.player.energy >= TRACKING_PROJECTILE_COST
+                    if world.player.energy >= world.balance.player.missile_cost
                         && world.player.tracking_projectile_cooldown <= 0
                     {
                         Self::tracking_projectile_attack(world);
+                        world.events.push(GameEvent::AbilityUsed { ability: "missile" });
                         world
                             .player
-                            .change_energy(-(TRACKING_PROJECTILE_COST as i32));
+                            .change_energy(-(world.balance.player.missile_cost as i32), world.balance.player.max_energy);
                         world.player.tracking_projectile_cooldown =
-                            TRACKING_PROJECTILE_COOLDOWN as i16;
+                            world.balance.player.missile_cooldown as i16;
                         world.player.projectile_cooldown -= 1;
                         world.player.slam_cooldown -= 1;
                         world.player.fire_cooldown -= 1;
@@ -709,7 +901,7 @@ impl Player {
 
         world
             .projectiles
-            .push(Projectile::tracking_projectile(pos.x, pos.y, world_pos));
+            .push(Projectile::tracking_projectile(pos.x, pos.y, world_pos, &world.balance));
 
         // Queue it to draw
         world.atmosphere_map[world_pos.y][world_pos.x].insert(pos, tile::TRACKING_PROJECTILE);
@@ -731,6 +923,7 @@ impl Player {
             pos.y,
             world.player.direction,
             world_pos,
+            &world.balance,
         ));
 
         // Queue it to draw
@@ -751,7 +944,7 @@ impl Player {
                         (world.player.pos.y as i16 + delta_y) as usize,
                     );
                     if enemy.pos.contains(&position) {
-                        enemy.damage(PLAYER_SLAM_DAMAGE);
+                        enemy.damage(world.balance.player.slam_damage, Actor::PlayerAttack("slam"));
                     }
                 }
             }
@@ -766,7 +959,12 @@ impl Player {
                     );
                     let hit_info = Boss::can_hit_boss(world, position, world.world_position);
                     if hit_info.0 && hit_info.1 {
-                        Boss::damage(world, PLAYER_SLAM_DAMAGE, world.world_position);
+                        Boss::damage(
+                            world,
+                            world.balance.player.slam_damage,
+                            world.world_position,
+                            Actor::PlayerAttack("slam"),
+                        );
                         return;
                     }
                 }
@@ -784,7 +982,7 @@ impl Player {
         // queued positions are definitionally valid, so no checking needs to be done
         world
             .projectiles
-            .push(Projectile::lightning(pos.x, pos.y, world_pos));
+            .push(Projectile::lightning(pos.x, pos.y, world_pos, &world.balance));
 
         // Queue it to draw
         world.atmosphere_map[world_pos.y][world_pos.x].insert(pos, tile::LIGHTNING_PLACEHOLDER);
@@ -796,41 +994,37 @@ impl Player {
             if Player::can_travel_to(world, (pos, world.world_position)) {
                 World::update_position(world, world.player.pos, (pos, world.world_position));
                 world.player.pos = pos;
-                world.player.change_energy(-(TELEPORTATION_COST as i32));
+                world.player.change_energy(-(world.balance.player.teleport_cost as i32), world.balance.player.max_energy);
             }
         }
     }
 
     pub fn build(world: &mut World) -> bool {
         if let Some(pos) = world.player.queued_position {
-            if (pos.x as i32 - world.player.pos.x as i32).abs() < 2
-                && (pos.y as i32 - world.player.pos.y as i32).abs() < 2
-            {
+            if world.player.in_build_range(pos) {
                 // get the things to check
                 let world_pos = world.world_position;
                 let terrain_map = &world.terrain_map[world_pos.y][world_pos.x];
                 let entity_map = &world.entity_map[world_pos.y][world_pos.x];
-                let atmosphere_map = &mut world.atmosphere_map[world_pos.y][world_pos.x];
-
-                // make sure build position has no terrain
-                if !terrain_map.contains_key(&pos) {
-                    // make sure there are no entities
-                    if !entity_map.contains_key(&pos) {
-                        // make sure the atmosphere doesn't contain anything
-                        if !atmosphere_map.contains_key(&pos) {
-                            atmosphere_map.insert(pos, tile::STRUCTURE);
-                            world.player.energy -= 2;
-                            return true;
-                        } else {
-                            match atmosphere_map.get(&pos).expect("This should be impossible because we checked that it contained a key before") {
-                                &tile::STRUCTURE => {
-                                    atmosphere_map.remove(&pos);
-                                    return true;
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
+                let atmosphere_map = &world.atmosphere_map[world_pos.y][world_pos.x];
+                let kind = world.player.selected_structure;
+
+                // building on top of an existing structure tears it down instead
+                if let Some(index) = Structure::get(world, pos, world_pos) {
+                    Structure::remove(world, index, world_pos);
+                    return true;
+                }
+
+                // make sure build position has no terrain, no entities, and nothing in the
+                // atmosphere
+                if !terrain_map.contains_key(&pos)
+                    && !entity_map.contains_key(&pos)
+                    && !atmosphere_map.contains_key(&pos)
+                    && world.player.energy >= kind.cost(&world.balance.structures)
+                {
+                    Structure::place(world, kind, pos, world_pos);
+                    world.player.energy -= kind.cost(&world.balance.structures);
+                    return true;
                 }
             }
         }
@@ -854,10 +1048,11 @@ impl Player {
         let world_pos = world.world_position;
         if let Some(entity) = world.entity_map[world_pos.y][world_pos.x].get(&attacking_position) {
             if entity.1 == Entity::Enemy {
-                for enemy in &mut world.enemies_map[world.world_position.y][world.world_position.x] {
+                for enemy in &mut world.enemies_map[world.world_position.y][world.world_position.x]
+                {
                     if enemy.pos.contains(&attacking_position) {
-                        enemy.damage(PLAYER_MELEE_DAMAGE);
-                        world.player.change_energy(2);
+                        enemy.damage(world.balance.player.melee_damage, Actor::PlayerAttack("melee attack"));
+                        world.player.change_energy(2, world.balance.player.max_energy);
                     }
                 }
             }
@@ -866,7 +1061,12 @@ impl Player {
         if BOSS_ROOMS.contains(&world_pos) {
             let hit_info = Boss::can_hit_boss(world, attacking_position, world_pos);
             if hit_info.0 && hit_info.1 {
-                Boss::damage(world, PLAYER_MELEE_DAMAGE, world_pos);
+                Boss::damage(
+                    world,
+                    world.balance.player.melee_damage,
+                    world_pos,
+                    Actor::PlayerAttack("melee attack"),
+                );
             }
         }
     }
@@ -890,21 +1090,34 @@ impl Player {
                 projectile_spawn_pos.0.y,
                 world.player.direction.clone(),
                 world.world_position,
+                &world.balance,
             );
-            for index in 0..world.enemies_map[world.world_position.y][world.world_position.x].len() {
+            for index in 0..world.enemies_map[world.world_position.y][world.world_position.x].len()
+            {
                 //Check if it's spawning on enemy, if so damage the enenmy and not spawn a projectile
-                if world.enemies_map[world.world_position.y][world.world_position.x][index].pos.contains(&projectile_spawn_pos.0)
-                    && projectile_spawn_pos.1 == world.enemies_map[world.world_position.y][world.world_position.x][index].world_pos
+                if world.enemies_map[world.world_position.y][world.world_position.x][index]
+                    .pos
+                    .contains(&projectile_spawn_pos.0)
+                    && projectile_spawn_pos.1
+                        == world.enemies_map[world.world_position.y][world.world_position.x][index]
+                            .world_pos
                 {
-                    world.enemies_map[world.world_position.y][world.world_position.x][index].damage(projectile.damage);
+                    world.enemies_map[world.world_position.y][world.world_position.x][index]
+                        .damage(projectile.damage, Actor::PlayerAttack("shot"));
                     return;
                 }
             }
-            
+
             if BOSS_ROOMS.contains(&world.world_position) {
-                let hit_info = Boss::can_hit_boss(world, projectile_spawn_pos.0, world.world_position);
+                let hit_info =
+                    Boss::can_hit_boss(world, projectile_spawn_pos.0, world.world_position);
                 if hit_info.0 && hit_info.0 {
-                    Boss::damage(world, PLAYER_MELEE_DAMAGE, world.world_position);
+                    Boss::damage(
+                        world,
+                        world.balance.player.melee_damage,
+                        world.world_position,
+                        Actor::PlayerAttack("shot"),
+                    );
                     return;
                 }
             }
@@ -917,44 +1130,128 @@ impl Player {
         }
     }
 
+    // Moves the player one tile the way they're facing. Mud and fords hold on to the player for a
+    // turn, and ice keeps them sliding until they run into something or it ends
+    fn walk(world: &mut World) {
+        if world.player.stuck {
+            world.player.stuck = false;
+            return;
+        }
+        World::travel(world, Entity::Player, None);
+        // a slide can't go on for longer than it takes to cross a world
+        for _ in 0..WORLD_SIZE.0 {
+            if Self::standing_on(world) != Some(tile::ICE) {
+                break;
+            }
+            let before = (world.player.pos, world.world_position);
+            World::travel(world, Entity::Player, None);
+            if (world.player.pos, world.world_position) == before {
+                break;
+            }
+        }
+        world.player.stuck = matches!(
+            Self::standing_on(world),
+            Some(tile::MUD) | Some(tile::FORD)
+        );
+    }
+
+    // The terrain under the player, if any
+    fn standing_on(world: &World) -> Option<[f32; 4]> {
+        world.terrain_map[world.world_position.y][world.world_position.x]
+            .get(&world.player.pos)
+            .copied()
+    }
+
     pub fn can_travel_to(
         world: &mut World,
         position_info: (Position, Position), //Where .0 is the position, and .1 is the world_position
     ) -> bool {
-        //Get the map on which the position is on
-        let terrain_map = &world.terrain_map;
-        let entity_map = &world.entity_map;
-        let atmosphere_map = &world.atmosphere_map;
-        let curr_terrain_map = &terrain_map[position_info.1.y][position_info.1.x];
-        let curr_entity_map = &entity_map[position_info.1.y][position_info.1.x];
-        let curr_atmosphere_map = &atmosphere_map[position_info.1.y][position_info.1.x];
         if world.player.stun_timer != 0 {
             world.player.stun_timer -= 1;
             return false;
         }
+        Self::is_open(world, position_info)
+    }
+
+    // Whether the player could stand on a tile, without anything like being stunned getting in
+    // the way
+    fn is_open(world: &World, position_info: (Position, Position)) -> bool {
+        let curr_terrain_map = &world.terrain_map[position_info.1.y][position_info.1.x];
+        let curr_entity_map = &world.entity_map[position_info.1.y][position_info.1.x];
+        let curr_atmosphere_map = &world.atmosphere_map[position_info.1.y][position_info.1.x];
         if curr_entity_map.contains_key(&position_info.0)
-            || curr_terrain_map.contains_key(&position_info.0)
             || curr_atmosphere_map.contains_key(&position_info.0)
         {
-            if let Some(info) = curr_entity_map.get(&position_info.0) {
-                if PERMISSIBLE_TILES.contains(&info.0) {
-                    return true;
-                }
-            }
-            if let Some(info) = curr_terrain_map.get(&position_info.0) {
-                if PERMISSIBLE_TILES.contains(&info) {
-                    return true;
-                }
-            }
             return false;
         }
+        // terrain like mud, ice and bridges can be walked on, as long as nothing else is standing there
+        if let Some(info) = curr_terrain_map.get(&position_info.0) {
+            if !PERMISSIBLE_TILES.contains(info) {
+                return false;
+            }
+        }
         if Boss::pos_inside_boss(world, position_info.0, position_info.1) {
             return false;
         }
         true
     }
 
-    pub fn change_energy(&mut self, delta: i32) {
-        self.energy = max(0, min(self.energy as i32 + delta, MAX_PLAYER_ENERGY as i32)) as usize;
+    // Structures can only be built on the eight tiles around the player
+    fn in_build_range(&self, pos: Position) -> bool {
+        (pos.x as i32 - self.pos.x as i32).abs() < 2 && (pos.y as i32 - self.pos.y as i32).abs() < 2
+    }
+
+    //Previews what the click abilities would do at the tile under the mouse, or at the queued tile
+    //when the mouse isn't over the world: the area lightning would strike, whether teleporting
+    //there would work, and which tiles are close enough to build on. The queued tile is framed so
+    //it's clear what the abilities will actually use
+    pub fn draw_targeting(world: &World, ctx: &Context, canvas: &mut Canvas) {
+        let player = &world.player;
+        let target = match player.hovered_position.or(player.queued_position) {
+            Some(target) => target,
+            None => return,
+        };
+        let mut batch = TileBatch::new(ctx, None);
+        for x in player.pos.x.saturating_sub(1)..=player.pos.x + 1 {
+            for y in player.pos.y.saturating_sub(1)..=player.pos.y + 1 {
+                let loc = Position::new(x, y);
+                if loc != player.pos && x < WORLD_SIZE.0 as usize && y < WORLD_SIZE.1 as usize {
+                    batch.push(loc, tile::TARGET_BUILD_RANGE);
+                }
+            }
+        }
+        for loc in Projectile::lightning_area(target, world.balance.projectiles.lightning_size) {
+            if loc != target {
+                batch.push(loc, tile::TARGET_LIGHTNING);
+            }
+        }
+        batch.push(
+            target,
+            if Self::is_open(world, (target, world.world_position)) {
+                tile::TARGET_VALID
+            } else {
+                tile::TARGET_INVALID
+            },
+        );
+        if let Some(queued) = player.queued_position {
+            let (x, y) = (
+                queued.x as f32 * TILE_SIZE.0 as f32,
+                (queued.y as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32,
+            );
+            let (w, h) = (TILE_SIZE.0 as f32, TILE_SIZE.1 as f32);
+            for rect in [
+                graphics::Rect::new(x, y, w, 2.0),
+                graphics::Rect::new(x, y + h - 2.0, w, 2.0),
+                graphics::Rect::new(x, y, 2.0, h),
+                graphics::Rect::new(x + w - 2.0, y, 2.0, h),
+            ] {
+                batch.push_rect(rect, tile::TARGET_QUEUED);
+            }
+        }
+        batch.finish(canvas);
+    }
+
+    pub fn change_energy(&mut self, delta: i32, max_energy: usize) {
+        self.energy = max(0, min(self.energy as i32 + delta, max_energy as i32)) as usize;
     }
 }
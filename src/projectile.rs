@@ -1,5 +1,6 @@
 use crate::{
-    direction::Direction, entity::Entity, player::Player, tile, utils::Position, world::World,
+    balance::Balance,
+    combat_log::Actor, direction::Direction, entity::Entity, player::Player, tile, utils::Position, world::World,
     BOARD_SIZE, TILE_SIZE, WORLD_SIZE,
 };
 use ggez::graphics::{self, Canvas};
@@ -8,22 +9,14 @@ use std::{
     collections::{HashMap, LinkedList},
 };
 
-const TRACKING_PROJECTILE_DAMAGE: usize = 100;
-const TRACKING_PROJECTILE_SPEED: usize = 3;
-const LIGHTNING_DAMAGE: usize = 80;
-const LIGHTNING_SPEED: usize = 0;
-const LIGHTNING_SIZE: i16 = 2;
-const PLAYER_PROJECTILE_DAMAGE: usize = 20;
-const PLAYER_PROJECTILE_SPEED: usize = 1;
-const FIRE_DAMAGE_INITIAL: usize = 60;
-const FIRE_DAMAGE_SECONDARY: usize = 45;
-const FIRE_DAMAGE_TERTIARY: usize = 30;
-const FIRE_DAMAGE_FINAL: usize = 15;
-const FIRE_SPEED: usize = 1;
-
-const PERMISSIBLE_TILES: [[f32; 4]; 9] = [
-    tile::WATER,
+// Projectiles sink in deep water, so rivers and lakes can only be shot across at a bridge or ford.
+// Unlike the player they aren't held up by fords or mud
+const PERMISSIBLE_TILES: [[f32; 4]; 12] = [
     tile::GRASS,
+    tile::MUD,
+    tile::ICE,
+    tile::BRIDGE,
+    tile::FORD,
     tile::PLAYER,
     // tile::PROJECTILE_PLAYER,
     tile::CHASING_ENEMY,
@@ -47,13 +40,13 @@ pub struct Projectile {
 }
 
 impl Projectile {
-    pub fn tracking_projectile(x: usize, y: usize, world_pos: Position) -> Self {
+    pub fn tracking_projectile(x: usize, y: usize, world_pos: Position, balance: &Balance) -> Self {
         Projectile {
             pos: Position::new(x, y),
-            speed: 2,
+            speed: balance.projectiles.missile_speed,
             direction: Direction::North,
             color: tile::TRACKING_PROJECTILE,
-            damage: TRACKING_PROJECTILE_DAMAGE,
+            damage: balance.projectiles.missile_damage,
             world_pos,
         }
     }
@@ -63,35 +56,85 @@ impl Projectile {
         y: usize,
         direction: Direction,
         world_pos: Position,
+        balance: &Balance,
     ) -> Self {
         Projectile {
             pos: Position::new(x, y),
-            speed: PLAYER_PROJECTILE_SPEED,
+            speed: balance.projectiles.shot_speed,
             direction,
             color: tile::PROJECTILE_PLAYER,
-            damage: PLAYER_PROJECTILE_DAMAGE,
+            damage: balance.projectiles.shot_damage,
             world_pos,
         }
     }
 
-    pub fn lightning(x: usize, y: usize, world_pos: Position) -> Self {
+    pub fn lightning(x: usize, y: usize, world_pos: Position, balance: &Balance) -> Self {
         Projectile {
             pos: Position::new(x, y),
-            speed: LIGHTNING_SPEED,
+            speed: balance.projectiles.lightning_speed,
             direction: Direction::North,
             color: tile::LIGHTNING_PLACEHOLDER,
-            damage: LIGHTNING_DAMAGE,
+            damage: balance.projectiles.lightning_damage,
             world_pos,
         }
     }
 
-    pub fn player_fire(x: usize, y: usize, direction: Direction, world_pos: Position) -> Self {
+    // Every tile a lightning strike at pos hits: the strike itself and a star reaching size tiles
+    // out in all eight directions, cut off at the edges of the world. The center is in here once
+    // for every step out, so it gets hit that many times
+    pub fn lightning_area(pos: Position, size: i16) -> Vec<Position> {
+        const deltas: [i16; 3] = [0, 1, -1];
+        let mut area = Vec::new();
+        for i in 1..=size {
+            for x_delta in deltas {
+                for y_delta in deltas {
+                    if pos.x < (WORLD_SIZE.0 - x_delta * i) as usize
+                        && pos.y < (WORLD_SIZE.1 - y_delta * i) as usize
+                        && pos.x as i16 >= -(x_delta * i)
+                        && pos.y as i16 >= -(y_delta * i)
+                    {
+                        let new_position = Position::new(
+                            (pos.x as i16 + (x_delta * i)) as usize,
+                            (pos.y as i16 + (y_delta * i)) as usize,
+                        );
+                        area.push(new_position);
+                    }
+                }
+            }
+        }
+        area
+    }
+
+    // What the log calls whatever hit an enemy with this projectile
+    pub fn attack_name(&self) -> &'static str {
+        match self.color {
+            tile::TRACKING_PROJECTILE => "missile",
+            tile::LIGHTNING_PLACEHOLDER
+            | tile::LIGHTNING_INITIAL
+            | tile::LIGHTNING_SECONDARY
+            | tile::LIGHTNING_FINAL => "lightning",
+            tile::FIRE_PLACEHOLDER
+            | tile::FIRE_INITIAL
+            | tile::FIRE_SECONDARY
+            | tile::FIRE_TERTIARY
+            | tile::FIRE_FINAL => "fire",
+            _ => "shot",
+        }
+    }
+
+    pub fn player_fire(
+        x: usize,
+        y: usize,
+        direction: Direction,
+        world_pos: Position,
+        balance: &Balance,
+    ) -> Self {
         Projectile {
             pos: Position::new(x, y),
-            speed: FIRE_SPEED,
+            speed: balance.projectiles.fire_speed,
             direction,
             color: tile::FIRE_PLACEHOLDER,
-            damage: FIRE_DAMAGE_INITIAL,
+            damage: balance.projectiles.fire_damage[0],
             world_pos,
         }
     }
@@ -134,61 +177,27 @@ impl Projectile {
                         .insert(pos, tile::LIGHTNING_SECONDARY);
                 }
                 tile::LIGHTNING_SECONDARY => {
-                    const deltas: [i16; 3] = [0, 1, -1];
                     let pos = world.projectiles[index as usize].pos;
                     let world_pos = world.projectiles[index as usize].world_pos;
                     world.projectiles[index as usize].color = tile::LIGHTNING_FINAL;
-                    // basically checks the 8 around and including the projectile and turns
-                    // them to their original state
-                    for i in 1..=LIGHTNING_SIZE {
-                        for x_delta in deltas {
-                            for y_delta in deltas {
-                                if pos.x < (WORLD_SIZE.0 - x_delta * i) as usize
-                                    && pos.y < (WORLD_SIZE.1 - y_delta * i) as usize
-                                    && pos.x as i16 >= -(x_delta * i)
-                                    && pos.y as i16 >= -(y_delta * i)
-                                {
-                                    let new_position = Position::new(
-                                        (pos.x as i16 + (x_delta * i)) as usize,
-                                        (pos.y as i16 + (y_delta * i)) as usize,
-                                    );
-                                    world.atmosphere_map[world_pos.y][world_pos.x]
-                                        .insert(new_position, tile::LIGHTNING_FINAL);
-                                    for enemy in &mut world.enemies_map[world.world_position.y][world.world_position.x] {
-                                        if enemy.pos.contains(&new_position) {
-                                            enemy.damage(LIGHTNING_DAMAGE);
-                                        }
-                                    }
-                                }
+                    for new_position in Self::lightning_area(pos, world.balance.projectiles.lightning_size) {
+                        world.atmosphere_map[world_pos.y][world_pos.x]
+                            .insert(new_position, tile::LIGHTNING_FINAL);
+                        for enemy in &mut world.enemies_map[world.world_position.y][world.world_position.x] {
+                            if enemy.pos.contains(&new_position) {
+                                enemy.damage(world.balance.projectiles.lightning_damage, Actor::PlayerAttack("lightning"));
                             }
                         }
                     }
                 }
                 tile::LIGHTNING_FINAL => {
-                    const deltas: [i16; 3] = [0, 1, -1];
                     let pos = world.projectiles[index as usize].pos;
                     let world_pos = world.projectiles[index as usize].world_pos;
                     world.projectiles.remove(index as usize);
                     index -= 1;
-                    // basically checks the 8 around and including the projectile and turns
-                    // them to their original state
-                    for i in 1..=LIGHTNING_SIZE {
-                        for x_delta in deltas {
-                            for y_delta in deltas {
-                                if pos.x < (WORLD_SIZE.0 - x_delta * i) as usize
-                                    && pos.y < (WORLD_SIZE.1 - y_delta * i) as usize
-                                    && pos.x as i16 >= -(x_delta * i)
-                                    && pos.y as i16 >= -(y_delta * i)
-                                {
-                                    let new_position = Position::new(
-                                        (pos.x as i16 + (x_delta * i)) as usize,
-                                        (pos.y as i16 + (y_delta * i)) as usize,
-                                    );
-                                    world.atmosphere_map[world_pos.y][world_pos.x]
-                                        .remove(&new_position);
-                                }
-                            }
-                        }
+                    // turns the whole strike back to its original state
+                    for new_position in Self::lightning_area(pos, world.balance.projectiles.lightning_size) {
+                        world.atmosphere_map[world_pos.y][world_pos.x].remove(&new_position);
                     }
                 }
                 tile::FIRE_PLACEHOLDER => {
@@ -198,7 +207,7 @@ impl Projectile {
                     world.atmosphere_map[world_pos.y][world_pos.x].insert(pos, tile::FIRE_INITIAL);
                     for enemy in &mut world.enemies_map[world.world_position.y][world.world_position.x] {
                         if enemy.pos.contains(&pos) {
-                            enemy.damage(FIRE_DAMAGE_INITIAL);
+                            enemy.damage(world.balance.projectiles.fire_damage[0], Actor::PlayerAttack("fire"));
                         }
                     }
                 }
@@ -246,7 +255,7 @@ impl Projectile {
                     for enemy in &mut world.enemies_map[world.world_position.y][world.world_position.x] {
                         for new_pos in &new_positions {
                             if enemy.pos.contains(&new_pos) {
-                                enemy.damage(FIRE_DAMAGE_SECONDARY);
+                                enemy.damage(world.balance.projectiles.fire_damage[1], Actor::PlayerAttack("fire"));
                             }
                         }
                     }
@@ -320,7 +329,7 @@ impl Projectile {
                     for enemy in &mut world.enemies_map[world.world_position.y][world.world_position.x] {
                         for new_pos in &new_positions {
                             if enemy.pos.contains(&*new_pos) {
-                                enemy.damage(FIRE_DAMAGE_TERTIARY);
+                                enemy.damage(world.balance.projectiles.fire_damage[2], Actor::PlayerAttack("fire"));
                             }
                         }
                     }
@@ -392,7 +401,7 @@ impl Projectile {
                     for enemy in &mut world.enemies_map[world.world_position.y][world.world_position.x] {
                         for new_pos in &new_positions {
                             if enemy.pos.contains(&new_pos) {
-                                enemy.damage(FIRE_DAMAGE_FINAL);
+                                enemy.damage(world.balance.projectiles.fire_damage[3], Actor::PlayerAttack("fire"));
                             }
                         }
                     }
@@ -481,7 +490,7 @@ impl Projectile {
                     let mut index_enemy: i32 = 0;
                     for _ in 0..world.enemies_map[world.world_position.y][world.world_position.x].len() {
                         if (world.enemies_map[world.world_position.y][world.world_position.x][index_enemy as usize].pos.contains(&new_pos)) {
-                            world.enemies_map[world.world_position.y][world.world_position.x][index_enemy as usize].damage(TRACKING_PROJECTILE_DAMAGE);
+                            world.enemies_map[world.world_position.y][world.world_position.x][index_enemy as usize].damage(world.balance.projectiles.missile_damage, Actor::PlayerAttack("missile"));
                             return (true, true);
                         }
                         index_enemy += 1;
@@ -3,6 +3,8 @@ use rand::rngs::ThreadRng;
 use rand::Rng;
 use rand_chacha::ChaCha8Rng;
 
+use crate::utils::Position;
+
 pub fn rand_range(rng: &mut ChaCha8Rng, a: i16, b: i16) -> i16 {
     rng.gen_range(a..b)
 }
@@ -14,3 +16,22 @@ pub fn rand_fraction(rng: &mut ChaCha8Rng) -> f32 {
 pub fn bernoulli(rng: &mut ChaCha8Rng, p: f32) -> bool {
     rand_range(rng, 0, 1000) < ((1000. * p) as i16)
 }
+
+// A number between 0 and 1 that only depends on which tile it is for, so things that only change
+// how the game looks (like the color of water) don't use up the gameplay rng. Asking for the same
+// tile and channel always gives back the same number
+pub fn tile_noise(world_pos: Position, pos: Position, channel: u64) -> f32 {
+    let x = ((world_pos.y * 7 + world_pos.x) as u64) << 32
+        ^ (pos.y as u64) << 16
+        ^ pos.x as u64
+        ^ channel.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    // mixing the bits makes neighbouring tiles look unrelated
+    (mix(x) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+// splitmix64 finalizer, turns similar numbers into completely different ones
+pub fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
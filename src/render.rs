@@ -0,0 +1,70 @@
+use crate::{
+    tileset::{TileBatch, TileLayer, Tileset},
+    utils::Position,
+    viewport::Viewport,
+    world::World,
+};
+use ggez::{graphics::Canvas, Context};
+use std::collections::HashMap;
+
+// Everything the renderer keeps around between frames. Terrain hardly ever changes, so each
+// world's terrain is batched once and only rebuilt when the world's terrain generation moves on
+pub struct Renderer {
+    // None if the tileset couldn't be loaded, everything is drawn with flat colors then
+    tileset: Option<Tileset>,
+    // the terrain generation each batch was built at
+    terrain: HashMap<Position, (u64, TileLayer)>,
+    pub viewport: Viewport,
+    // whether enemies get little health bars over them
+    pub show_health_bars: bool,
+}
+
+impl Renderer {
+    pub fn new(ctx: &Context) -> Self {
+        Renderer {
+            tileset: Tileset::load(ctx),
+            terrain: HashMap::new(),
+            viewport: Viewport::new(ctx),
+            show_health_bars: true,
+        }
+    }
+
+    pub fn tileset(&self) -> Option<&Tileset> {
+        self.tileset.as_ref()
+    }
+
+    // Switches between the sprites and the flat colors, returns false if there is no tileset to
+    // switch to
+    pub fn toggle_theme(&mut self) -> bool {
+        if let Some(tileset) = self.tileset.as_mut() {
+            tileset.enabled = !tileset.enabled;
+            self.terrain.clear();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn draw_terrain(
+        &mut self,
+        ctx: &Context,
+        canvas: &mut Canvas,
+        world_pos: Position,
+        terrain: &HashMap<Position, [f32; 4]>,
+        generation: u64,
+    ) {
+        let stale = match self.terrain.get(&world_pos) {
+            Some((built_at, _)) => *built_at != generation,
+            None => true,
+        };
+        if stale {
+            let mut batch = TileBatch::new(ctx, self.tileset.as_ref());
+            for (loc, color) in terrain {
+                batch.push_shaded(*loc, *color, World::related_color(world_pos, *loc, *color));
+            }
+            let layer = batch.build();
+            self.terrain.insert(world_pos, (generation, layer));
+        }
+        self.terrain[&world_pos].1.draw(canvas);
+    }
+}
@@ -0,0 +1,372 @@
+use crate::{
+    random, tile,
+    utils::Position,
+    world::{World, BOSS_ROOMS},
+    BOARD_SIZE, WORLD_SIZE,
+};
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashSet;
+
+// How close the middle of a river gets to the edges of a world, other than where it flows into
+// the next one
+const MARGIN: i16 = 6;
+// Spots along a river tried for each bridge or ford before giving up on it
+const CROSSING_TRIES: usize = 20;
+// Crossings any longer than this would be running along the river instead of across it
+const MAX_CROSSING: i16 = 8;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+// A river running from one edge of the board to the other, with bridges and fords across it
+pub struct River {
+    // every world it flows through, from where it starts
+    pub worlds: Vec<Position>,
+}
+
+impl River {
+    // Carves rivers across the board. They go around boss rooms and boss wings, and get their
+    // bridges and fords in every world they flow through
+    pub fn gen_rivers(
+        world: &mut World,
+        rng: &mut ChaCha8Rng,
+        count: usize,
+        width: usize,
+        bridges: usize,
+        fords: usize,
+    ) {
+        for _ in 0..count {
+            if let Some(river) = Self::gen_river(world, rng, width, bridges, fords) {
+                world.rivers.push(river);
+            }
+        }
+    }
+
+    // Whether any river flows through the world
+    pub fn flows_through(world: &World, world_pos: Position) -> bool {
+        world
+            .rivers
+            .iter()
+            .any(|river| river.worlds.contains(&world_pos))
+    }
+
+    fn gen_river(
+        world: &mut World,
+        rng: &mut ChaCha8Rng,
+        width: usize,
+        bridges: usize,
+        fords: usize,
+    ) -> Option<River> {
+        // rivers flow west to east or north to south
+        let flow = if random::bernoulli(rng, 0.5) {
+            (1, 0)
+        } else {
+            (0, 1)
+        };
+        let worlds = BOARD_SIZE.0 / WORLD_SIZE.0;
+        let start = random::rand_range(rng, 0, worlds);
+        let mut course = vec![if flow == (1, 0) {
+            (0, start)
+        } else {
+            (start, 0)
+        }];
+        if !Self::extend_course(world, rng, flow, &mut course) {
+            return None;
+        }
+
+        // where the river crosses each edge along the way, counting the edges of the board it
+        // starts and ends at
+        let crossings: Vec<i16> = (0..=course.len())
+            .map(|_| random::rand_range(rng, MARGIN, WORLD_SIZE.0 - MARGIN))
+            .collect();
+        let reach = (width as i16 / 2).min(MARGIN - 2);
+        let mut water = HashSet::new();
+        let mut middles = Vec::new();
+        for (i, &world_pos) in course.iter().enumerate() {
+            let back = match i {
+                0 => (-flow.0, -flow.1),
+                _ => (course[i - 1].0 - world_pos.0, course[i - 1].1 - world_pos.1),
+            };
+            let ahead = match course.get(i + 1) {
+                Some(next) => (next.0 - world_pos.0, next.1 - world_pos.1),
+                None => flow,
+            };
+            let middle = meander(
+                rng,
+                edge_point(back, crossings[i]),
+                edge_point(ahead, crossings[i + 1]),
+            );
+            for pos in &middle {
+                let center = (
+                    world_pos.0 * WORLD_SIZE.0 + pos.0,
+                    world_pos.1 * WORLD_SIZE.1 + pos.1,
+                );
+                for dx in -reach..=reach {
+                    for dy in -reach..=reach {
+                        let board_pos = (center.0 + dx, center.1 + dy);
+                        if Self::carve(world, board_pos) {
+                            water.insert(board_pos);
+                        }
+                    }
+                }
+            }
+            middles.push(middle);
+        }
+
+        for (&world_pos, middle) in course.iter().zip(&middles) {
+            for (count, crossing) in [(bridges, tile::BRIDGE), (fords, tile::FORD)] {
+                for _ in 0..count {
+                    Self::gen_crossing(world, rng, world_pos, middle, &water, crossing);
+                }
+            }
+        }
+        Some(River {
+            worlds: course
+                .into_iter()
+                .map(|(x, y)| Position::new(x as usize, y as usize))
+                .collect(),
+        })
+    }
+
+    // Looks for a way to the far edge of the board one world at a time, mostly keeping on in the
+    // direction the river flows. Backs up out of dead ends
+    fn extend_course(
+        world: &World,
+        rng: &mut ChaCha8Rng,
+        flow: (i16, i16),
+        course: &mut Vec<(i16, i16)>,
+    ) -> bool {
+        let worlds = BOARD_SIZE.0 / WORLD_SIZE.0;
+        let last = *course.last().unwrap();
+        if (flow == (1, 0) && last.0 == worlds - 1) || (flow == (0, 1) && last.1 == worlds - 1) {
+            return true;
+        }
+        let mut sides = [(flow.1, flow.0), (-flow.1, -flow.0)];
+        if random::bernoulli(rng, 0.5) {
+            sides.swap(0, 1);
+        }
+        let steps = if random::bernoulli(rng, 0.7) {
+            [flow, sides[0], sides[1]]
+        } else {
+            [sides[0], flow, sides[1]]
+        };
+        for step in steps {
+            let next = (last.0 + step.0, last.1 + step.1);
+            if next.0 < 0 || next.0 >= worlds || next.1 < 0 || next.1 >= worlds {
+                continue;
+            }
+            if course.contains(&next) || Self::is_blocked(world, next) {
+                continue;
+            }
+            course.push(next);
+            if Self::extend_course(world, rng, flow, course) {
+                return true;
+            }
+            course.pop();
+        }
+        false
+    }
+
+    fn is_blocked(world: &World, world_pos: (i16, i16)) -> bool {
+        let world_pos = Position::new(world_pos.0 as usize, world_pos.1 as usize);
+        BOSS_ROOMS.contains(&world_pos)
+            || world.wings.iter().any(|wing| wing.world_pos == world_pos)
+    }
+
+    // Turns a tile of the board into water, unless something is standing there or another
+    // river's crossing is already on it. Returns whether it did
+    fn carve(world: &mut World, board_pos: (i16, i16)) -> bool {
+        let (x, y) = board_pos;
+        if x < 0 || x >= BOARD_SIZE.0 || y < 0 || y >= BOARD_SIZE.1 {
+            return false;
+        }
+        let world_pos = (x / WORLD_SIZE.0, y / WORLD_SIZE.1);
+        if Self::is_blocked(world, world_pos) {
+            return false;
+        }
+        let pos = Position::new((x % WORLD_SIZE.0) as usize, (y % WORLD_SIZE.1) as usize);
+        let (world_x, world_y) = (world_pos.0 as usize, world_pos.1 as usize);
+        if world.entity_map[world_y][world_x].contains_key(&pos) {
+            return false;
+        }
+        let terrain = &mut world.terrain_map[world_y][world_x];
+        if matches!(terrain.get(&pos), Some(&tile::BRIDGE) | Some(&tile::FORD)) {
+            return false;
+        }
+        terrain.insert(pos, tile::WATER);
+        true
+    }
+
+    // Lays a bridge or ford straight across the river somewhere in the world, as long as there's
+    // open ground on both ends of it
+    fn gen_crossing(
+        world: &mut World,
+        rng: &mut ChaCha8Rng,
+        world_pos: (i16, i16),
+        middle: &[(i16, i16)],
+        water: &HashSet<(i16, i16)>,
+        crossing: [f32; 4],
+    ) {
+        let terrain = &world.terrain_map[world_pos.1 as usize][world_pos.0 as usize];
+        let corner = (world_pos.0 * WORLD_SIZE.0, world_pos.1 * WORLD_SIZE.1);
+        let is_river = |pos: (i16, i16)| {
+            water.contains(&(corner.0 + pos.0, corner.1 + pos.1))
+                && terrain.get(&Position::new(pos.0 as usize, pos.1 as usize)) == Some(&tile::WATER)
+        };
+        for _ in 0..CROSSING_TRIES {
+            let i = random::rand_range(rng, 0, middle.len() as i16 - 1) as usize;
+            let (from, to) = (middle[i], middle[i + 1]);
+            if !in_bounds(from, MARGIN) {
+                continue;
+            }
+            // straight across the way the river is flowing there
+            let across = (to.1 - from.1, to.0 - from.0);
+            let mut tiles = vec![from];
+            let mut ends = Vec::new();
+            for side in [across, (-across.0, -across.1)] {
+                let mut pos = (from.0 + side.0, from.1 + side.1);
+                while is_river(pos) && tiles.len() < MAX_CROSSING as usize {
+                    tiles.push(pos);
+                    pos = (pos.0 + side.0, pos.1 + side.1);
+                }
+                ends.push(pos);
+            }
+            let open = |pos: &(i16, i16)| {
+                in_bounds(*pos, 1)
+                    && !terrain.contains_key(&Position::new(pos.0 as usize, pos.1 as usize))
+            };
+            if !is_river(from) || !ends.iter().all(open) {
+                continue;
+            }
+            for pos in tiles {
+                world.terrain_map[world_pos.1 as usize][world_pos.0 as usize]
+                    .insert(Position::new(pos.0 as usize, pos.1 as usize), crossing);
+            }
+            return;
+        }
+    }
+}
+
+// Whether a tile of a world is at least margin tiles in from every edge
+fn in_bounds(pos: (i16, i16), margin: i16) -> bool {
+    let range = margin..WORLD_SIZE.0 - margin;
+    range.contains(&pos.0) && range.contains(&pos.1)
+}
+
+// The tile on one edge of a world, side being the way out of the world through that edge, at
+// offset along it
+fn edge_point(side: (i16, i16), offset: i16) -> (i16, i16) {
+    match side {
+        (1, 0) => (WORLD_SIZE.0 - 1, offset),
+        (-1, 0) => (0, offset),
+        (0, 1) => (offset, WORLD_SIZE.1 - 1),
+        _ => (offset, 0),
+    }
+}
+
+// The middle of a river from one tile of a world to another. Mostly heads for where it's going,
+// wandering off to the sides now and then away from the edges of the world
+fn meander(rng: &mut ChaCha8Rng, from: (i16, i16), to: (i16, i16)) -> Vec<(i16, i16)> {
+    const DIRECTIONS: [(i16, i16); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+    let mut middle = vec![from];
+    let mut pos = from;
+    while pos != to {
+        let wander = in_bounds(pos, MARGIN)
+            && middle.len() < 4 * WORLD_SIZE.0 as usize
+            && random::bernoulli(rng, 0.3);
+        let step = if wander {
+            DIRECTIONS[random::rand_range(rng, 0, 4) as usize]
+        } else {
+            let (dx, dy) = (to.0 - pos.0, to.1 - pos.1);
+            // the further it has to go one way, the more likely it heads that way
+            if random::rand_range(rng, 0, dx.abs() + dy.abs()) < dx.abs() {
+                (dx.signum(), 0)
+            } else {
+                (0, dy.signum())
+            }
+        };
+        let next = (pos.0 + step.0, pos.1 + step.1);
+        if wander && !in_bounds(next, MARGIN) {
+            continue;
+        }
+        pos = next;
+        middle.push(pos);
+    }
+    middle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    const SEEDS: [u64; 4] = [0, 1, 42, 1234];
+
+    fn world_with_rivers(seed: u64) -> World {
+        let mut world = World::empty();
+        River::gen_rivers(&mut world, &mut ChaCha8Rng::seed_from_u64(seed), 2, 3, 1, 1);
+        world
+    }
+
+    #[test]
+    fn rivers_reach_the_far_edge() {
+        let last = (BOARD_SIZE.0 / WORLD_SIZE.0 - 1) as usize;
+        let edge = WORLD_SIZE.0 as usize - 1;
+        for seed in SEEDS {
+            let world = world_with_rivers(seed);
+            assert!(!world.rivers.is_empty(), "no rivers with seed {}", seed);
+            for river in &world.rivers {
+                let (start, end) = (river.worlds[0], *river.worlds.last().unwrap());
+                for pair in river.worlds.windows(2) {
+                    assert_eq!(
+                        pair[0].x.abs_diff(pair[1].x) + pair[0].y.abs_diff(pair[1].y),
+                        1,
+                        "river skips a world with seed {}",
+                        seed
+                    );
+                }
+                // whether there's water all the way out to one edge of a world
+                let wet = |world_pos: Position, tile_pos: &dyn Fn(usize) -> Position| {
+                    (0..WORLD_SIZE.0 as usize).any(|i| {
+                        matches!(
+                            world.terrain_map[world_pos.y][world_pos.x].get(&tile_pos(i)),
+                            Some(&tile::WATER) | Some(&tile::BRIDGE) | Some(&tile::FORD)
+                        )
+                    })
+                };
+                let west_to_east = start.x == 0
+                    && end.x == last
+                    && wet(start, &|y| Position::new(0, y))
+                    && wet(end, &|y| Position::new(edge, y));
+                let north_to_south = start.y == 0
+                    && end.y == last
+                    && wet(start, &|x| Position::new(x, 0))
+                    && wet(end, &|x| Position::new(x, edge));
+                assert!(
+                    west_to_east || north_to_south,
+                    "river from {:?} to {:?} stops short with seed {}",
+                    start,
+                    end,
+                    seed
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rivers_go_around_boss_rooms() {
+        for seed in SEEDS {
+            let world = world_with_rivers(seed);
+            for room in BOSS_ROOMS {
+                assert!(
+                    world.terrain_map[room.y][room.x].is_empty(),
+                    "a river runs through the boss room at {:?} with seed {}",
+                    room,
+                    seed
+                );
+                assert!(world
+                    .rivers
+                    .iter()
+                    .all(|river| !river.worlds.contains(&room)));
+            }
+        }
+    }
+}
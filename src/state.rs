@@ -1,8 +1,18 @@
+use crate::balance::{Balance, BALANCE_RELOAD_FRAMES};
+use crate::boss_script::BossScripts;
+use crate::camera::Camera;
+use crate::combat_log::CombatLog;
+use crate::damage_number::DamageNumber;
+use crate::difficulty::Difficulty;
 use crate::direction::Direction;
 use crate::enemy::Enemy;
+use crate::mode::{Challenge, GameMode, HighScores};
 use crate::player::Player;
+use crate::undo::UndoStack;
 use crate::utils::Boss;
 use crate::utils::Position;
+use crate::wing::Wing;
+use crate::worldgen::{WorldGen, DEFAULT_PRESET};
 use crate::UNIVERSAL_OFFSET;
 use ggez::audio;
 use ggez::audio::SoundSource;
@@ -13,7 +23,9 @@ use std::io::Write;
 
 use crate::{
     entity::Entity,
+    render::Renderer,
     projectile::Projectile,
+    structure::Structure,
     tile,
     world::{World, BOSS_ROOMS, FINAL_BOSS_ROOM},
     BOARD_SIZE, SCREEN_SIZE, TILE_SIZE, WORLD_SIZE,
@@ -24,14 +36,35 @@ use rand::rngs::ThreadRng;
 use rand_chacha::ChaCha8Rng;
 
 use ggez::{
+    conf::FullscreenType,
     event,
     glam::*,
     graphics::{self, Canvas},
-    input::keyboard::{KeyCode, KeyInput},
+    input::keyboard::{KeyCode, KeyInput, KeyMods},
     Context, GameError, GameResult,
 };
 
 pub const RNG_SEED: u64 = 0;
+// Opens and closes the full screen world map, doesn't use up a turn
+const WORLD_MAP_KEYCODE: KeyCode = KeyCode::Tab;
+// Switches between the camera that shows one world at a time and the one that follows the player
+const CAMERA_MODE_KEYCODE: KeyCode = KeyCode::C;
+// Switches between the tileset sprites and the flat colors
+const THEME_KEYCODE: KeyCode = KeyCode::G;
+const FULLSCREEN_KEYCODE: KeyCode = KeyCode::F11;
+// Make the game bigger or smaller inside the window, or go back to the biggest size that fits
+const SCALE_UP_KEYCODE: KeyCode = KeyCode::Equals;
+const SCALE_DOWN_KEYCODE: KeyCode = KeyCode::Minus;
+const SCALE_RESET_KEYCODE: KeyCode = KeyCode::Key0;
+// Shows or hides the health bars over enemies
+const HEALTH_BARS_KEYCODE: KeyCode = KeyCode::V;
+// Opens and closes the combat log, and scrolls through it while it's open
+const COMBAT_LOG_KEYCODE: KeyCode = KeyCode::J;
+const COMBAT_LOG_OLDER_KEYCODE: KeyCode = KeyCode::PageUp;
+const COMBAT_LOG_NEWER_KEYCODE: KeyCode = KeyCode::PageDown;
+// Takes back the last turn, or UNDO_LONG_REWIND_TURNS turns while holding shift. Not in hardcore
+const UNDO_KEYCODE: KeyCode = KeyCode::U;
+const UNDO_LONG_REWIND_TURNS: usize = 10;
 // const MOVES_TILL_ENERGY_REGEN: usize = 5;
 
 // #[derive(serde::Deserialize, serde::Serialize)]
@@ -45,11 +78,66 @@ pub struct State {
     pub rng: Option<ChaCha8Rng>,
     player_curr_world_position: Position,
     death_font_size: f32,
+    // whether the full screen world map is open instead of the current world
+    show_world_map: bool,
+    camera: Camera,
+    renderer: Renderer,
+    combat_log: CombatLog,
+    // hardcore games can't be undone
+    hardcore: bool,
+    // None when undoing is turned off
+    undo: Option<UndoStack>,
+    // what the next new game started from the title screen will be played on
+    difficulty: Difficulty,
+    // and what kind of game it will be
+    mode: GameMode,
+    // the worldgen.ron preset its world will be made with
+    preset: String,
+    world_gen: WorldGen,
+    high_scores: HighScores,
+    // whether the boss rush or arena game that just ended set a new best
+    new_high_score: bool,
+    // loaded from balance.ron, and reloaded whenever it changes in debug builds
+    balance: Balance,
+    // what balance.ron said the last time it was read, to tell when it changes
+    balance_text: Option<String>,
+    // loaded from bosses.ron, and reloaded the same way as the balance
+    boss_scripts: BossScripts,
+    boss_scripts_text: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Metadata", default)]
+// Things about a saved game that aren't part of the world itself. The undo history isn't saved,
+// only whether the game allows undoing at all
+struct SaveMetadata {
+    hardcore: bool,
+    undo_enabled: bool,
+    // the worldgen.ron preset the world was made with
+    preset: String,
+}
+
+impl Default for SaveMetadata {
+    // saves from before there was any metadata are regular games in the default world
+    fn default() -> Self {
+        SaveMetadata {
+            hardcore: false,
+            undo_enabled: true,
+            preset: DEFAULT_PRESET.to_string(),
+        }
+    }
 }
 
 impl State {
     // just returns the default values
-    pub fn new(ctx: &mut Context, title_screen: bool) -> GameResult<State> {
+    pub fn new(
+        ctx: &mut Context,
+        title_screen: bool,
+        hardcore: bool,
+        difficulty: Difficulty,
+        mode: GameMode,
+        preset: &str,
+    ) -> GameResult<State> {
         let songs = [
             audio::Source::new(ctx, "/overworld.ogg")?,
             audio::Source::new(ctx, "/final_boss.ogg")?,
@@ -61,15 +149,50 @@ impl State {
             audio::Source::new(ctx, "/Sad_Violin_-_Sound_Effect_(HD).ogg")?
         ];
         let mut rng = ChaCha8Rng::seed_from_u64(RNG_SEED);
+        let balance = Balance::load(ctx);
+        let boss_scripts = BossScripts::load(ctx);
+        let world_gen = WorldGen::load(ctx);
+        let mut world = World::new(
+            &mut rng,
+            difficulty,
+            &balance,
+            &boss_scripts,
+            world_gen.preset(preset),
+        );
+        Challenge::start(&mut world, mode, &mut rng);
+        // a challenge starts out in a room, which shouldn't be entered again on the first turn
+        let mut combat_log = CombatLog::new();
+        combat_log.record_turn(std::mem::take(&mut world.events));
         let temp = State {
             should_draw: true,
             command: false,
             songs,
-            world: Some(World::new(&mut rng)),
+            world: Some(world),
             title_screen,
             rng: Some(rng),
             player_curr_world_position: Position::new(0, 0),
-            death_font_size: 0.
+            death_font_size: 0.,
+            show_world_map: false,
+            camera: Camera::new(),
+            renderer: Renderer::new(ctx),
+            combat_log,
+            hardcore,
+            // scores only count if the game can't be taken back
+            undo: if hardcore || mode.is_challenge() {
+                None
+            } else {
+                Some(UndoStack::new())
+            },
+            difficulty,
+            mode,
+            preset: preset.to_string(),
+            world_gen,
+            high_scores: HighScores::load(),
+            new_high_score: false,
+            balance,
+            balance_text: Balance::read(ctx),
+            boss_scripts,
+            boss_scripts_text: BossScripts::read(ctx),
         };
         Ok(temp)
     }
@@ -94,11 +217,41 @@ impl State {
             title_screen: true,
             rng: None,
             player_curr_world_position: Position::new(0, 0),
-            death_font_size: 0.
+            death_font_size: 0.,
+            show_world_map: false,
+            camera: Camera::new(),
+            renderer: Renderer::new(ctx),
+            combat_log: CombatLog::new(),
+            hardcore: false,
+            undo: None,
+            difficulty: Difficulty::default(),
+            mode: GameMode::default(),
+            preset: DEFAULT_PRESET.to_string(),
+            world_gen: WorldGen::load(ctx),
+            high_scores: HighScores::load(),
+            new_high_score: false,
+            balance: Balance::load(ctx),
+            balance_text: Balance::read(ctx),
+            boss_scripts: BossScripts::load(ctx),
+            boss_scripts_text: BossScripts::read(ctx),
         })
     }
 
-    pub fn from(world: World, ctx: &mut Context, rng: ChaCha8Rng) -> GameResult<State> {
+    fn from(
+        mut world: World,
+        ctx: &mut Context,
+        rng: ChaCha8Rng,
+        metadata: SaveMetadata,
+    ) -> GameResult<State> {
+        let difficulty = world.difficulty;
+        let mode = world.challenge.mode;
+        // what the player can see isn't saved, so work it out again for loaded worlds
+        world.update_vision();
+        let balance = Balance::load(ctx);
+        world.balance = balance.clone();
+        let boss_scripts = BossScripts::load(ctx);
+        world.boss_scripts = boss_scripts.clone();
+        Boss::restore_kinds(&mut world);
         let songs = [
             audio::Source::new(ctx, "/overworld.ogg")?,
             audio::Source::new(ctx, "/final_boss.ogg")?,
@@ -117,7 +270,27 @@ impl State {
             title_screen: false,
             rng: Some(rng),
             player_curr_world_position: Position::new(0, 0),
-            death_font_size: 0.
+            death_font_size: 0.,
+            show_world_map: false,
+            camera: Camera::new(),
+            renderer: Renderer::new(ctx),
+            combat_log: CombatLog::new(),
+            hardcore: metadata.hardcore,
+            undo: if metadata.undo_enabled && !metadata.hardcore {
+                Some(UndoStack::new())
+            } else {
+                None
+            },
+            difficulty,
+            mode,
+            preset: metadata.preset,
+            world_gen: WorldGen::load(ctx),
+            high_scores: HighScores::load(),
+            new_high_score: false,
+            balance,
+            balance_text: Balance::read(ctx),
+            boss_scripts,
+            boss_scripts_text: BossScripts::read(ctx),
         };
         Ok(temp)
     }
@@ -125,7 +298,19 @@ impl State {
 
 impl ggez::event::EventHandler<GameError> for State {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
+        if cfg!(debug_assertions) && ctx.time.ticks() % BALANCE_RELOAD_FRAMES == 0 {
+            self.reload_balance(ctx);
+            self.reload_boss_scripts(ctx);
+        }
         if !self.title_screen {
+            // keep redrawing while the camera scrolls towards the player
+            if self.camera.update(self.world.as_ref().unwrap()) {
+                self.should_draw = true;
+            }
+            // and while damage numbers are still fading out
+            if DamageNumber::update(&mut self.world.as_mut().unwrap().damage_numbers) {
+                self.should_draw = true;
+            }
             if !self.world.as_ref().unwrap().player.is_alive() {
                 if !self.songs[7].playing() {
                     for song in &mut self.songs {
@@ -208,6 +393,7 @@ impl ggez::event::EventHandler<GameError> for State {
         if self.title_screen {
             let mut canvas =
                 graphics::Canvas::from_frame(ctx, graphics::Color::from(tile::TITLE_SCREEN_FLOOR));
+            self.renderer.viewport.apply(&mut canvas);
             let pos = Position::new(8, 2);
             let text_spot = Vec2::new((pos.x as f32 + 0.25) * TILE_SIZE.0 as f32,  (pos.y as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32);
             let duration_left = "Very Good Game";
@@ -236,7 +422,45 @@ impl ggez::event::EventHandler<GameError> for State {
                     &*(graphics::Text::new(duration_left).set_scale(52.)),//.set_scale(48.),
                     graphics::DrawParam::from(text_spot).color(graphics::Color::from_rgb(159, 197, 232)),
                 );
-            let pos = Position::new(12, 42);
+            let pos = Position::new(13, 34);
+            let text_spot = Vec2::new((pos.x as f32 + 0.25) * TILE_SIZE.0 as f32,  (pos.y as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32);
+            // small enough to leave room for the settings listed under it
+            let duration_left = " Hardcore [H]";
+                canvas.draw(
+                    &*(graphics::Text::new(duration_left).set_scale(32.)),//.set_scale(48.),
+                    graphics::DrawParam::from(text_spot).color(graphics::Color::from_rgb(234, 153, 153)),
+                );
+            let pos = Position::new(13, 36);
+            let text_spot = Vec2::new((pos.x as f32 + 0.25) * TILE_SIZE.0 as f32,  (pos.y as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32);
+            let duration_left = format!(" Difficulty: {} [D]", self.difficulty.name());
+                canvas.draw(
+                    &*(graphics::Text::new(duration_left).set_scale(32.)),//.set_scale(48.),
+                    graphics::DrawParam::from(text_spot).color(graphics::Color::from_rgb(255, 229, 153)),
+                );
+            let pos = Position::new(13, 39);
+            let text_spot = Vec2::new((pos.x as f32 + 0.25) * TILE_SIZE.0 as f32,  (pos.y as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32);
+            let duration_left = format!(" Mode: {} [M]", self.mode.name());
+                canvas.draw(
+                    &*(graphics::Text::new(duration_left).set_scale(32.)),
+                    graphics::DrawParam::from(text_spot).color(graphics::Color::from_rgb(182, 215, 168)),
+                );
+            let pos = Position::new(13, 42);
+            let text_spot = Vec2::new((pos.x as f32 + 0.25) * TILE_SIZE.0 as f32,  (pos.y as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32);
+            let duration_left = format!(" World: {} [W]", self.preset);
+                canvas.draw(
+                    &*(graphics::Text::new(duration_left).set_scale(32.)),
+                    graphics::DrawParam::from(text_spot).color(graphics::Color::from_rgb(159, 197, 232)),
+                );
+            // the best game played so far of the picked mode
+            if let Some(best) = self.high_scores.best(self.mode, self.difficulty) {
+                let pos = Position::new(14, 45);
+                let text_spot = Vec2::new((pos.x as f32 + 0.25) * TILE_SIZE.0 as f32,  (pos.y as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32);
+                canvas.draw(
+                    &*(graphics::Text::new(format!("Best on {}: {}", self.difficulty.name(), best.describe(self.mode))).set_scale(20.)),
+                    graphics::DrawParam::from(text_spot).color(graphics::Color::from_rgb(182, 215, 168)),
+                );
+            }
+            let pos = Position::new(12, 47);
             let text_spot = Vec2::new((pos.x as f32 + 0.25) * TILE_SIZE.0 as f32,  (pos.y as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32);
             let duration_left = "By: Ishan, Michael, and Aiden";
                 canvas.draw(
@@ -246,6 +470,7 @@ impl ggez::event::EventHandler<GameError> for State {
             canvas.finish(ctx)?;
         } else if !self.world.as_mut().unwrap().player.is_alive() {
             let mut canvas = graphics::Canvas::from_frame(ctx, graphics::Color::from(tile::BLACK));
+            self.renderer.viewport.apply(&mut canvas);
             let pos = Position::new(7, 15);
             let text_spot = Vec2::new((pos.x as f32 + 0.25) * TILE_SIZE.0 as f32,  (pos.y as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32);
             let duration_left = "You died :(";
@@ -256,51 +481,57 @@ impl ggez::event::EventHandler<GameError> for State {
                 if self.death_font_size < 100.0 {
                     self.death_font_size += 0.1;
                 }
+            // so the player can see what got them
+            if let Some(cause) = self.combat_log.cause_of_death() {
+                let pos = Position::new(7, 23);
+                let text_spot = Vec2::new((pos.x as f32 + 0.25) * TILE_SIZE.0 as f32,  (pos.y as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32);
+                canvas.draw(
+                    &*(graphics::Text::new(cause).set_scale(32.)),
+                    graphics::DrawParam::from(text_spot).color(graphics::Color::WHITE),
+                );
+            }
+            self.draw_challenge_result(&mut canvas);
+            self.combat_log.draw(ctx, &mut canvas);
+            canvas.finish(ctx)?;
+        } else if self.world.as_ref().unwrap().challenge.won() {
+            let mut canvas = graphics::Canvas::from_frame(ctx, graphics::Color::from(tile::BLACK));
+            self.renderer.viewport.apply(&mut canvas);
+            let pos = Position::new(7, 15);
+            let text_spot = Vec2::new((pos.x as f32 + 0.25) * TILE_SIZE.0 as f32,  (pos.y as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32);
+            canvas.draw(
+                &*(graphics::Text::new("Boss rush cleared!").set_scale(72.)),
+                graphics::DrawParam::from(text_spot).color(graphics::Color::from_rgb(255, 229, 153)),
+            );
+            self.draw_challenge_result(&mut canvas);
             canvas.finish(ctx)?;
         } else {
             if self.should_draw {
                 let world = self.world.as_mut().unwrap();
-                let rng = self.rng.as_mut().unwrap();
-                let mut boss_room = false;
-                let mut final_boss = false;
-                for boss_room_position in BOSS_ROOMS {
-                    if world.world_position == boss_room_position {
-                        boss_room = true;
+                // the letterboxing around the game stays black
+                let mut canvas = graphics::Canvas::from_frame(ctx, graphics::Color::BLACK);
+                self.renderer.viewport.apply(&mut canvas);
+                if self.show_world_map {
+                    world.draw_full_map(ctx, &mut canvas);
+                } else {
+                    canvas.draw(
+                        &graphics::Quad,
+                        graphics::DrawParam::new()
+                            .dest_rect(graphics::Rect::new(0.0, 0.0, SCREEN_SIZE.0, SCREEN_SIZE.1))
+                            .color(world.floor_color(world.world_position)),
+                    );
+                    world.draw(ctx, &mut canvas, &self.camera, &mut self.renderer);
+                    // how far into the boss rush or arena the player is
+                    if world.challenge.mode.is_challenge() {
+                        canvas.draw(
+                            &*(graphics::Text::new(world.challenge.describe()).set_scale(20.)),
+                            graphics::DrawParam::from(Vec2::new(8.0, (UNIVERSAL_OFFSET * TILE_SIZE.1) as f32 + 4.0))
+                                .color(graphics::Color::WHITE),
+                        );
+                    }
+                    if self.combat_log.open {
+                        self.combat_log.draw(ctx, &mut canvas);
                     }
                 }
-                if world.world_position == FINAL_BOSS_ROOM {
-                    final_boss = true;
-                }
-                let mut canvas = if final_boss {
-                    graphics::Canvas::from_frame(ctx, graphics::Color::from(tile::BOSS_FLOOR))
-                } else if boss_room {
-                    graphics::Canvas::from_frame(ctx, graphics::Color::from(tile::FLOOR))
-                } else {
-                    graphics::Canvas::from_frame(ctx, graphics::Color::from(tile::GRASS))
-                    // for x in 0..WORLD_SIZE.0 {
-                    //     for y in 0..WORLD_SIZE.1 {
-                    //         let mut rng = rand::thread_rng();
-                    //         canvas.draw(
-                    //             &graphics::Quad,
-                    //             graphics::DrawParam::new()
-                    //                 .dest_rect(graphics::Rect::new_i32(
-                    //                     (x as usize * TILE_SIZE.0 as usize) as i32,
-                    //                     ((y as usize + UNIVERSAL_OFFSET as usize) as i32) * TILE_SIZE.1 as i32,
-                    //                     // (loc.x - (self.world_position.x * WORLD_SIZE.0 as usize)) as i32
-                    //                     //     * TILE_SIZE.0 as i32,
-                    //                     // (loc.y - (self.world_position.y * WORLD_SIZE.1 as usize)
-                    //                     //     + UNIVERSAL_OFFSET as usize) as i32
-                    //                     //     * TILE_SIZE.1 as i32,
-
-                    //                     TILE_SIZE.0 as i32,
-                    //                     TILE_SIZE.1 as i32,
-                    //                 ))
-                    //                 .color(World::related_color(&mut rng, tile::GRASS)),
-                    //         )
-                    //     }
-                    // }
-                };
-                world.draw(&mut canvas, rng);
 
                 //For Text
                 // let level_dest = bevy::math::Vec2::new(10.0, 10.0);
@@ -331,18 +562,74 @@ impl ggez::event::EventHandler<GameError> for State {
         input: KeyInput,
         // _repeated: bool,
     ) -> Result<(), GameError> {
+        // Window controls work everywhere, even on the title screen
+        match input.keycode {
+            Some(FULLSCREEN_KEYCODE) => {
+                let fullscreen = ctx.gfx.window().fullscreen().is_some();
+                ctx.gfx.set_fullscreen(if fullscreen {
+                    FullscreenType::Windowed
+                } else {
+                    FullscreenType::Desktop
+                })?;
+                return Ok(());
+            }
+            Some(SCALE_UP_KEYCODE) => {
+                self.renderer.viewport.increase_scale();
+                self.should_draw = true;
+                return Ok(());
+            }
+            Some(SCALE_DOWN_KEYCODE) => {
+                self.renderer.viewport.decrease_scale();
+                self.should_draw = true;
+                return Ok(());
+            }
+            Some(SCALE_RESET_KEYCODE) => {
+                self.renderer.viewport.reset_scale();
+                self.should_draw = true;
+                return Ok(());
+            }
+            _ => {}
+        }
+
         // Just takes in the user input and makes an action based off of it
         if self.title_screen {
             if let Some(key) = input.keycode {
                 if key == KeyCode::N {
                     // new game
-                    *self = Self::new(ctx, false)?;
+                    *self = Self::new(ctx, false, false, self.difficulty, self.mode, &self.preset)?;
+                } else if key == KeyCode::H {
+                    // new game without undo
+                    *self = Self::new(ctx, false, true, self.difficulty, self.mode, &self.preset)?;
+                } else if key == KeyCode::D {
+                    self.difficulty = self.difficulty.next();
+                    self.should_draw = true;
+                } else if key == KeyCode::M {
+                    self.mode = self.mode.next();
+                    self.should_draw = true;
+                } else if key == KeyCode::W {
+                    self.preset = self.world_gen.next(&self.preset);
+                    self.should_draw = true;
                 } else if key == KeyCode::L {
                     // load game
-                    *self = Self::load_save(ctx).unwrap();
+                    *self = match Self::load_save(ctx) {
+                        Some(state) => state,
+                        None => Self::new(ctx, false, false, self.difficulty, self.mode, &self.preset)?,
+                    };
                 }
             }
         } else {
+            // a finished boss rush or arena game goes back to the title screen
+            if input.keycode == Some(KeyCode::Return)
+                && Challenge::is_over(self.world.as_ref().unwrap())
+            {
+                let (difficulty, mode) = (self.difficulty, self.mode);
+                let preset = std::mem::take(&mut self.preset);
+                *self = Self::title_screen(ctx)?;
+                self.difficulty = difficulty;
+                self.mode = mode;
+                self.preset = preset;
+                return Ok(());
+            }
             if let Some(key) = input.keycode {
                 if key == KeyCode::Colon {
                     self.command = true;
@@ -354,9 +641,74 @@ impl ggez::event::EventHandler<GameError> for State {
                 }
             }
 
+            // while the map is open the game is paused, so the only thing to do is close it
+            if input.keycode == Some(WORLD_MAP_KEYCODE)
+                || (self.show_world_map && input.keycode == Some(KeyCode::Escape))
+            {
+                self.show_world_map = !self.show_world_map;
+                self.should_draw = true;
+                return Ok(());
+            } else if self.show_world_map {
+                return Ok(());
+            } else if input.keycode == Some(CAMERA_MODE_KEYCODE) {
+                self.camera.toggle_mode(self.world.as_ref().unwrap());
+                self.should_draw = true;
+                return Ok(());
+            } else if input.keycode == Some(THEME_KEYCODE) {
+                if self.renderer.toggle_theme() {
+                    self.should_draw = true;
+                }
+                return Ok(());
+            } else if input.keycode == Some(HEALTH_BARS_KEYCODE) {
+                self.renderer.show_health_bars = !self.renderer.show_health_bars;
+                self.should_draw = true;
+                return Ok(());
+            } else if input.keycode == Some(UNDO_KEYCODE) {
+                let turns = if input.mods.contains(KeyMods::SHIFT) {
+                    UNDO_LONG_REWIND_TURNS
+                } else {
+                    1
+                };
+                if let Some((mut world, rng)) =
+                    self.undo.as_mut().and_then(|undo| undo.rewind(turns))
+                {
+                    world.balance = self.balance.clone();
+                    world.boss_scripts = self.boss_scripts.clone();
+                    world.update_vision();
+                    self.camera.snap(&world);
+                    self.world = Some(world);
+                    self.rng = Some(rng);
+                    self.death_font_size = 0.;
+                    self.should_draw = true;
+                }
+                return Ok(());
+            } else if input.keycode == Some(COMBAT_LOG_KEYCODE) {
+                self.combat_log.open = !self.combat_log.open;
+                self.should_draw = true;
+                return Ok(());
+            } else if self.combat_log.open
+                && (input.keycode == Some(COMBAT_LOG_OLDER_KEYCODE)
+                    || input.keycode == Some(COMBAT_LOG_NEWER_KEYCODE))
+            {
+                let lines = if input.keycode == Some(COMBAT_LOG_OLDER_KEYCODE) { 5 } else { -5 };
+                self.combat_log.scroll(lines);
+                self.should_draw = true;
+                return Ok(());
+            }
+
             let world = self.world.as_mut().unwrap();
 
+            // the game from before this turn, in case it gets undone
+            let snapshot = self
+                .undo
+                .as_ref()
+                .map(|_| UndoStack::snapshot(world, self.rng.as_ref().unwrap()));
             if Player::use_input(input, world, self.rng.as_mut().unwrap()) {
+                if let (Some(undo), Some(snapshot)) = (self.undo.as_mut(), snapshot) {
+                    undo.push(snapshot);
+                }
+                // keys are picked up and doors opened where the player ended up
+                Wing::update(world);
                 // self.player_move_count += 1;
                 // if self.player_move_count >= MOVES_TILL_ENERGY_REGEN {
                 //     self.world.player.change_energy(1);
@@ -367,6 +719,19 @@ impl ggez::event::EventHandler<GameError> for State {
                 // updates all the enemies in the world, for now only removes them once their health is
                 // less than or equal to 0
                 Enemy::update(world);
+
+                // turrets and traps act after the enemies have moved
+                Structure::update(world);
+                Challenge::update(world, self.rng.as_mut().unwrap());
+                // anything hit after the enemies updated still gets its damage numbers this turn
+                world.collect_hits();
+                self.combat_log.record_turn(std::mem::take(&mut world.events));
+                if Challenge::is_over(world) && !world.challenge.recorded {
+                    world.challenge.recorded = true;
+                    self.new_high_score = self
+                        .high_scores
+                        .record(world.challenge.mode, world.difficulty, world.challenge.score());
+                }
                 self.should_draw = true;
             }
         }
@@ -376,28 +741,197 @@ impl ggez::event::EventHandler<GameError> for State {
     fn mouse_button_down_event(
         &mut self,
         _ctx: &mut Context,
-        _button: event::MouseButton,
+        button: event::MouseButton,
+        _x: f32,
+        _y: f32,
+    ) -> Result<(), GameError> {
+        if !self.title_screen && !self.show_world_map {
+            // right click lets go of the queued tile
+            let pos = if button == event::MouseButton::Right {
+                None
+            } else {
+                match self.tile_at(_x, _y) {
+                    Some(pos) => Some(pos),
+                    None => return Ok(()),
+                }
+            };
+            self.world.as_mut().unwrap().player.queued_position = pos;
+            self.should_draw = true;
+        }
+        Ok(())
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        _ctx: &mut Context,
         _x: f32,
         _y: f32,
+        _dx: f32,
+        _dy: f32,
     ) -> Result<(), GameError> {
-        if !self.title_screen && (_y / TILE_SIZE.1 as f32) as usize >= UNIVERSAL_OFFSET as usize {
-            self.world.as_mut().unwrap().player.queued_position = Some(Position::new(
-                (_x / TILE_SIZE.0 as f32) as usize,
-                (_y / TILE_SIZE.1 as f32) as usize - UNIVERSAL_OFFSET as usize,
-            ));
+        if !self.title_screen && !self.show_world_map {
+            let hovered = self.tile_at(_x, _y);
+            let player = &mut self.world.as_mut().unwrap().player;
+            if player.hovered_position != hovered {
+                player.hovered_position = hovered;
+                self.should_draw = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) -> GameResult {
+        if self.combat_log.open {
+            // scrolling up goes back to older entries
+            self.combat_log.scroll(y.signum() as i32);
+            self.should_draw = true;
         }
         Ok(())
     }
+
+    fn resize_event(&mut self, _ctx: &mut Context, width: f32, height: f32) -> GameResult {
+        self.renderer.viewport.resize(width, height);
+        self.should_draw = true;
+        Ok(())
+    }
 }
 
 impl State {
+    // The score of a finished boss rush or arena game, under whatever ended it
+    fn draw_challenge_result(&self, canvas: &mut graphics::Canvas) {
+        let challenge = self.world.as_ref().unwrap().challenge;
+        let difficulty = self.world.as_ref().unwrap().difficulty;
+        if !challenge.mode.is_challenge() {
+            return;
+        }
+        let best = if self.new_high_score {
+            "New best!".to_string()
+        } else if let Some(best) = self.high_scores.best(challenge.mode, difficulty) {
+            format!("Best on {}: {}", difficulty.name(), best.describe(challenge.mode))
+        } else {
+            String::new()
+        };
+        let lines = [
+            challenge.score().describe(challenge.mode),
+            best,
+            "[Enter] back to the title screen".to_string(),
+        ];
+        for (i, line) in lines.into_iter().enumerate() {
+            let pos = Position::new(7, 27 + 3 * i);
+            let text_spot = Vec2::new(
+                (pos.x as f32 + 0.25) * TILE_SIZE.0 as f32,
+                (pos.y as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32,
+            );
+            canvas.draw(
+                &*(graphics::Text::new(line).set_scale(32.)),
+                graphics::DrawParam::from(text_spot).color(graphics::Color::WHITE),
+            );
+        }
+    }
+
+    // The tile of the player's world under a point in the window, if there is one. Goes through
+    // the same scaling the game is drawn with, then through the camera
+    fn tile_at(&self, x: f32, y: f32) -> Option<Position> {
+        let world = self.world.as_ref()?;
+        let point = self.renderer.viewport.to_screen(x, y)?;
+        self.camera.screen_to_tile(world, point.x, point.y)
+    }
+
+    // Picks up changes to balance.ron while the game is running. A broken file is reported and
+    // ignored, the game keeps going with the last balance that worked. Worlds are never
+    // regenerated, so the world section only matters for the next new game
+    fn reload_balance(&mut self, ctx: &Context) {
+        let text = Balance::read(ctx);
+        if text == self.balance_text {
+            return;
+        }
+        self.balance_text = text;
+        let Some(text) = &self.balance_text else {
+            return;
+        };
+        match Balance::parse(text) {
+            Ok(balance) => {
+                println!("Reloaded balance.ron");
+                if let Some(world) = self.world.as_mut() {
+                    world.balance = balance.clone();
+                }
+                self.balance = balance;
+                self.should_draw = true;
+            }
+            Err(errors) => {
+                println!("balance.ron is invalid, keeping the old balance:");
+                for error in errors {
+                    println!("    {}", error);
+                }
+            }
+        }
+    }
+
+    // Picks up changes to bosses.ron the same way. Bosses already fighting carry on from the same
+    // phase of their new script
+    fn reload_boss_scripts(&mut self, ctx: &Context) {
+        let text = BossScripts::read(ctx);
+        if text == self.boss_scripts_text {
+            return;
+        }
+        self.boss_scripts_text = text;
+        let Some(text) = &self.boss_scripts_text else {
+            return;
+        };
+        match BossScripts::parse(text) {
+            Ok(boss_scripts) => {
+                println!("Reloaded bosses.ron");
+                if let Some(world) = self.world.as_mut() {
+                    world.boss_scripts = boss_scripts.clone();
+                }
+                self.boss_scripts = boss_scripts;
+            }
+            Err(errors) => {
+                println!("bosses.ron is invalid, keeping the old boss scripts:");
+                for error in errors {
+                    println!("    {}", error);
+                }
+            }
+        }
+    }
+
     fn save_state(&self) {
         if self.world.as_ref().unwrap().player.is_alive() {
             let serialized_world = ron::to_string(self.world.as_ref().unwrap()).unwrap();
-            fs::write("./serialization/world", serialized_world.as_bytes());
             let serialized_rng = serde_json::to_string(self.rng.as_ref().unwrap()).unwrap();
-            fs::write("./serialization/rng", serialized_rng.as_bytes());
-            fs::write("./serialization/is_serialized", b"1");
+            let metadata = SaveMetadata {
+                hardcore: self.hardcore,
+                undo_enabled: self.undo.is_some(),
+                preset: self.preset.clone(),
+            };
+            let files = [
+                ("./serialization/world", serialized_world),
+                ("./serialization/rng", serialized_rng),
+                ("./serialization/metadata", ron::to_string(&metadata).unwrap()),
+            ];
+            // everything is written out next to the old save first, so the old one is still
+            // whole if that fails. While the files are swapped in the save is marked as missing,
+            // so a world never gets loaded with another game's metadata, which could turn undo
+            // on for a hardcore game
+            for (path, contents) in &files {
+                if let Err(error) = fs::write(format!("{}.tmp", path), contents.as_bytes()) {
+                    println!("Couldn't save the game: {}", error);
+                    return;
+                }
+            }
+            if let Err(error) = fs::write("./serialization/is_serialized", b"0") {
+                println!("Couldn't save the game: {}", error);
+                return;
+            }
+            for (path, _) in &files {
+                if let Err(error) = fs::rename(format!("{}.tmp", path), path) {
+                    println!("Couldn't save the game: {}", error);
+                    return;
+                }
+            }
+            if let Err(error) = fs::write("./serialization/is_serialized", b"1") {
+                println!("Couldn't save the game: {}", error);
+            }
         }
     }
     fn load_save(ctx: &mut Context) -> Option<State> {
@@ -410,6 +944,9 @@ impl State {
          *          Contains the actual world object, written to in RON
          *      rng:
          *          Contains the rng object, in JSON
+         *      metadata:
+         *          Whether the game is hardcore and whether it can be undone, in RON. Saves
+         *          from before this file existed are treated as regular games
          *
          */
 
@@ -430,11 +967,24 @@ impl State {
         } else if serialized_game == 1 {
             let world_str =
                 fs::read_to_string("./serialization/world").expect("Couldn't read world file");
-            let world: World = ron::from_str(&world_str).unwrap();
+            let world: World = match ron::from_str(&world_str) {
+                Ok(world) => world,
+                Err(error) => {
+                    println!("Couldn't read the saved world, starting a new game: {}", error);
+                    return None;
+                }
+            };
             let rng_str =
                 fs::read_to_string("./serialization/rng").expect("Couldn't read rng file");
             let rng: ChaCha8Rng = serde_json::from_str(&rng_str).unwrap();
-            return Some(State::from(world, ctx, rng).expect("couldn't do audio for some reason"));
+            let metadata = fs::read_to_string("./serialization/metadata")
+                .ok()
+                .and_then(|metadata_str| ron::from_str(&metadata_str).ok())
+                .unwrap_or_default();
+            return Some(
+                State::from(world, ctx, rng, metadata)
+                    .expect("couldn't do audio for some reason"),
+            );
         } else {
             panic!("Save data corrupted")
         };
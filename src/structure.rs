@@ -0,0 +1,228 @@
+use crate::{
+    balance::StructureBalance,
+    combat_log::Actor, direction::Direction, entity::Entity, projectile::Projectile, tile, utils::Position,
+    world::World, WORLD_SIZE,
+};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, serde::Serialize, serde::Deserialize)]
+// The different things a player can build
+pub enum StructureKind {
+    #[default]
+    Wall,
+    SpikeTrap,
+    Turret,
+}
+
+impl StructureKind {
+    pub fn color(&self) -> [f32; 4] {
+        match self {
+            StructureKind::Wall => tile::STRUCTURE,
+            StructureKind::SpikeTrap => tile::SPIKE_TRAP,
+            StructureKind::Turret => tile::TURRET,
+        }
+    }
+
+    pub fn cost(&self, balance: &StructureBalance) -> usize {
+        match self {
+            StructureKind::Wall => balance.wall_cost,
+            StructureKind::SpikeTrap => balance.spike_trap_cost,
+            StructureKind::Turret => balance.turret_cost,
+        }
+    }
+
+    pub fn max_health(&self, balance: &StructureBalance) -> usize {
+        match self {
+            StructureKind::Wall => balance.wall_health,
+            StructureKind::SpikeTrap => balance.spike_trap_health,
+            StructureKind::Turret => balance.turret_health,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
+pub struct Structure {
+    pub pos: Position,
+    pub world_pos: Position,
+    pub kind: StructureKind,
+    health: usize,
+    // turns until a turret can shoot again, unused for the other structures
+    cooldown: usize,
+}
+
+impl Structure {
+    pub fn new(
+        kind: StructureKind,
+        pos: Position,
+        world_pos: Position,
+        balance: &StructureBalance,
+    ) -> Self {
+        Structure {
+            pos,
+            world_pos,
+            kind,
+            health: kind.max_health(balance),
+            cooldown: 0,
+        }
+    }
+
+    // Places a new structure and the tile that represents it in the atmosphere map
+    pub fn place(world: &mut World, kind: StructureKind, pos: Position, world_pos: Position) {
+        world.atmosphere_map[world_pos.y][world_pos.x].insert(pos, kind.color());
+        let structure = Structure::new(kind, pos, world_pos, &world.balance.structures);
+        world.structures_map[world_pos.y][world_pos.x].push(structure);
+    }
+
+    // Returns the index of the structure at the position, if there is one
+    pub fn get(world: &World, pos: Position, world_pos: Position) -> Option<usize> {
+        world.structures_map[world_pos.y][world_pos.x]
+            .iter()
+            .position(|structure| structure.pos == pos)
+    }
+
+    pub fn remove(world: &mut World, index: usize, world_pos: Position) {
+        let structure = world.structures_map[world_pos.y][world_pos.x].remove(index);
+        let atmosphere = &mut world.atmosphere_map[world_pos.y][world_pos.x];
+        // only clear the tile if it still shows the structure, so we don't erase an effect like
+        // lightning that is currently drawn on top of it
+        if atmosphere.get(&structure.pos) == Some(&structure.kind.color()) {
+            atmosphere.remove(&structure.pos);
+        }
+    }
+
+    // Damages every structure in the world that satisfies the predicate, and destroys the ones
+    // that run out of health. Used for area attacks like bomber explosions and boss lasers
+    pub fn damage_where(
+        world: &mut World,
+        world_pos: Position,
+        damage: usize,
+        in_area: impl Fn(Position) -> bool,
+    ) {
+        for index in (0..world.structures_map[world_pos.y][world_pos.x].len()).rev() {
+            let structure = &mut world.structures_map[world_pos.y][world_pos.x][index];
+            if in_area(structure.pos) {
+                structure.health = structure.health.saturating_sub(damage);
+                if structure.health == 0 {
+                    Self::remove(world, index, world_pos);
+                }
+            }
+        }
+    }
+
+    pub fn damage_at(world: &mut World, pos: Position, world_pos: Position, damage: usize) {
+        Self::damage_where(world, world_pos, damage, |structure_pos| structure_pos == pos);
+    }
+
+    // Runs every structure in the current world for one turn
+    pub fn update(world: &mut World) {
+        let world_pos = world.world_position;
+        for index in (0..world.structures_map[world_pos.y][world_pos.x].len()).rev() {
+            let structure = world.structures_map[world_pos.y][world_pos.x][index].clone();
+            match structure.kind {
+                StructureKind::Wall => {}
+                StructureKind::SpikeTrap => Self::spike_enemies(world, index, structure.pos),
+                StructureKind::Turret => Self::turret_shoot(world, index, structure.pos),
+            }
+        }
+
+        // Lightning and fire clear the atmosphere tiles they pass over, so put back the tiles of
+        // any structure that survived them
+        for structure in &world.structures_map[world_pos.y][world_pos.x] {
+            world.atmosphere_map[world_pos.y][world_pos.x]
+                .entry(structure.pos)
+                .or_insert(structure.kind.color());
+        }
+    }
+
+    // Hurts every enemy standing on the trap, and wears the trap down a bit each time
+    fn spike_enemies(world: &mut World, index: usize, pos: Position) {
+        let world_pos = world.world_position;
+        let mut triggered = false;
+        for enemy in &mut world.enemies_map[world_pos.y][world_pos.x] {
+            if enemy.pos.contains(&pos) {
+                enemy.damage(
+                    world.balance.structures.spike_trap_damage,
+                    Actor::PlayerAttack("spike trap"),
+                );
+                triggered = true;
+            }
+        }
+        if triggered {
+            let trap = &mut world.structures_map[world_pos.y][world_pos.x][index];
+            trap.health = trap.health.saturating_sub(world.balance.structures.spike_trap_wear);
+            if trap.health == 0 {
+                Self::remove(world, index, world_pos);
+            }
+        }
+    }
+
+    // Shoots a player projectile towards the closest enemy in range
+    fn turret_shoot(world: &mut World, index: usize, pos: Position) {
+        let world_pos = world.world_position;
+        if world.structures_map[world_pos.y][world_pos.x][index].cooldown > 0 {
+            world.structures_map[world_pos.y][world_pos.x][index].cooldown -= 1;
+            return;
+        }
+
+        // find the closest enemy tile by manhattan distance
+        let mut closest: Option<(i32, i32)> = None;
+        for enemy in &world.enemies_map[world_pos.y][world_pos.x] {
+            for tile in &enemy.pos {
+                let delta = (tile.x as i32 - pos.x as i32, tile.y as i32 - pos.y as i32);
+                let distance = delta.0.abs() + delta.1.abs();
+                if distance <= world.balance.structures.turret_range
+                    && closest.is_none_or(|best| distance < best.0.abs() + best.1.abs())
+                {
+                    closest = Some(delta);
+                }
+            }
+        }
+
+        if let Some((delta_x, delta_y)) = closest {
+            let direction = if delta_x.abs() > delta_y.abs() {
+                if delta_x > 0 {
+                    Direction::East
+                } else {
+                    Direction::West
+                }
+            } else if delta_y > 0 {
+                Direction::South
+            } else {
+                Direction::North
+            };
+            let (x, y) = match direction {
+                Direction::North => (pos.x as i32, pos.y as i32 - 1),
+                Direction::South => (pos.x as i32, pos.y as i32 + 1),
+                Direction::East => (pos.x as i32 + 1, pos.y as i32),
+                Direction::West => (pos.x as i32 - 1, pos.y as i32),
+            };
+            if x < 0 || y < 0 || x >= WORLD_SIZE.0 as i32 || y >= WORLD_SIZE.1 as i32 {
+                return;
+            }
+            let spawn_pos = Position::new(x as usize, y as usize);
+            let projectile = Projectile::player_projectile(
+                spawn_pos.x,
+                spawn_pos.y,
+                direction,
+                world_pos,
+                &world.balance,
+            );
+
+            // An enemy right next to the turret gets hit immediately, the same way the player's
+            // projectile attack works
+            if let Some(enemy_index) = World::get_enemy(spawn_pos, world) {
+                world.enemies_map[world_pos.y][world_pos.x][enemy_index]
+                    .damage(projectile.damage, Actor::PlayerAttack("turret"));
+            } else if world.terrain_map[world_pos.y][world_pos.x].contains_key(&spawn_pos)
+                || world.entity_map[world_pos.y][world_pos.x].contains_key(&spawn_pos)
+                || world.atmosphere_map[world_pos.y][world_pos.x].contains_key(&spawn_pos)
+            {
+                return;
+            } else {
+                world.entity_map[world_pos.y][world_pos.x]
+                    .insert(spawn_pos, (tile::PROJECTILE_PLAYER, Entity::Projectile));
+                world.projectiles.push(projectile);
+            }
+            world.structures_map[world_pos.y][world_pos.x][index].cooldown = world.balance.structures.turret_cooldown;
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{utils::Position, TILE_SIZE, UNIVERSAL_OFFSET, WORLD_SIZE};
+use ggez::{
+    glam::Vec2,
+    graphics::{self, Canvas},
+};
+use std::collections::HashMap;
+
+const TELEGRAPH_NUMBER_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
+const TELEGRAPH_NUMBER_SCALE: f32 = 12.0;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+// Every tile a boss attack is about to hurt, with how many turns are left until it does. Worked
+// out from the attacks every time the room is drawn, using the same checks the attacks hurt with,
+// so a countdown can never be out of step with its attack
+pub struct Telegraphs {
+    turns: HashMap<Position, usize>,
+}
+
+impl Telegraphs {
+    pub fn new() -> Self {
+        Telegraphs::default()
+    }
+
+    // A tile two attacks are coming for shows whichever lands first. Attacks that have already
+    // gone off don't get a number, they are drawn at full strength instead
+    pub fn warn(&mut self, pos: Position, turns: usize) {
+        if turns == 0 || pos.x >= WORLD_SIZE.0 as usize || pos.y >= WORLD_SIZE.1 as usize {
+            return;
+        }
+        let soonest = self.turns.entry(pos).or_insert(turns);
+        *soonest = (*soonest).min(turns);
+    }
+
+    // Warns every tile of the room the attack would hit
+    pub fn warn_where(&mut self, turns: usize, hits: impl Fn(Position) -> bool) {
+        if turns == 0 {
+            return;
+        }
+        for x in 0..WORLD_SIZE.0 as usize {
+            for y in 0..WORLD_SIZE.1 as usize {
+                let pos = Position::new(x, y);
+                if hits(pos) {
+                    self.warn(pos, turns);
+                }
+            }
+        }
+    }
+
+    // Has to be drawn with the screen coordinates of the player's world, over everything it warns
+    // about so the numbers can be read
+    pub fn draw(&self, canvas: &mut Canvas) {
+        for (pos, turns) in &self.turns {
+            let dest = Vec2::new(
+                (pos.x as f32 + 0.25) * TILE_SIZE.0 as f32,
+                (pos.y as f32 + UNIVERSAL_OFFSET as f32 + 0.1) * TILE_SIZE.1 as f32,
+            );
+            canvas.draw(
+                &*(graphics::Text::new(format!("{}", turns)).set_scale(TELEGRAPH_NUMBER_SCALE)),
+                graphics::DrawParam::from(dest).color(TELEGRAPH_NUMBER_COLOR),
+            );
+        }
+    }
+}
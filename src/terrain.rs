@@ -0,0 +1,434 @@
+use crate::{
+    random, tile,
+    utils::Position,
+    world::{World, BOSS_ROOMS},
+    BOARD_SIZE, WORLD_SIZE,
+};
+use rand_chacha::ChaCha8Rng;
+use std::collections::{HashMap, VecDeque};
+
+// Times the cellular automaton smooths out the noise a blob starts as
+const SMOOTHING_STEPS: usize = 4;
+// How much of the circle a blob starts in is filled in before smoothing. Much less and blobs
+// crumble away, much more and they come out as plain circles
+const BLOB_DENSITY: f32 = 0.65;
+// The smallest and biggest radius of a mountain or lake before smoothing
+const MOUNTAIN_RADIUS: (i16, i16) = (4, 10);
+const LAKE_RADIUS: (i16, i16) = (3, 6);
+// Spots tried for each lake before leaving it out, when it keeps landing on walls or other terrain
+const LAKE_TRIES: usize = 10;
+// Pockets smaller than this that can't be walked to get filled in instead of dug out
+const POCKET_FILL: usize = 4;
+// Terrain the player can walk through. Doors count, since the keys to them are always on the
+// player's side
+const WALKABLE: [[f32; 4]; 7] = [
+    tile::GRASS,
+    tile::MUD,
+    tile::ICE,
+    tile::BRIDGE,
+    tile::FORD,
+    tile::LOCKED_DOOR,
+    tile::BOSS_DOOR,
+];
+// Tiles on the whole board
+const TILES: usize = BOARD_SIZE.0 as usize * BOARD_SIZE.1 as usize;
+const DIRECTIONS: [(i16, i16); 4] = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+// How far connect has got with a tile
+enum Reach {
+    Unknown,
+    // can't be walked to, and connect is still working on it
+    Pocket,
+    // can be walked to from where the player starts
+    Connected,
+}
+
+// Grows mountains anywhere but the boss rooms. Each one is a blob that gets darker towards its
+// middle
+pub fn gen_mountains(world: &mut World, rng: &mut ChaCha8Rng, count: usize) {
+    let mut mountain = vec![false; TILES];
+    for _ in 0..count {
+        let center = loop {
+            let center = (
+                random::rand_range(rng, 0, BOARD_SIZE.0),
+                random::rand_range(rng, 0, BOARD_SIZE.1),
+            );
+            if !BOSS_ROOMS.contains(&split(center).0) {
+                break center;
+            }
+        };
+        let radius = random::rand_range(rng, MOUNTAIN_RADIUS.0, MOUNTAIN_RADIUS.1 + 1);
+        for pos in grow_blob(rng, center, radius) {
+            if can_grow(world, pos) && !BOSS_ROOMS.contains(&split(pos).0) {
+                mountain[index(pos)] = true;
+            }
+        }
+    }
+    for (i, depth) in depths(&mountain).into_iter().enumerate() {
+        if depth > 0 {
+            let shade = 4 - (depth as usize - 1).min(4);
+            set_terrain(world, board_pos(i), tile::MOUNTAIN[shade]);
+        }
+    }
+}
+
+// Grows lakes in every world. Boss rooms get lava lakes, and the rest get whatever their biome's
+// lakes are filled with
+pub fn gen_lakes(world: &mut World, rng: &mut ChaCha8Rng, per_world: usize) {
+    for world_y in 0..BOARD_SIZE.1 / WORLD_SIZE.1 {
+        for world_x in 0..BOARD_SIZE.0 / WORLD_SIZE.0 {
+            for _ in 0..per_world {
+                for _ in 0..LAKE_TRIES {
+                    let center = (
+                        world_x * WORLD_SIZE.0 + random::rand_range(rng, 0, WORLD_SIZE.0),
+                        world_y * WORLD_SIZE.1 + random::rand_range(rng, 0, WORLD_SIZE.1),
+                    );
+                    let radius = random::rand_range(rng, LAKE_RADIUS.0, LAKE_RADIUS.1 + 1);
+                    let lake: Vec<(i16, i16)> = grow_blob(rng, center, radius)
+                        .into_iter()
+                        .filter(|pos| can_grow(world, *pos))
+                        .collect();
+                    if lake.is_empty() {
+                        continue;
+                    }
+                    // a lake that spills into the next world takes on that world's look
+                    for pos in lake {
+                        let world_pos = split(pos).0;
+                        let tile = if BOSS_ROOMS.contains(&world_pos) {
+                            tile::LAVA
+                        } else {
+                            world.biomes[world_pos.y][world_pos.x].lake()
+                        };
+                        set_terrain(world, pos, tile);
+                    }
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// Makes sure every open tile on the board can be walked to from where the player starts. Tiny
+// pockets get filled in with whatever is around them, and the rest get the shortest way dug out
+// of them, with fords through water. Walls are never dug through, so anything only walls keep
+// the player out of stays that way
+pub fn connect(world: &mut World) {
+    let mut reach = vec![Reach::Unknown; TILES];
+    let start = (
+        world.world_position.x as i16 * WORLD_SIZE.0 + world.player.pos.x as i16,
+        world.world_position.y as i16 * WORLD_SIZE.1 + world.player.pos.y as i16,
+    );
+    flood(world, &mut reach, vec![start], Reach::Connected);
+    for i in 0..reach.len() {
+        let pos = board_pos(i);
+        if reach[i] != Reach::Unknown || !is_walkable(world, pos) {
+            continue;
+        }
+        let pocket = flood(world, &mut reach, vec![pos], Reach::Pocket);
+        if pocket.len() < POCKET_FILL && !pocket.iter().any(|pos| has_entity(world, *pos)) {
+            fill(world, &pocket);
+        } else if let Some(path) = way_out(world, &reach, &pocket) {
+            for pos in path {
+                let carved = match terrain(world, pos) {
+                    Some(&tile::WATER) => Some(tile::FORD),
+                    _ => None,
+                };
+                let (world_pos, tile_pos) = split(pos);
+                let terrain = &mut world.terrain_map[world_pos.y][world_pos.x];
+                match carved {
+                    Some(carved) => terrain.insert(tile_pos, carved),
+                    None => terrain.remove(&tile_pos),
+                };
+            }
+            flood(world, &mut reach, pocket, Reach::Connected);
+        }
+    }
+}
+
+// A blob of terrain grown by a cellular automaton around center. The tiles in a circle start out
+// filled at random, then every step each tile ends up filled if most of the 3x3 around it is,
+// which smooths the noise into one rounded shape with ragged edges
+fn grow_blob(rng: &mut ChaCha8Rng, center: (i16, i16), radius: i16) -> Vec<(i16, i16)> {
+    // one tile of empty space around the circle so the edges can smooth out too
+    let size = 2 * radius + 3;
+    let offset = radius + 1;
+    let cell = |x: i16, y: i16| (y * size + x) as usize;
+    let mut cells = Vec::with_capacity((size * size) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let (dx, dy) = (x - offset, y - offset);
+            let in_circle = dx * dx + dy * dy <= radius * radius;
+            cells.push(in_circle && random::bernoulli(rng, BLOB_DENSITY));
+        }
+    }
+    for _ in 0..SMOOTHING_STEPS {
+        let mut next = vec![false; cells.len()];
+        for y in 1..size - 1 {
+            for x in 1..size - 1 {
+                let filled = (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+                    .filter(|(x, y)| cells[cell(*x, *y)])
+                    .count();
+                next[cell(x, y)] = filled >= 5;
+            }
+        }
+        cells = next;
+    }
+    let mut blob = Vec::new();
+    for y in 0..size {
+        for x in 0..size {
+            if cells[cell(x, y)] {
+                blob.push((center.0 + x - offset, center.1 + y - offset));
+            }
+        }
+    }
+    blob
+}
+
+// How far every tile of a shape is from the nearest tile outside it, 1 along its edges and 0
+// outside it
+fn depths(shape: &[bool]) -> Vec<u8> {
+    let mut depths: Vec<u8> = vec![0; shape.len()];
+    let mut queue = VecDeque::new();
+    for (i, inside) in shape.iter().enumerate() {
+        let pos = board_pos(i);
+        let on_edge = DIRECTIONS.iter().any(|(dx, dy)| {
+            let next = (pos.0 + dx, pos.1 + dy);
+            !on_board(next) || !shape[index(next)]
+        });
+        if *inside && on_edge {
+            depths[i] = 1;
+            queue.push_back(pos);
+        }
+    }
+    while let Some(pos) = queue.pop_front() {
+        let depth = depths[index(pos)];
+        for (dx, dy) in DIRECTIONS {
+            let next = (pos.0 + dx, pos.1 + dy);
+            if on_board(next) && shape[index(next)] && depths[index(next)] == 0 {
+                depths[index(next)] = depth.saturating_add(1);
+                queue.push_back(next);
+            }
+        }
+    }
+    depths
+}
+
+// Marks everything walkable that can be reached from the given tiles, and hasn't been already.
+// Returns every tile it marked
+fn flood(
+    world: &World,
+    reach: &mut [Reach],
+    from: Vec<(i16, i16)>,
+    mark: Reach,
+) -> Vec<(i16, i16)> {
+    let mut marked = Vec::new();
+    let mut queue = VecDeque::new();
+    for pos in from {
+        if reach[index(pos)] != mark {
+            reach[index(pos)] = mark;
+            queue.push_back(pos);
+        }
+    }
+    while let Some(pos) = queue.pop_front() {
+        marked.push(pos);
+        for (dx, dy) in DIRECTIONS {
+            let next = (pos.0 + dx, pos.1 + dy);
+            if on_board(next) && reach[index(next)] != mark && is_walkable(world, next) {
+                reach[index(next)] = mark;
+                queue.push_back(next);
+            }
+        }
+    }
+    marked
+}
+
+// Fills a pocket in with the first terrain found next to it
+fn fill(world: &mut World, pocket: &[(i16, i16)]) {
+    let around = pocket.iter().find_map(|pos| {
+        DIRECTIONS.iter().find_map(|(dx, dy)| {
+            let next = (pos.0 + dx, pos.1 + dy);
+            terrain(world, next)
+                .filter(|_| !is_walkable(world, next))
+                .copied()
+        })
+    });
+    if let Some(tile) = around {
+        for pos in pocket {
+            set_terrain(world, *pos, tile);
+        }
+    }
+}
+
+// The way from a pocket to somewhere connected that goes through the least terrain, and the
+// terrain tiles on it that would have to be dug out. None if walls are in the way everywhere
+fn way_out(world: &World, reach: &[Reach], pocket: &[(i16, i16)]) -> Option<Vec<(i16, i16)>> {
+    // walking over open ground is free and digging costs one, so going through a tile that has
+    // to be dug out gets put at the back of the queue and open ground at the front
+    let mut cost: HashMap<(i16, i16), usize> = HashMap::new();
+    let mut came_from: HashMap<(i16, i16), (i16, i16)> = HashMap::new();
+    let mut queue = VecDeque::new();
+    for pos in pocket {
+        cost.insert(*pos, 0);
+        queue.push_back((*pos, 0));
+    }
+    while let Some((pos, so_far)) = queue.pop_front() {
+        if so_far > cost[&pos] {
+            continue;
+        }
+        if reach[index(pos)] == Reach::Connected {
+            let mut path = Vec::new();
+            let mut pos = pos;
+            while let Some(previous) = came_from.get(&pos) {
+                if !is_walkable(world, pos) {
+                    path.push(pos);
+                }
+                pos = *previous;
+            }
+            return Some(path);
+        }
+        for (dx, dy) in DIRECTIONS {
+            let next = (pos.0 + dx, pos.1 + dy);
+            if !on_board(next) {
+                continue;
+            }
+            let step = if is_walkable(world, next) {
+                0
+            } else if can_dig(world, next) {
+                1
+            } else {
+                continue;
+            };
+            if cost.get(&next).is_none_or(|cost| so_far + step < *cost) {
+                cost.insert(next, so_far + step);
+                came_from.insert(next, pos);
+                if step == 0 {
+                    queue.push_front((next, so_far));
+                } else {
+                    queue.push_back((next, so_far + step));
+                }
+            }
+        }
+    }
+    None
+}
+
+// Whether a tile is free for a mountain or lake to grow over. Nothing grows on the edge of the
+// board or next to a wall, so the doors into the boss rooms stay clear
+fn can_grow(world: &World, pos: (i16, i16)) -> bool {
+    let near_edge =
+        pos.0 <= 0 || pos.0 >= BOARD_SIZE.0 - 1 || pos.1 <= 0 || pos.1 >= BOARD_SIZE.1 - 1;
+    if near_edge || terrain(world, pos).is_some() || has_entity(world, pos) {
+        return false;
+    }
+    !(-1..=1)
+        .flat_map(|dy| (-1..=1).map(move |dx| (pos.0 + dx, pos.1 + dy)))
+        .any(|pos| terrain(world, pos) == Some(&tile::WALL))
+}
+
+// Terrain that can be dug out of the way. Walls, portals and anything the player can't get rid of
+// later stay put
+fn can_dig(world: &World, pos: (i16, i16)) -> bool {
+    match terrain(world, pos) {
+        Some(color) => {
+            tile::MOUNTAIN.contains(color)
+                || [tile::WATER, tile::LAVA, tile::TREE, tile::CACTUS].contains(color)
+        }
+        None => false,
+    }
+}
+
+fn is_walkable(world: &World, pos: (i16, i16)) -> bool {
+    terrain(world, pos).is_none_or(|color| WALKABLE.contains(color))
+}
+
+fn terrain(world: &World, pos: (i16, i16)) -> Option<&[f32; 4]> {
+    let (world_pos, tile_pos) = split(pos);
+    world.terrain_map[world_pos.y][world_pos.x].get(&tile_pos)
+}
+
+fn set_terrain(world: &mut World, pos: (i16, i16), color: [f32; 4]) {
+    let (world_pos, tile_pos) = split(pos);
+    world.terrain_map[world_pos.y][world_pos.x].insert(tile_pos, color);
+}
+
+fn has_entity(world: &World, pos: (i16, i16)) -> bool {
+    let (world_pos, tile_pos) = split(pos);
+    world.entity_map[world_pos.y][world_pos.x].contains_key(&tile_pos)
+}
+
+// The world a tile of the board is in, and where it is in that world
+fn split(pos: (i16, i16)) -> (Position, Position) {
+    (
+        Position::new(
+            (pos.0 / WORLD_SIZE.0) as usize,
+            (pos.1 / WORLD_SIZE.1) as usize,
+        ),
+        Position::new(
+            (pos.0 % WORLD_SIZE.0) as usize,
+            (pos.1 % WORLD_SIZE.1) as usize,
+        ),
+    )
+}
+
+fn on_board(pos: (i16, i16)) -> bool {
+    pos.0 >= 0 && pos.0 < BOARD_SIZE.0 && pos.1 >= 0 && pos.1 < BOARD_SIZE.1
+}
+
+fn index(pos: (i16, i16)) -> usize {
+    (pos.1 as usize) * BOARD_SIZE.0 as usize + pos.0 as usize
+}
+
+fn board_pos(index: usize) -> (i16, i16) {
+    (
+        (index % BOARD_SIZE.0 as usize) as i16,
+        (index / BOARD_SIZE.0 as usize) as i16,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        balance::Balance,
+        boss_script::BossScripts,
+        difficulty::Difficulty,
+        worldgen::WorldGen,
+    };
+    use rand::SeedableRng;
+
+    #[test]
+    fn every_walkable_tile_can_be_reached() {
+        let world_gen = WorldGen::default();
+        for (name, preset) in &world_gen.0 {
+            for seed in [0, 1, 42, 1234, 9001] {
+                let world = World::new(
+                    &mut ChaCha8Rng::seed_from_u64(seed),
+                    Difficulty::default(),
+                    &Balance::default(),
+                    &BossScripts::default(),
+                    preset,
+                );
+                let mut reach = vec![Reach::Unknown; TILES];
+                let start = (
+                    world.world_position.x as i16 * WORLD_SIZE.0 + world.player.pos.x as i16,
+                    world.world_position.y as i16 * WORLD_SIZE.1 + world.player.pos.y as i16,
+                );
+                flood(&world, &mut reach, vec![start], Reach::Connected);
+                let stranded: Vec<(i16, i16)> = (0..TILES)
+                    .map(board_pos)
+                    .filter(|pos| is_walkable(&world, *pos) && reach[index(*pos)] != Reach::Connected)
+                    .collect();
+                assert!(
+                    stranded.is_empty(),
+                    "{} tiles of {} with seed {} can't be reached, like {:?}",
+                    stranded.len(),
+                    name,
+                    seed,
+                    stranded.first()
+                );
+            }
+        }
+    }
+}
@@ -17,7 +17,31 @@ pub const PORTAL: [f32; 4] = [0.631, 0.012, 0.988, 1.0];
 pub const WALL: [f32; 4] = [0.3, 0.3, 0.3, 1.0];
 pub const PROJECTILE_PLAYER: [f32; 4] = [0.5, 0.0, 0.0, 1.0];
 pub const GRASS: [f32; 4] = [0.0, 0.5, 0.0, 1.0];
-pub const STRUCTURE: [f32; 4] = [0.3, 0.0, 0.0, 1.0];
+pub const FOREST_FLOOR: [f32; 4] = [0.05, 0.33, 0.1, 1.0]; //Ground of forest worlds
+pub const SAND: [f32; 4] = [0.76, 0.66, 0.4, 1.0]; //Ground of desert worlds
+pub const SNOW: [f32; 4] = [0.8, 0.84, 0.88, 1.0]; //Ground of tundra worlds
+pub const SWAMP_FLOOR: [f32; 4] = [0.24, 0.3, 0.16, 1.0]; //Ground of swamp worlds
+pub const ASH: [f32; 4] = [0.24, 0.18, 0.16, 1.0]; //Ground of the volcanic worlds around the final boss
+pub const TREE: [f32; 4] = [0.0, 0.2, 0.05, 1.0]; //Blocks movement and sight
+pub const CACTUS: [f32; 4] = [0.3, 0.55, 0.25, 1.0]; //Blocks movement
+pub const MUD: [f32; 4] = [0.36, 0.27, 0.15, 1.0]; //Can be walked through, but holds on to the player for a turn
+pub const ICE: [f32; 4] = [0.66, 0.84, 0.95, 1.0]; //Can be walked on, but the player slides until something stops them
+pub const BRIDGE: [f32; 4] = [0.56, 0.4, 0.22, 1.0]; //Crosses a river
+pub const FORD: [f32; 4] = [0.36, 0.66, 0.8, 1.0]; //Shallow part of a river, holds on to the player for a turn like mud
+pub const STRUCTURE: [f32; 4] = [0.3, 0.0, 0.0, 1.0]; //Player built wall, blocks movement
+pub const SPIKE_TRAP: [f32; 4] = [0.55, 0.55, 0.6, 1.0]; //Player built trap, damages enemies that walk over it
+pub const TURRET: [f32; 4] = [0.45, 0.1, 0.35, 1.0]; //Player built turret, shoots projectiles at nearby enemies
+pub const LOCKED_DOOR: [f32; 4] = [0.55, 0.35, 0.1, 1.0]; //Door in a boss wing, opened with one of the wing's keys
+pub const BOSS_DOOR: [f32; 4] = [0.6, 0.1, 0.1, 1.0]; //Door into a mini boss room, opened with the wing's boss key
+pub const KEY: [f32; 4] = [0.95, 0.85, 0.2, 1.0]; //Key lying on the floor of a boss wing
+pub const BOSS_KEY: [f32; 4] = [1.0, 0.45, 0.2, 1.0]; //The key to the boss door at the end of a wing
+pub const FOG_UNEXPLORED: [f32; 4] = [0.0, 0.0, 0.0, 1.0]; //Covers tiles the player has never seen
+pub const FOG_REMEMBERED: [f32; 4] = [0.0, 0.0, 0.0, 0.6]; //Dims tiles the player has seen but can't see right now
+pub const TARGET_VALID: [f32; 4] = [0.2, 0.9, 0.2, 0.6]; //Targeted tile the player could teleport to
+pub const TARGET_INVALID: [f32; 4] = [0.9, 0.2, 0.2, 0.6]; //Targeted tile the player can't teleport to
+pub const TARGET_LIGHTNING: [f32; 4] = [1.0, 1.0, 0.4, 0.35]; //Area lightning would strike around the target
+pub const TARGET_BUILD_RANGE: [f32; 4] = [1.0, 1.0, 1.0, 0.25]; //Tiles close enough to the player to build on
+pub const TARGET_QUEUED: [f32; 4] = [1.0, 1.0, 1.0, 0.9]; //Frame around the tile the click abilities will use
 
 // pub const LIGHTNING: [[f32; 4]; 4] = [
 // This is a random color, it just can't conflict with anything esle
@@ -0,0 +1,256 @@
+use crate::{direction::Direction, tile, utils::Position, TILE_SIZE, UNIVERSAL_OFFSET};
+use ggez::{
+    glam::Vec2,
+    graphics::{self, Canvas, DrawParam, Image, InstanceArray, Rect},
+    Context,
+};
+use std::collections::HashMap;
+use std::io::Read;
+
+pub const TILESET_MANIFEST_PATH: &str = "/tileset.ron";
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, serde::Deserialize)]
+// Every kind of tile that can have a sprite. Several tile colors can share the same kind, like
+// all the mountain shades or the three bomber states
+pub enum SpriteKind {
+    Water,
+    Lava,
+    Mountain,
+    Wall,
+    Portal,
+    Structure,
+    SpikeTrap,
+    Turret,
+    Player,
+    Chaser,
+    Bomber,
+    Major,
+    Boss,
+    BossShell,
+    Projectile,
+    Missile,
+}
+
+impl SpriteKind {
+    // Tiles are told apart by their colors everywhere else, so the sprites are picked the same way
+    pub fn from_color(color: [f32; 4]) -> Option<SpriteKind> {
+        Some(match color {
+            tile::WATER => SpriteKind::Water,
+            tile::LAVA => SpriteKind::Lava,
+            color if tile::MOUNTAIN.contains(&color) => SpriteKind::Mountain,
+            tile::WALL => SpriteKind::Wall,
+            tile::PORTAL => SpriteKind::Portal,
+            tile::STRUCTURE => SpriteKind::Structure,
+            tile::SPIKE_TRAP => SpriteKind::SpikeTrap,
+            tile::TURRET => SpriteKind::Turret,
+            tile::PLAYER | tile::PLAYER_INVISIBLE | tile::PLAYER_STUNNED => SpriteKind::Player,
+            // shooters, knights and the final boss's minions all share this color
+            tile::CHASING_ENEMY => SpriteKind::Chaser,
+            tile::BOMBER_ENEMY | tile::BOMBER_ENEMY_ACTIVATED | tile::BOMBER_ENEMY_DEACTIVATED => {
+                SpriteKind::Bomber
+            }
+            tile::MAJOR_ENEMY => SpriteKind::Major,
+            tile::LASER_BOSS | tile::COLUMN_BOSS | tile::BLACK_OUT_BOSS | tile::CHASING_BOSS => {
+                SpriteKind::Boss
+            }
+            tile::BOSS_SURROUNDINGS
+            | tile::BOSS_VULNERABLE
+            | tile::BOSS_RECOVERY_ONE
+            | tile::BOSS_RECOVERY_TWO => SpriteKind::BossShell,
+            tile::PROJECTILE_PLAYER => SpriteKind::Projectile,
+            tile::TRACKING_PROJECTILE => SpriteKind::Missile,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename = "Tileset")]
+// What tileset.ron looks like
+struct TilesetManifest {
+    image: String,
+    // size of one sprite in the image, in pixels
+    tile_size: u32,
+    // tile coordinates of the sprites for each kind, either one sprite or four facing north,
+    // east, south and west
+    sprites: HashMap<SpriteKind, Vec<(u32, u32)>>,
+}
+
+pub struct Tileset {
+    image: Image,
+    // the part of the image each sprite takes up, in the 0 to 1 range ggez wants for src rects
+    sprites: HashMap<SpriteKind, Vec<Rect>>,
+    scale: Vec2,
+    // when false everything is drawn with the flat colors from tile.rs instead
+    pub enabled: bool,
+}
+
+impl Tileset {
+    // Loads the atlas and its manifest from the resources folder. Returns None if anything is
+    // missing or broken, in which case the game just keeps using flat colors
+    pub fn load(ctx: &Context) -> Option<Tileset> {
+        let mut manifest_str = String::new();
+        if let Err(e) = ctx
+            .fs
+            .open(TILESET_MANIFEST_PATH)
+            .map(|mut file| file.read_to_string(&mut manifest_str))
+        {
+            println!("Couldn't open tileset manifest, using flat colors: {}", e);
+            return None;
+        }
+        let manifest: TilesetManifest = match ron::from_str(&manifest_str) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                println!("Tileset manifest is invalid, using flat colors: {}", e);
+                return None;
+            }
+        };
+        let image = match Image::from_path(ctx, &manifest.image) {
+            Ok(image) => image,
+            Err(e) => {
+                println!("Couldn't load tileset image, using flat colors: {}", e);
+                return None;
+            }
+        };
+
+        let (width, height) = (image.width() as f32, image.height() as f32);
+        let size = manifest.tile_size as f32;
+        let sprites = manifest
+            .sprites
+            .into_iter()
+            .map(|(kind, coords)| {
+                let rects = coords
+                    .iter()
+                    .map(|(x, y)| {
+                        Rect::new(
+                            *x as f32 * size / width,
+                            *y as f32 * size / height,
+                            size / width,
+                            size / height,
+                        )
+                    })
+                    .collect();
+                (kind, rects)
+            })
+            .collect();
+        Some(Tileset {
+            image,
+            sprites,
+            scale: Vec2::new(TILE_SIZE.0 as f32 / size, TILE_SIZE.1 as f32 / size),
+            enabled: true,
+        })
+    }
+
+    fn sprite(&self, color: [f32; 4], direction: Direction) -> Option<Rect> {
+        let rects = self.sprites.get(&SpriteKind::from_color(color)?)?;
+        if rects.len() < 4 {
+            return rects.first().copied();
+        }
+        Some(match direction {
+            Direction::North => rects[0],
+            Direction::East => rects[1],
+            Direction::South => rects[2],
+            Direction::West => rects[3],
+        })
+    }
+}
+
+// Tiles that have been batched up and can be drawn as many times as needed, with one draw call
+// for the flat colored tiles and one for the sprites
+pub struct TileLayer {
+    flat: InstanceArray,
+    sprites: Option<InstanceArray>,
+}
+
+impl TileLayer {
+    pub fn draw(&self, canvas: &mut Canvas) {
+        if !self.flat.instances().is_empty() {
+            canvas.draw(&self.flat, DrawParam::new());
+        }
+        if let Some(sprites) = &self.sprites {
+            if !sprites.instances().is_empty() {
+                // keep the pixel art sharp when it is scaled up
+                canvas.set_sampler(graphics::Sampler::nearest_clamp());
+                canvas.draw(sprites, DrawParam::new());
+            }
+        }
+    }
+}
+
+// Collects the tiles of one layer of the world so they can all be drawn at once. Tiles without a
+// sprite, or everything when there's no tileset, are drawn as flat colored squares
+pub struct TileBatch<'a> {
+    tileset: Option<&'a Tileset>,
+    layer: TileLayer,
+}
+
+impl<'a> TileBatch<'a> {
+    pub fn new(ctx: &Context, tileset: Option<&'a Tileset>) -> Self {
+        let tileset = tileset.filter(|tileset| tileset.enabled);
+        TileBatch {
+            tileset,
+            layer: TileLayer {
+                // without an image every instance is a plain white square that gets tinted
+                flat: InstanceArray::new(ctx, None),
+                sprites: tileset.map(|tileset| InstanceArray::new(ctx, tileset.image.clone())),
+            },
+        }
+    }
+
+    pub fn push(&mut self, loc: Position, color: [f32; 4]) {
+        self.add(loc, color, color, Direction::North);
+    }
+
+    // Same as push, but the tile is drawn in a slightly different color than the one that decides
+    // its sprite, for things like the color variation of terrain
+    pub fn push_shaded(&mut self, loc: Position, color: [f32; 4], shade: [f32; 4]) {
+        self.add(loc, color, shade, Direction::North);
+    }
+
+    // For the player and enemies, whose sprites turn to face where they are going
+    pub fn push_facing(&mut self, loc: Position, color: [f32; 4], direction: Direction) {
+        self.add(loc, color, color, direction);
+    }
+
+    // A flat colored rectangle anywhere on screen, in pixels. Used for the HUD
+    pub fn push_rect(&mut self, rect: Rect, color: [f32; 4]) {
+        self.layer.flat.push(
+            DrawParam::new()
+                .dest(rect.point())
+                .scale(Vec2::new(rect.w, rect.h))
+                .color(color),
+        );
+    }
+
+    fn add(&mut self, loc: Position, color: [f32; 4], shade: [f32; 4], direction: Direction) {
+        let dest = Vec2::new(
+            loc.x as f32 * TILE_SIZE.0 as f32,
+            (loc.y as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32,
+        );
+        if let (Some(tileset), Some(sprites)) = (self.tileset, self.layer.sprites.as_mut()) {
+            if let Some(src) = tileset.sprite(color, direction) {
+                sprites.push(
+                    DrawParam::new()
+                        .src(src)
+                        .dest(dest)
+                        .scale(tileset.scale)
+                        .color(shade),
+                );
+                return;
+            }
+        }
+        self.push_rect(
+            Rect::new(dest.x, dest.y, TILE_SIZE.0 as f32, TILE_SIZE.1 as f32),
+            shade,
+        );
+    }
+
+    pub fn build(self) -> TileLayer {
+        self.layer
+    }
+
+    // For layers that change every turn and aren't worth keeping around
+    pub fn finish(self, canvas: &mut Canvas) {
+        self.build().draw(canvas);
+    }
+}
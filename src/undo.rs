@@ -0,0 +1,239 @@
+use crate::{random, world::World};
+use rand_chacha::ChaCha8Rng;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+
+// How many turns back the player can go
+const MAX_UNDO_TURNS: usize = 100;
+// Snapshots are cut into chunks of about 4KB. A cut happens wherever the hash of the last 64
+// bytes has its top CHUNK_BITS bits all zero, so the cuts only depend on the contents nearby
+const CHUNK_BITS: u32 = 12;
+const MIN_CHUNK_SIZE: usize = 512;
+const MAX_CHUNK_SIZE: usize = 64 * 1024;
+
+// The game as it was before each of the last few turns, so they can be taken back. A whole
+// world is big and one turn barely changes it, so snapshots are split into chunks and any chunk
+// that is the same as one in another snapshot is only stored once. Since the cuts depend on the
+// contents, a change somewhere in the world only changes the chunks right around it
+pub struct UndoStack {
+    // oldest first, each snapshot is the ids of its chunks in order
+    snapshots: VecDeque<Vec<u64>>,
+    // every chunk that some snapshot still uses, and how many snapshots use it
+    chunks: HashMap<u64, (Vec<u8>, usize)>,
+}
+
+impl UndoStack {
+    pub fn new() -> Self {
+        UndoStack {
+            snapshots: VecDeque::new(),
+            chunks: HashMap::new(),
+        }
+    }
+
+    // Everything that decides how the game plays out from here, the world and the rng. The world
+    // writes its maps out sorted, so nothing changes between two snapshots of the same game. ron
+    // can't write the rng's 128 bit counter, so like in the save files it goes in as json
+    pub fn snapshot(world: &World, rng: &ChaCha8Rng) -> Vec<u8> {
+        let rng = serde_json::to_string(rng).expect("The rng should always be serializable");
+        ron::to_string(&(world, rng))
+            .expect("The world should always be serializable")
+            .into_bytes()
+    }
+
+    pub fn push(&mut self, snapshot: Vec<u8>) {
+        let mut ids = Vec::new();
+        for chunk in split_chunks(&snapshot) {
+            let mut hasher = DefaultHasher::new();
+            chunk.hash(&mut hasher);
+            let mut id = hasher.finish();
+            // two different chunks can hash the same, so the bytes have to match too. A chunk
+            // that doesn't gets the next free id instead
+            loop {
+                match self.chunks.get_mut(&id) {
+                    Some((bytes, users)) if bytes.as_slice() == chunk => {
+                        *users += 1;
+                        break;
+                    }
+                    Some(_) => id = id.wrapping_add(1),
+                    None => {
+                        self.chunks.insert(id, (chunk.to_vec(), 1));
+                        break;
+                    }
+                }
+            }
+            ids.push(id);
+        }
+        // a turn that changed nothing, like walking into a wall, doesn't need taking back
+        if self.snapshots.back() == Some(&ids) {
+            self.release(&ids);
+            return;
+        }
+        self.snapshots.push_back(ids);
+        if self.snapshots.len() > MAX_UNDO_TURNS {
+            let oldest = self.snapshots.pop_front().unwrap();
+            self.release(&oldest);
+        }
+    }
+
+    // Takes back the last `turns` turns, or as many as there are. Returns the world and rng from
+    // before the earliest of them
+    pub fn rewind(&mut self, turns: usize) -> Option<(World, ChaCha8Rng)> {
+        let mut target: Option<Vec<u64>> = None;
+        for _ in 0..turns.min(self.snapshots.len()) {
+            if let Some(ids) = target.take() {
+                self.release(&ids);
+            }
+            target = self.snapshots.pop_back();
+        }
+        let ids = target?;
+        let bytes: Vec<u8> = ids
+            .iter()
+            .flat_map(|id| self.chunks[id].0.iter().copied())
+            .collect();
+        self.release(&ids);
+        let text = String::from_utf8(bytes).ok()?;
+        let state = ron::from_str::<(World, String)>(&text)
+            .map_err(|e| e.to_string())
+            .and_then(|(world, rng)| {
+                serde_json::from_str(&rng)
+                    .map(|rng| (world, rng))
+                    .map_err(|e| e.to_string())
+            });
+        match state {
+            Ok(state) => Some(state),
+            Err(e) => {
+                println!("Couldn't undo, the snapshot is broken: {}", e);
+                None
+            }
+        }
+    }
+
+    fn release(&mut self, ids: &[u64]) {
+        for id in ids {
+            if let Some(chunk) = self.chunks.get_mut(id) {
+                chunk.1 -= 1;
+                if chunk.1 == 0 {
+                    self.chunks.remove(id);
+                }
+            }
+        }
+    }
+}
+
+// Cuts bytes into chunks using a gear hash, where every byte shifts the hash over by one and adds
+// a random number picked by that byte
+fn split_chunks(bytes: &[u8]) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut hash: u64 = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        hash = (hash << 1).wrapping_add(random::mix(*byte as u64 + 1));
+        let size = i + 1 - start;
+        if (size >= MIN_CHUNK_SIZE && hash >> (64 - CHUNK_BITS) == 0) || size >= MAX_CHUNK_SIZE {
+            chunks.push(&bytes[start..=i]);
+            start = i + 1;
+            hash = 0;
+        }
+    }
+    if start < bytes.len() {
+        chunks.push(&bytes[start..]);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tile, utils::Position};
+    use rand::SeedableRng;
+
+    // Bytes that look random, so the chunk cuts land all over the place
+    fn noise(len: usize) -> Vec<u8> {
+        (0..len as u64).map(|i| random::mix(i) as u8).collect()
+    }
+
+    #[test]
+    fn chunks_cover_everything_within_their_limits() {
+        let bytes = noise(300_000);
+        let chunks = split_chunks(&bytes);
+        assert_eq!(chunks.concat(), bytes);
+        assert!(chunks.len() > 1);
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert!((MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&chunk.len()));
+        }
+        // with nothing to cut on, chunks stop growing at the biggest size
+        let zeros = vec![0; 3 * MAX_CHUNK_SIZE];
+        assert!(split_chunks(&zeros)
+            .iter()
+            .all(|chunk| chunk.len() == MAX_CHUNK_SIZE));
+    }
+
+    #[test]
+    fn a_change_only_touches_the_chunks_around_it() {
+        let bytes = noise(300_000);
+        let mut changed = bytes.clone();
+        changed[150_000] ^= 0xFF;
+        let before = split_chunks(&bytes);
+        let after = split_chunks(&changed);
+        let new = after.iter().filter(|chunk| !before.contains(chunk)).count();
+        assert!(new <= 2, "{} of {} chunks changed", new, after.len());
+    }
+
+    #[test]
+    fn snapshots_share_their_chunks() {
+        let mut world = World::empty();
+        let rng = ChaCha8Rng::seed_from_u64(0);
+        let mut undo = UndoStack::new();
+        undo.push(UndoStack::snapshot(&world, &rng));
+        let stored = undo.chunks.len();
+        // a turn where nothing happened isn't kept
+        undo.push(UndoStack::snapshot(&world, &rng));
+        assert_eq!(undo.snapshots.len(), 1);
+        assert_eq!(undo.chunks.len(), stored);
+
+        world.terrain_map[0][0].insert(Position::new(5, 5), tile::WALL);
+        undo.push(UndoStack::snapshot(&world, &rng));
+        assert_eq!(undo.snapshots.len(), 2);
+        assert!(undo.chunks.len() <= stored + 2);
+    }
+
+    #[test]
+    fn chunks_with_the_same_hash_are_kept_apart() {
+        let world = World::empty();
+        let rng = ChaCha8Rng::seed_from_u64(0);
+        let snapshot = UndoStack::snapshot(&world, &rng);
+        let mut hasher = DefaultHasher::new();
+        split_chunks(&snapshot)[0].hash(&mut hasher);
+        let id = hasher.finish();
+
+        let mut undo = UndoStack::new();
+        undo.chunks.insert(id, (b"something else".to_vec(), 1));
+        undo.push(snapshot.clone());
+        assert_eq!(undo.snapshots[0][0], id.wrapping_add(1));
+        let (world, rng) = undo.rewind(1).unwrap();
+        assert_eq!(UndoStack::snapshot(&world, &rng), snapshot);
+        assert_eq!(undo.chunks[&id].0, b"something else");
+    }
+
+    #[test]
+    fn rewinding_goes_back_to_the_earliest_turn_taken_back() {
+        let mut world = World::empty();
+        let rng = ChaCha8Rng::seed_from_u64(0);
+        let mut undo = UndoStack::new();
+        let mut snapshots = Vec::new();
+        for x in 0..3 {
+            world.terrain_map[0][0].insert(Position::new(x, 5), tile::WALL);
+            snapshots.push(UndoStack::snapshot(&world, &rng));
+            undo.push(snapshots[x].clone());
+        }
+
+        let (world, rng) = undo.rewind(2).unwrap();
+        assert_eq!(UndoStack::snapshot(&world, &rng), snapshots[1]);
+        // asking for more turns than there are goes back as far as it can
+        let (world, rng) = undo.rewind(5).unwrap();
+        assert_eq!(UndoStack::snapshot(&world, &rng), snapshots[0]);
+        assert!(undo.rewind(1).is_none());
+        assert!(undo.chunks.is_empty());
+    }
+}
@@ -1,35 +1,32 @@
 use crate::{
-    direction::Direction, enemy::Enemy, entity::Entity, random, tile, world::World,
-    world::BOSS_ROOMS, BOARD_SIZE, TILE_SIZE, UNIVERSAL_OFFSET, WORLD_SIZE, 
-    player::MAX_PLAYER_HEALTH,
+    combat_log::{Actor, GameEvent},
+    balance::BossBalance,
+    boss::{BossBehavior, BossKind, ScriptedBoss},
+    boss_script::Wave,
+    damage_number::DamageNumber,
+    difficulty::Difficulty,
+    direction::Direction, encounter::Encounter, enemy::Enemy, random, structure::Structure, telegraph::Telegraphs, tile,
+    tileset::{TileBatch, Tileset},
+    world::{World, BOSS_ROOMS, BOSS_ROOM_SCRIPTS},
+    BOARD_SIZE, TILE_SIZE, UNIVERSAL_OFFSET, WORLD_SIZE, 
 };
-use ggez::{graphics::{self, Canvas}, mint::Point2, glam::*};
+use ggez::{graphics::{self, Canvas}, mint::Point2, glam::*, Context};
 use rand::rngs;
 use rand::rngs::ThreadRng;
 use rand_chacha::ChaCha8Rng;
 use std::{cmp::max, collections::HashMap};
 use ggez::glam::*;
 
-pub const BOSS_HEALTH: usize = 1000;
-pub const MAJOR_BOSS_HEALTH: usize = 5000;
-const LASER_LINGER_VALUE: usize = 3;
-const ASTEROID_LINGER_VALUE: usize = 5;
-const ASTEROID_COOLDOWN: usize = 20;
-const STUN_WELL_COOLDOWN: usize = 10;
-const STUN_WELL_LINGER_VALUE: usize = 50;
-const LASER_DAMAGE: usize = 5;
-const COLUMN_LASER_DAMAGE: usize = 20;
-const LASER_AMOUNT: usize = 7;
-const ASTEROID_DAMAGE: usize = 10;
-const VULNERABLE_TIME_BASE: usize = 15;
-const BOSS_3_RUSH_COOLDOWN: usize = 20;
-const BOSS_3_MOVE_DELAY: usize = 2;
-const SAFE_SPOT_ATTACK_COOLDOWN: usize = 20;
-const SAFE_SPOT_TIME: usize = 10;
-const BOSS_COLLISION_DAMAGE: usize = 10;
-const STUN_WELL_STUN_TIME: usize = 2;
-const SHIELD_HITS_NEEDED: usize = 3;
-const ENEMY_SPAWN_COOLDOWN: usize = 10;
+#[derive(Debug, Clone, Copy, PartialEq)]
+// The parts of a boss fight the combat log points out
+pub enum BossPhase {
+    Attacking,
+    // the shield is down and the boss can be hurt
+    Vulnerable,
+    Blackout,
+    // the boss moved on to the next phase of its script
+    Stage(usize),
+}
 
 #[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq)]
 pub struct Boss {
@@ -37,224 +34,119 @@ pub struct Boss {
     pub color: [f32; 4],
     pub world_position: Position,
     pub health: usize,
-    pub laser_amount: usize,
-    pub stun_well_cooldown: usize,
-    pub asteroid_cooldown: usize,
-    pub safe_spot_cooldown: usize,
-    pub enemy_spawn_cooldown: usize,
     pub is_major: bool,
     pub offset: usize,
     pub boss_can_attack: bool,
-    pub rush_info: (bool, Option<Direction>, [f32; 4], usize), //is rushing, direction, color of rush indicator, length of indicator
     pub vulnerable_time: usize,
-    pub shield_health: usize,
-    pub chase_rush_cooldown: usize,
-    pub speed_delay: usize,
-    pub first_enter: bool,
+    // which boss this is, and everything only that boss keeps track of. Older saves don't have
+    // it, see restore_kinds
+    #[serde(default)]
+    pub kind: BossKind,
 }
 
 impl Boss {
     pub fn new(
         x: usize,
         y: usize,
-        color: [f32; 4],
+        kind: BossKind,
         world_position: Position,
         terrain_loc: &mut HashMap<Position, [f32; 4]>,
+        difficulty: Difficulty,
+        balance: &BossBalance,
     ) -> Self {
         let mut offset: usize = 4;
-        let is_major: bool = color == tile::MAJOR_BOSS;
-        let mut health = BOSS_HEALTH;
+        let is_major: bool = kind.behavior().is_major();
+        let mut health = balance.health;
         if is_major {
             offset = 5;
-        }
-        if color == tile::MAJOR_BOSS {
-            health = MAJOR_BOSS_HEALTH;
+            health = balance.major_health;
         }
         Boss {
             position: Position::new(x, y),
-            color,
+            color: kind.behavior().color(),
             world_position,
-            health,
-            laser_amount: LASER_AMOUNT,
-            safe_spot_cooldown: SAFE_SPOT_ATTACK_COOLDOWN,
-            asteroid_cooldown: ASTEROID_COOLDOWN,
-            stun_well_cooldown: STUN_WELL_COOLDOWN,
-            enemy_spawn_cooldown: 0,
+            health: difficulty.boss_health(health),
             is_major,
             offset,
-            first_enter: true,
-            rush_info: (false, None, tile::BOSS_LASER_STAGE_1, 0),
             boss_can_attack: true,
             vulnerable_time: 0,
-            shield_health: SHIELD_HITS_NEEDED,
-            chase_rush_cooldown: BOSS_3_RUSH_COOLDOWN,
-            speed_delay: BOSS_3_MOVE_DELAY,
+            kind,
         }
     }
 
-    pub fn spawn_enemies(world: &mut World, rng: &mut ChaCha8Rng, index: usize) {
-        let curr_entity_map = &world.enemies_map[world.world_position.y as usize][world.world_position.x as usize]; 
-        if world.world_position == BOSS_ROOMS[0] {
-            if curr_entity_map.is_empty() {
-                if world.bosses[index].vulnerable_time == 0 && world.bosses[index].boss_can_attack && !world.bosses[index].first_enter{
-                    world.bosses[index].vulnerable_time = VULNERABLE_TIME_BASE + 10;
-                    world.bosses[index].boss_can_attack = false;
-                } else if world.bosses[index].vulnerable_time != 0 {
-                    world.bosses[index].vulnerable_time -= 1;
-                } else {
-                    world.bosses[index].first_enter = false;
-                    world.bosses[index].boss_can_attack = true;
-                    for i in 0..13 {
-                        let mut pos = world.bosses[index].position;
-                        let size = world.bosses[index].offset - 1;
-                        while Self::pos_inside_boss(world, Position::new(pos.x + size, pos.y), world.world_position) ||
-                            Self::pos_inside_boss(world, Position::new(pos.x - size, pos.y), world.world_position) ||
-                            Self::pos_inside_boss(world, Position::new(pos.x, pos.y + size), world.world_position) ||
-                            Self::pos_inside_boss(world, Position::new(pos.x, pos.y - size), world.world_position) {
-                                let x = random::rand_range(rng, 3, WORLD_SIZE.0 - 5) as usize;
-                                let y = random::rand_range(rng, 3, WORLD_SIZE.1-  5) as usize;
-                                pos = Position::new(x,y);
-                        }
-                        if i <= 3 {
-                            world.enemies_map[world.world_position.y as usize][world.world_position.x as usize].push(Enemy::major_enemy(
-                                pos.x as usize,
-                                pos.y as usize,
-                                world.world_position),);
-                            for h in 0..3 {
-                                for j in 0..3 {
-                                    world.entity_map[world.world_position.y][world.world_position.x].insert(
-                                        Position::new(pos.x as usize + h, pos.y as usize + j), (tile::MAJOR_ENEMY, Entity::Enemy));
-                                }
-                            }
+    pub fn spawn_wave(world: &mut World, rng: &mut ChaCha8Rng, index: usize, wave: &Wave) {
+        let size = world.bosses[index].offset - 1;
+        Enemy::spawn_wave(world, rng, wave, |world, pos| Self::area_touches_boss(world, pos, size));
+    }
 
-                        } else {
-                            world.enemies_map[world.world_position.y as usize][world.world_position.x as usize].push(Enemy::bomber(
-                                pos.x as usize,
-                                pos.y as usize,
-                                world.world_position),);
-                            world.entity_map[world.world_position.y][world.world_position.x].insert(pos, (tile::BOMBER_ENEMY, Entity::Enemy));
-                        }
-                    }
-                }
-            } 
-        } else if world.world_position == BOSS_ROOMS[1] {
-            if curr_entity_map.is_empty() && world.bosses[index].enemy_spawn_cooldown == 0 {
-                for i in 0..9 {
-                    let mut pos = world.bosses[index].position;
-                    let size = world.bosses[index].offset - 1;
-                        while Self::pos_inside_boss(world, Position::new(pos.x + size, pos.y), world.world_position) ||
-                            Self::pos_inside_boss(world, Position::new(pos.x - size, pos.y), world.world_position) ||
-                            Self::pos_inside_boss(world, Position::new(pos.x, pos.y + size), world.world_position) ||
-                            Self::pos_inside_boss(world, Position::new(pos.x, pos.y - size), world.world_position) {
-                            let x = random::rand_range(rng, 3, WORLD_SIZE.0 - 5) as usize;
-                            let y = random::rand_range(rng, 3, WORLD_SIZE.1 - 5) as usize;
-                            pos = Position::new(x,y);
-                        }
-                    if i <= 2 {
-                        world.enemies_map[world.world_position.y as usize][world.world_position.x as usize].push(Enemy::major_enemy(
-                            pos.x as usize,
-                            pos.y as usize,
-                            world.world_position),);
-                        for h in 0..3 {
-                            for j in 0..3 {
-                                world.entity_map[world.world_position.y][world.world_position.x].insert(
-                                    Position::new(pos.x as usize + h, pos.y as usize + j), (tile::MAJOR_ENEMY, Entity::Enemy));
-                            }
-                        }
+    // Whether a square reaching size tiles out from pos in every direction would poke into the boss
+    pub fn area_touches_boss(world: &World, pos: Position, size: usize) -> bool {
+        Self::pos_inside_boss(world, Position::new(pos.x + size, pos.y), world.world_position) ||
+        Self::pos_inside_boss(world, Position::new(pos.x - size, pos.y), world.world_position) ||
+        Self::pos_inside_boss(world, Position::new(pos.x, pos.y + size), world.world_position) ||
+        Self::pos_inside_boss(world, Position::new(pos.x, pos.y - size), world.world_position)
+    }
 
-                    } else {
-                        world.enemies_map[world.world_position.y as usize][world.world_position.x as usize].push(Enemy::chasing(
-                            pos.x as usize,
-                            pos.y as usize,
-                            world.world_position),);
-                        world.entity_map[world.world_position.y][world.world_position.x].insert(pos, (tile::CHASING_ENEMY, Entity::Enemy));
-                    }
-                }
-                world.bosses[index].enemy_spawn_cooldown = ENEMY_SPAWN_COOLDOWN;
-            } else {
-                if world.bosses[index].enemy_spawn_cooldown != 0 {
-                    world.bosses[index].enemy_spawn_cooldown -= 1;
-                }
+    // Saves from before bosses kept their own state don't say which boss is which, so it's worked
+    // out from the room each one is in. Their attacks start over from the beginning
+    pub fn restore_kinds(world: &mut World) {
+        for boss in &mut world.bosses {
+            if !boss.kind.is_missing() {
+                continue;
             }
-        } else if world.world_position == BOSS_ROOMS[4] {
-            if curr_entity_map.is_empty() && world.bosses[index].enemy_spawn_cooldown == 0 {
-                for i in 0..10 {
-                    let mut pos = world.bosses[index].position;
-                    let size = world.bosses[index].offset - 1;
-                        while Self::pos_inside_boss(world, Position::new(pos.x + size, pos.y), world.world_position) ||
-                            Self::pos_inside_boss(world, Position::new(pos.x - size, pos.y), world.world_position) ||
-                            Self::pos_inside_boss(world, Position::new(pos.x, pos.y + size), world.world_position) ||
-                            Self::pos_inside_boss(world, Position::new(pos.x, pos.y - size), world.world_position) {
-                            let x = random::rand_range(rng, 3, WORLD_SIZE.0) as usize;
-                            let y = random::rand_range(rng, 3, WORLD_SIZE.1) as usize;
-                            pos = Position::new(x,y);
-                        }
-                    world.enemies_map[world.world_position.y as usize][world.world_position.x as usize].push(Enemy::chasing(
-                        pos.x as usize,
-                        pos.y as usize,
-                        world.world_position),);
-                    world.entity_map[world.world_position.y][world.world_position.x].insert(pos, (tile::CHASING_ENEMY, Entity::Enemy));
-                }
-                world.bosses[index].enemy_spawn_cooldown = ENEMY_SPAWN_COOLDOWN;
-            } else {
-                if world.bosses[index].enemy_spawn_cooldown != 0 {
-                    world.bosses[index].enemy_spawn_cooldown -= 1;
-                }
+            let script = BOSS_ROOMS
+                .iter()
+                .position(|room| *room == boss.world_position)
+                .map(|room| BOSS_ROOM_SCRIPTS[room]);
+            if let Some((script, boss_script)) =
+                script.and_then(|script| Some((script, world.boss_scripts.get(script)?)))
+            {
+                boss.kind = BossKind::Scripted(ScriptedBoss::new(script, boss_script));
             }
-        } else if world.world_position == BOSS_ROOMS[2] {
-            if curr_entity_map.is_empty() && world.bosses[index].enemy_spawn_cooldown == 0 {
-                for i in 0..33 {
-                    let mut pos = world.bosses[index].position;
-                    let size = world.bosses[index].offset - 2;
-                        while Self::pos_inside_boss(world, Position::new(pos.x + size, pos.y), world.world_position) ||
-                            Self::pos_inside_boss(world, Position::new(pos.x - size, pos.y), world.world_position) ||
-                            Self::pos_inside_boss(world, Position::new(pos.x, pos.y + size), world.world_position) ||
-                            Self::pos_inside_boss(world, Position::new(pos.x, pos.y - size), world.world_position) {
-                            let x = random::rand_range(rng, 3, WORLD_SIZE.0 - 5) as usize;
-                            let y = random::rand_range(rng, 3, WORLD_SIZE.1 - 5) as usize;
-                            pos = Position::new(x,y);
-                        }
-                    if i <= 3 {
-                        world.enemies_map[world.world_position.y as usize][world.world_position.x as usize].push(Enemy::major_enemy(
-                            pos.x as usize,
-                            pos.y as usize,
-                            world.world_position),);
-                        for h in 0..3 {
-                            for j in 0..3 {
-                                world.entity_map[world.world_position.y][world.world_position.x].insert(
-                                    Position::new(pos.x as usize + h, pos.y as usize + j), (tile::MAJOR_ENEMY, Entity::Enemy));
-                            }
-                        }
-
-                    } else if i <= 18{
-                        world.enemies_map[world.world_position.y as usize][world.world_position.x as usize].push(Enemy::chasing(
-                            pos.x as usize,
-                            pos.y as usize,
-                            world.world_position),);
-                        world.entity_map[world.world_position.y][world.world_position.x].insert(pos, (tile::CHASING_ENEMY, Entity::Enemy));
+        }
+    }
 
-                    } else {
-                        world.enemies_map[world.world_position.y as usize][world.world_position.x as usize].push(Enemy::bomber(
-                            pos.x as usize,
-                            pos.y as usize,
-                            world.world_position),);
-                        world.entity_map[world.world_position.y][world.world_position.x].insert(pos, (tile::BOMBER_ENEMY, Entity::Enemy));
+    // Runs one of the boss's behaviour hooks. The behaviour is taken out of the boss while it runs
+    // so it can be handed the whole world, then put back
+    fn with_behavior<T>(
+        world: &mut World,
+        index: usize,
+        hook: impl FnOnce(&mut dyn BossBehavior, &mut World) -> T,
+    ) -> T {
+        let mut kind = world.bosses[index].kind.clone();
+        let result = hook(kind.behavior_mut(), world);
+        world.bosses[index].kind = kind;
+        result
+    }
 
-                    }
-                } 
-                world.bosses[index].enemy_spawn_cooldown = ENEMY_SPAWN_COOLDOWN;
-            } else {
-                if world.bosses[index].enemy_spawn_cooldown != 0 {
-                    world.bosses[index].enemy_spawn_cooldown -= 1;
-                }
+    // Lets the boss in the player's room know they just walked in
+    pub fn enter(world: &mut World, rng: &mut ChaCha8Rng) {
+        for index in 0..world.bosses.len() {
+            if world.bosses[index].world_position == world.world_position {
+                Self::with_behavior(world, index, |boss, world| boss.on_enter(world, index, rng));
             }
-        }  
+        }
     }
 
     pub fn update(world: &mut World, rng: &mut ChaCha8Rng) {
+        let world_pos = world.world_position;
+        let entered = world.events.iter().any(|event| {
+            matches!(event, GameEvent::RoomEntered { world_pos: room } if *room == world_pos)
+        });
+        if entered {
+            Self::enter(world, rng);
+        }
         for index in (0..world.bosses.len()).rev() {
             if world.bosses[index].world_position == world.world_position {
-                Self::attack(world, index, rng);
+                let phase = Self::phase(world, index);
+                Self::with_behavior(world, index, |boss, world| boss.take_turn(world, index, rng));
+                let new_phase = Self::phase(world, index);
+                if new_phase != phase {
+                    world.events.push(GameEvent::BossPhaseChanged {
+                        boss: world.bosses[index].name(),
+                        phase: new_phase,
+                    });
+                }
             }
             if world.bosses[index].health <= 0 {
                 Self::kill(world, index);
@@ -262,7 +154,14 @@ impl Boss {
         }
     }
 
-    pub fn draw_boss(world: &mut World, canvas: &mut graphics::Canvas, index: usize) {
+    pub fn draw_boss(
+        world: &mut World,
+        ctx: &Context,
+        canvas: &mut graphics::Canvas,
+        index: usize,
+        tileset: Option<&Tileset>,
+    ) {
+        let mut batch = TileBatch::new(ctx, tileset);
         let boss_size = (world.bosses[index].offset - 1) as i32;
         for i in -boss_size..=boss_size {
             for j in -boss_size..=boss_size {
@@ -279,89 +178,29 @@ impl Boss {
                         color = tile::BOSS_SURROUNDINGS;
                     }
                 }
-                if let Some(spot) = world.boss_vulnerable_spot {
+                if let Some(spot) = world.encounter().vulnerable_spot {
                     if Position::new((pos.x as i32 + i) as usize, (pos.y as i32+ j) as usize) == spot {
                         color = tile::BOSS_VULNERABLE
                     }
                 }
 
-                canvas.draw(
-                    &graphics::Quad,
-                    graphics::DrawParam::new()
-                        .dest_rect(graphics::Rect::new_i32(
-                            (pos.x as i32 + i as i32) * TILE_SIZE.0 as i32,
-                            (pos.y as i32
-                                + j as i32
-                                + UNIVERSAL_OFFSET as i32)
-                                * TILE_SIZE.1 as i32,
-                            TILE_SIZE.0 as i32,
-                            TILE_SIZE.1 as i32,
-                        ))
-                        .color(color),
+                batch.push(
+                    Position::new((pos.x as i32 + i) as usize, (pos.y as i32 + j) as usize),
+                    color,
                 );
             }
         }
+        batch.finish(canvas);
     }
 
-    pub fn draw_boss_stuff(world: &mut World, canvas: &mut graphics::Canvas, index: usize, rng: &mut ChaCha8Rng) {
-        if world.world_position == BOSS_ROOMS[0] {
-            Self::draw_lasers(world, canvas, rng);
-        } else if world.world_position == BOSS_ROOMS[1] {
-            Self::draw_laser_column(world, index, canvas);
-            Self::draw_asteroids(world, index, canvas);
-        } else if world.world_position == BOSS_ROOMS[3] {
-            if world.bosses[index].rush_info.0 {
-                Self::draw_rush(world, index, world.bosses[index].rush_info.3, canvas);
-            }
-            Self::draw_stun_wells(world, canvas);
-        } else if world.world_position == BOSS_ROOMS[4] {
-            Self::draw_safe_spot(world, canvas);
-        } else if world.world_position == BOSS_ROOMS[2] {
-            Self::draw_stun_wells(world, canvas);
-            Self::draw_laser_column(world, index, canvas);
-            Self::draw_asteroids(world, index, canvas);
-            Self::draw_lasers(world, canvas, rng);
-        }  
+    pub fn draw_boss_stuff(world: &mut World, ctx: &Context, canvas: &mut graphics::Canvas, index: usize) {
+        Self::with_behavior(world, index, |boss, world| boss.draw(world, index, ctx, canvas));
     }
 
     pub fn coin_flip(rng: &mut ChaCha8Rng) -> bool {
         random::rand_range(rng, 0, 2) > 0
     }
 
-    pub fn attack(world: &mut World, index: usize, rng: &mut ChaCha8Rng) {
-        if world.world_position == BOSS_ROOMS[0] {
-            Self::generate_lasers(world, world.bosses[index].laser_amount, index, rng);
-            Self::check_laser_damage(world);
-            Self::spawn_enemies(world, rng, index);
-        } else if world.world_position == BOSS_ROOMS[1] {
-            Self::generate_asteroid(world, index);
-            Self::check_asteroid_damage(world);
-            Self::generate_column_laser(world, index);
-            Self::check_laser_column_damage(world, index);
-            Self::spawn_enemies(world, rng, index);
-        } else if world.world_position == BOSS_ROOMS[3] {
-            Self::chase_player(world, index);
-            Self::generate_stun_well(world, index, rng);
-            Self::check_stun_well_stun(world);
-            Self::spawn_enemies(world, rng, index);
-        } else if world.world_position == BOSS_ROOMS[4] {
-            Self::generate_safe_spot(world, index, rng);
-            Self::check_survive_black_out(world);
-            Self::generate_vulnerable_spot(world, index, rng);
-            Self::spawn_enemies(world, rng, index);
-        } else if world.world_position == BOSS_ROOMS[2] {
-            Self::spawn_enemies(world, rng, index);
-            Self::generate_asteroid(world, index);
-            Self::check_asteroid_damage(world);
-            Self::generate_lasers(world, world.bosses[index].laser_amount, index, rng);
-            Self::check_laser_damage(world);
-            Self::generate_column_laser(world, index);
-            Self::check_laser_column_damage(world, index);
-            Self::generate_stun_well(world, index, rng);
-            Self::check_stun_well_stun(world);
-        }  
-    }
-
     pub fn pos_inside_boss(world: &World, pos: Position, world_pos: Position) -> bool {
         for boss_info in &world.bosses {
             if boss_info.world_position == world_pos {
@@ -374,50 +213,70 @@ impl Boss {
         return false;
     }
 
-    pub fn generate_lasers(world: &mut World, num_lasers: usize, index: usize, rng: &mut ChaCha8Rng) {
-        let lasers = &mut world.boss_lasers;
-        for index in (0..lasers.len()).rev() {
-            match lasers[index].1 {
-                tile::BOSS_LASER_STAGE_1 => {
-                    lasers[index].1 = tile::BOSS_LASER_STAGE_2;
-                },
+    // Counts down every laser, asteroid and stun well in the room, once a turn no matter how many
+    // attacks made them
+    pub fn tick_hazards(world: &mut World) {
+        let tick = |telegraph: &mut usize, linger: &mut usize| {
+            if *telegraph > 0 {
+                *telegraph -= 1;
+                true
+            } else if *linger == 0 {
+                false
+            } else {
+                *linger -= 1;
+                true
+            }
+        };
+        world.encounter_mut().lasers.retain_mut(|laser| tick(&mut laser.1, &mut laser.2));
+        world.encounter_mut().asteroids.retain_mut(|asteroid| tick(&mut asteroid.1, &mut asteroid.2));
+        world.encounter_mut().stun_wells.retain_mut(|well| tick(&mut well.1, &mut well.3));
+    }
 
-                tile::BOSS_LASER_STAGE_2 => {
-                    lasers[index].1 = tile::BOSS_LASER_REAL;
-                },
+    // Hurts whatever is standing in the boss's attacks once they've gone off
+    pub fn check_hazards(world: &mut World, index: usize) {
+        Self::check_laser_damage(world);
+        Self::check_asteroid_damage(world);
+        Self::check_laser_column_damage(world, index);
+        Self::check_stun_well_stun(world);
+        Self::check_survive_black_out(world);
+    }
 
-                _ => {
-                    if lasers[index].2 == 0 {
-                        lasers.remove(index);
-                    } else {
-                        lasers[index].2 -= 1;
-                    }
-                }
-            }
+    // Lasers fade in over the last two turns of their telegraph
+    pub fn laser_color(telegraph: usize) -> [f32; 4] {
+        match telegraph {
+            0 => tile::BOSS_LASER_REAL,
+            1 => tile::BOSS_LASER_STAGE_2,
+            _ => tile::BOSS_LASER_STAGE_1,
+        }
+    }
+
+    fn asteroid_color(telegraph: usize) -> [f32; 4] {
+        match telegraph {
+            0 => tile::BOSS_ASTEROID_REAL,
+            1 => tile::BOSS_ASTEROID_STAGE_3,
+            2 => tile::BOSS_ASTEROID_STAGE_2,
+            _ => tile::BOSS_ASTEROID_STAGE_1,
         }
+    }
 
+    pub fn generate_lasers(world: &mut World, num_lasers: usize, telegraph: usize, linger: usize, rng: &mut ChaCha8Rng) {
+        let telegraph = world.difficulty.telegraph(telegraph);
         for _ in 0..num_lasers {
             let coord: Position = if Boss::coin_flip(rng) {
                 Position::new(0, random::rand_range(rng, 0, BOARD_SIZE.1) as usize)
             } else {
                 Position::new(random::rand_range(rng, 0, BOARD_SIZE.0) as usize, 0)
             };
-            world
-                .boss_lasers
-                .push((coord, tile::BOSS_LASER_STAGE_1, LASER_LINGER_VALUE));
+            world.encounter_mut().lasers
+                .push((coord, telegraph, linger));
         }
     }
 
-    pub fn draw_lasers(world: &mut World, canvas: &mut Canvas, rng: &mut ChaCha8Rng) {
-         for lasers in &mut world.boss_lasers {
-            let mut special_case = true;
-            if lasers.0 == Position::new(0,0) {
-                if Boss::coin_flip(rng) {
-                    special_case = false;
-                }
-            }
-
-            if lasers.0.x == 0 && special_case {
+    pub fn draw_lasers(world: &mut World, canvas: &mut Canvas) {
+         for lasers in &world.encounter().lasers {
+            // a laser at (0, 0) could be either a row or a column, it is always drawn as a row
+            // so that drawing doesn't need the rng
+            if lasers.0.x == 0 {
                 for i in 0..WORLD_SIZE.0 {
                     canvas.draw(
                         &graphics::Quad,
@@ -428,7 +287,7 @@ impl Boss {
                                 TILE_SIZE.0 as i32,
                                 TILE_SIZE.1 as i32,
                             ))
-                            .color(lasers.1),
+                            .color(Self::laser_color(lasers.1)),
                     )
                 }
             } else {
@@ -442,29 +301,48 @@ impl Boss {
                                 TILE_SIZE.0 as i32,
                                 TILE_SIZE.1 as i32,
                             ))
-                            .color(lasers.1),
+                            .color(Self::laser_color(lasers.1)),
                     )
                 }
             }
         }
     }
     
+    // Whether a laser hits pos. Lasers reach across the whole room but not into the walls
+    fn in_laser(laser: Position, pos: Position) -> bool {
+        (pos.x == laser.x || pos.y == laser.y)
+            && pos.y != 0
+            && pos.x != 0
+            && pos.y != WORLD_SIZE.1 as usize - 1
+            && pos.x != WORLD_SIZE.0 as usize - 1
+    }
+
     pub fn check_laser_damage(world: &mut World) {
-        for laser in &world.boss_lasers {
-            if (world.player.pos.x == laser.0.x
-            || world.player.pos.y == laser.0.y)
-            && laser.1 == tile::BOSS_LASER_REAL
-            && world.player.pos.y != 0
-            && world.player.pos.x != 0
-            && world.player.pos.y != WORLD_SIZE.1 as usize - 1
-            && world.player.pos.x != WORLD_SIZE.0 as usize - 1 {
-                world.player.damage(LASER_DAMAGE);
-            }
+        let active_lasers: Vec<Position> = world.encounter().lasers
+            .iter()
+            .filter(|laser| laser.1 == 0)
+            .map(|laser| laser.0)
+            .collect();
+        for laser in &active_lasers {
+            if Self::in_laser(*laser, world.player.pos) {
+                world.player.damage(world.difficulty.damage(world.balance.bosses.laser_damage), Actor::Hazard("a laser"));
+            }
+        }
+
+        for laser in active_lasers {
+            //Lasers starting on the left edge are horizontal, the rest are vertical
+            Structure::damage_where(world, world.world_position, world.balance.bosses.attack_structure_damage, |pos| {
+                if laser.x == 0 {
+                    pos.y == laser.y
+                } else {
+                    pos.x == laser.x
+                }
+            });
         }
     }
     
-    pub fn draw_asteroids(world: &mut World, index: usize, canvas: &mut Canvas) {
-        for asteroids in &mut world.boss_asteroids {
+    pub fn draw_asteroids(world: &mut World, canvas: &mut Canvas) {
+        for asteroids in &world.encounter().asteroids {
             for i in 0..=2 {
                 for j in 0..=2 {
                     canvas.draw(
@@ -476,154 +354,139 @@ impl Boss {
                                 TILE_SIZE.0 as i32,
                                 TILE_SIZE.1 as i32,
                             ))
-                            .color(asteroids.1),
+                            .color(Self::asteroid_color(asteroids.1)),
                     )
                 }
             }
         }
     }
 
-    pub fn generate_asteroid(world: &mut World, index: usize) {
-        let asteroids = &mut world.boss_asteroids;
-        let cooldown = world.bosses[index].asteroid_cooldown;
-        for index in (0..asteroids.len()).rev() {
-            match asteroids[index].1 {
-                tile::BOSS_ASTEROID_STAGE_1 => {
-                    asteroids[index].1 = tile::BOSS_ASTEROID_STAGE_2;
-                },
-
-                tile::BOSS_ASTEROID_STAGE_2 => {
-                    asteroids[index].1 = tile::BOSS_ASTEROID_STAGE_3;
-                },
+    pub fn generate_asteroid(world: &mut World, telegraph: usize, linger: usize) {
+        let pos = world.player.pos;
+        let telegraph = world.difficulty.telegraph(telegraph);
+        world.encounter_mut().asteroids.push((pos, telegraph, linger));
+    }
 
-                tile::BOSS_ASTEROID_STAGE_3 => {
-                    asteroids[index].1 = tile::BOSS_ASTEROID_REAL;
-                },
+    // Asteroids hit the 3x3 square around where they land
+    fn in_asteroid(asteroid: Position, pos: Position) -> bool {
+        (pos.x as i32 - asteroid.x as i32).abs() <= 1 && (pos.y as i32 - asteroid.y as i32).abs() <= 1
+    }
 
-                _ => {
-                    if asteroids[index].2 == 0 {
-                        asteroids.remove(index);
-                    } else {
-                        asteroids[index].2 -= 1;
-                    }
-                }
+    pub fn check_asteroid_damage(world: &mut World) {
+        let active_asteroids: Vec<Position> = world.encounter().asteroids
+            .iter()
+            .filter(|asteroid| asteroid.1 == 0)
+            .map(|asteroid| asteroid.0)
+            .collect();
+        for asteroid in &active_asteroids {
+            if Self::in_asteroid(*asteroid, world.player.pos) {
+                world.player.damage(world.difficulty.damage(world.balance.bosses.asteroid_damage), Actor::Hazard("an asteroid"));
             }
         }
-        
-        if cooldown == 0 {
-            world.boss_asteroids.push((
-                world.player.pos,
-                tile::BOSS_ASTEROID_STAGE_1,
-                ASTEROID_LINGER_VALUE,
-            ));
-            world.bosses[index].asteroid_cooldown = ASTEROID_COOLDOWN;
+
+        for asteroid in active_asteroids {
+            Structure::damage_where(world, world.world_position, world.balance.bosses.attack_structure_damage, |pos| {
+                Self::in_asteroid(asteroid, pos)
+            });
+        }
+    }
+    
+    // How far the column laser reaches to either side of its middle
+    fn column_laser_width(boss: &Boss) -> usize {
+        if boss.is_major {
+            3
+        } else {
+            2
+        }
+    }
+
+    // Whether the column laser hits pos. It fires from the boss's edge, away from the boss, all the
+    // way to the wall
+    fn in_column_laser(laser: Position, boss_pos: Position, width: usize, pos: Position) -> bool {
+        let across = |a: usize, b: usize| (a as i32 - b as i32).abs() <= width as i32;
+        if laser.x > boss_pos.x {
+            across(pos.y, laser.y) && pos.x >= laser.x
+        } else if laser.x < boss_pos.x {
+            across(pos.y, laser.y) && pos.x <= laser.x
+        } else if laser.y > boss_pos.y {
+            across(pos.x, laser.x) && pos.y >= laser.y
+        } else if laser.y < boss_pos.y {
+            across(pos.x, laser.x) && pos.y <= laser.y
         } else {
-            world.bosses[index].asteroid_cooldown -= 1;
+            false
         }
     }
 
-    pub fn check_asteroid_damage(world: &mut World) {
-        for asteroid in &world.boss_asteroids {
-            if (world.player.pos.x <= asteroid.0.x + 1
-            && world.player.pos.x >= asteroid.0.x - 1
-            && world.player.pos.y <= asteroid.0.y + 1
-            && world.player.pos.y >= asteroid.0.y - 1)
-            && asteroid.1 == tile::BOSS_ASTEROID_REAL {
-                world.player.damage(ASTEROID_DAMAGE);
+    // Where the column laser and the boss carrying it are after sliding one more tile, or None
+    // once the boss has reached the wall
+    fn column_laser_step(laser: Position, direction: Direction, boss_pos: Position, offset: usize) -> Option<(Position, Position)> {
+        let (mut laser, mut boss_pos) = (laser, boss_pos);
+        match direction {
+            Direction::North => {
+                if boss_pos.y - offset == 0 {
+                    return None;
+                }
+                laser.y -= 1;
+                boss_pos.y -= 1;
+            }
+            Direction::South => {
+                if boss_pos.y + offset == WORLD_SIZE.1 as usize - 1 {
+                    return None;
+                }
+                laser.y += 1;
+                boss_pos.y += 1;
+            }
+            Direction::East => {
+                if boss_pos.x + offset == WORLD_SIZE.0 as usize - 1 {
+                    return None;
+                }
+                laser.x += 1;
+                boss_pos.x += 1;
+            }
+            Direction::West => {
+                if boss_pos.x - offset == 0 {
+                    return None;
+                }
+                laser.x -= 1;
+                boss_pos.x -= 1;
             }
         }
+        Some((laser, boss_pos))
     }
-    
-    pub fn draw_laser_column(world: &mut World, index: usize, canvas: &mut Canvas) {
-        let boss_pos = world.bosses[index].position;
-        let mut laser_width: i32 = -2;
-        if world.bosses[index].is_major {
-            laser_width = -3;
-        }
-        if let Some(laser_pos) = world.boss_column_laser {
-            for j in laser_width..=laser_width.abs() {
-                for i in 0..WORLD_SIZE.1 / 2 {
-                    let mut x: i32 = laser_pos.0.x as i32;
-                    let mut y: i32 = laser_pos.0.y as i32;
-                    if laser_pos.0.x > boss_pos.x {
-                        y = y + j;
-                        x = x + i as i32;
-                    } else if laser_pos.0.x < boss_pos.x {
-                        y = y + j;
-                        x = x - i as i32;
-                    } else if laser_pos.0.y > boss_pos.y {
-                        y = y + i as i32;
-                        x = x + j;
-                    } else if laser_pos.0.y < boss_pos.y {
-                        y = y - i as i32;
-                        x = x + j;
-                    }
 
-                    canvas.draw(
-                        &graphics::Quad,
-                        graphics::DrawParam::new()
-                            .dest_rect(graphics::Rect::new_i32(
-                                x * TILE_SIZE.0 as i32,
-                                (y + UNIVERSAL_OFFSET as i32) * TILE_SIZE.1 as i32,
-                                TILE_SIZE.0 as i32,
-                                TILE_SIZE.1 as i32,
-                            ))
-                            .color(tile::BOSS_LASER_REAL),
-                    )
+    pub fn draw_laser_column(world: &mut World, index: usize, batch: &mut TileBatch) {
+        let boss = &world.bosses[index];
+        let width = Self::column_laser_width(boss);
+        if let Some(laser) = world.encounter().column_laser {
+            for x in 0..WORLD_SIZE.0 as usize {
+                for y in 0..WORLD_SIZE.1 as usize {
+                    if !Self::in_column_laser(laser.0, boss.position, width, Position::new(x, y)) {
+                        continue;
+                    }
+                    batch.push(Position::new(x, y), Self::laser_color(laser.2));
                 }
             }
         }
     }
 
-    pub fn generate_column_laser(world: &mut World, index: usize) {
+    pub fn generate_column_laser(world: &mut World, index: usize, telegraph: usize) {
         let time_vulnerable = world.bosses[index].vulnerable_time;
         let offset = world.bosses[index].offset;
-        let mut boss_pos = world.bosses[index].position;
-        let can_attack = &mut world.bosses[index].boss_can_attack;
-        if let Some(curr_pos) = &mut world.boss_column_laser {
-            let pos = curr_pos.0;
-            match curr_pos.1 {
-                Direction::North => {
-                    if boss_pos.y - offset == 0 {
-                        world.boss_column_laser = None;
-                    } else {
-                        boss_pos.y -= 1;
-                        curr_pos.0.y = pos.y - 1;
-                        Self::move_boss(world, index, boss_pos, Direction::North);
-                    }
-                }
-
-                Direction::South => {
-                    if boss_pos.y + offset == WORLD_SIZE.1 as usize - 1 {
-                        world.boss_column_laser = None;
-                    } else {
-                        boss_pos.y += 1;
-                        curr_pos.0.y = pos.y + 1;
-                        Self::move_boss(world, index, boss_pos, Direction::South);
-                    }
-                }
-
-                Direction::East => {
-                    if boss_pos.x + offset == WORLD_SIZE.0 as usize - 1 {
-                        world.boss_column_laser = None;
-                    } else {
-                        boss_pos.x += 1;
-                        curr_pos.0.x = pos.x + 1;
-                        Self::move_boss(world, index, boss_pos, Direction::East);
-                    }
-                }
-
-                Direction::West => {
-                    if boss_pos.x - offset == 0 {
-                        world.boss_column_laser = None;
-                    } else {
-                        boss_pos.x -= 1;
-                        curr_pos.0.x = pos.x - 1;
-                        Self::move_boss(world, index, boss_pos, Direction::West);
-                    }
+        let boss_pos = world.bosses[index].position;
+        if let Some(laser) = world.encounter().column_laser {
+            // the laser fires where it was shown, and only starts sliding the turn after
+            if laser.2 > 0 {
+                world.encounter_mut().column_laser = Some((laser.0, laser.1, laser.2 - 1));
+                return;
+            }
+            match Self::column_laser_step(laser.0, laser.1, boss_pos, offset) {
+                Some((laser_pos, boss_pos)) => {
+                    world.encounter_mut().column_laser = Some((laser_pos, laser.1, 0));
+                    Self::move_boss(world, index, boss_pos, laser.1);
                 }
+                None => world.encounter_mut().column_laser = None,
             }
-        } else if *can_attack && time_vulnerable == 0 {
+        } else if world.bosses[index].boss_can_attack && time_vulnerable == 0 {
             let boss_delta = (
                 boss_pos.x as i32 - world.player.pos.x as i32,
                 boss_pos.y as i32 - world.player.pos.y as i32,
@@ -662,41 +525,72 @@ impl Boss {
                     new_position.0.x = boss_pos.x + offset;
                 }
             }
-            *can_attack = false;
-            world.boss_column_laser = Some(new_position);
+            world.bosses[index].boss_can_attack = false;
+            let telegraph = world.difficulty.telegraph(telegraph);
+            world.encounter_mut().column_laser = Some((new_position.0, new_position.1, telegraph));
         } else {
             Self::return_boss_to_center(world, index);
         }
     }
 
     pub fn check_laser_column_damage(world: &mut World, index: usize) {
-        if let Some(laser) = world.boss_column_laser {
-            let boss_pos = world.bosses[index].position;
-            let mut player_in_laser: bool = false;
-            let mut laser_width: usize = 2;
-            let player_pos = world.player.pos;
-            if world.bosses[index].is_major {
-                laser_width = 3;
+        if let Some(laser) = world.encounter().column_laser {
+            if laser.2 > 0 {
+                return;
             }
-            if laser.0.x > boss_pos.x {
-                player_in_laser = (player_pos.y >= laser.0.y - laser_width && player_pos.y <= laser.0.y + laser_width
-                && player_pos.x >= laser.0.x);
-            } else if laser.0.x < boss_pos.x {
-                player_in_laser = (player_pos.y >= laser.0.y - laser_width && player_pos.y <= laser.0.y + laser_width
-                && player_pos.x <= laser.0.x);
-            } else if laser.0.y > boss_pos.y {
-                player_in_laser = (player_pos.x >= laser.0.x - laser_width && player_pos.x <= laser.0.x + laser_width
-                && player_pos.y >= laser.0.y);
+            let boss_pos = world.bosses[index].position;
+            let width = Self::column_laser_width(&world.bosses[index]);
+            let in_laser = |pos: Position| Self::in_column_laser(laser.0, boss_pos, width, pos);
 
-            } else if laser.0.y < boss_pos.y {
-                player_in_laser = (player_pos.x >= laser.0.x - laser_width && player_pos.x <= laser.0.x + laser_width
-                && player_pos.y <= laser.0.y);
+            if in_laser(world.player.pos) {
+                world.player.damage(world.difficulty.damage(world.balance.bosses.column_laser_damage), Actor::Hazard("a column laser"));
             }
+            Structure::damage_where(world, world.world_position, world.balance.bosses.attack_structure_damage, in_laser);
+        }
+    }
 
-            if player_in_laser {
-                world.player.damage(COLUMN_LASER_DAMAGE);
+    // Warns about every laser, asteroid, stun well, column laser and blackout in the room that
+    // hasn't gone off yet
+    pub fn hazard_telegraphs(world: &World, index: usize, telegraphs: &mut Telegraphs) {
+        let encounter = world.encounter();
+        for laser in &encounter.lasers {
+            telegraphs.warn_where(laser.1, |pos| Self::in_laser(laser.0, pos));
+        }
+        for asteroid in &encounter.asteroids {
+            telegraphs.warn_where(asteroid.1, |pos| Self::in_asteroid(asteroid.0, pos));
+        }
+        for well in &encounter.stun_wells {
+            telegraphs.warn_where(well.1, |pos| Self::in_stun_well(well.0, well.2, pos));
+        }
+        if let Some(laser) = encounter.column_laser {
+            let boss = &world.bosses[index];
+            let width = Self::column_laser_width(boss);
+            if laser.2 > 0 {
+                telegraphs.warn_where(laser.2, |pos| {
+                    Self::in_column_laser(laser.0, boss.position, width, pos)
+                });
+            } else if let Some((next_laser, next_boss)) =
+                Self::column_laser_step(laser.0, laser.1, boss.position, boss.offset)
+            {
+                // the tiles it slides onto next turn
+                telegraphs.warn_where(1, |pos| {
+                    Self::in_column_laser(next_laser, next_boss, width, pos)
+                        && !Self::in_column_laser(laser.0, boss.position, width, pos)
+                });
             }
         }
+        // the blackout hits everything but the safe spot, so its countdown goes in the middle of
+        // the spot instead of on every tile of the room
+        if let Some(spot) = encounter.safe_spot {
+            telegraphs.warn(spot.0, spot.2);
+        }
+    }
+
+    // Draws how many turns are left on every tile the boss is about to hurt
+    pub fn draw_telegraphs(world: &World, canvas: &mut graphics::Canvas, index: usize) {
+        let mut telegraphs = Telegraphs::new();
+        world.bosses[index].kind.behavior().telegraphs(world, index, &mut telegraphs);
+        telegraphs.draw(canvas);
     }
     
     pub fn return_boss_to_center(world: &mut World, index: usize) {
@@ -719,55 +613,31 @@ impl Boss {
             } else {
                 if !world.bosses[index].boss_can_attack {
                     world.bosses[index].boss_can_attack = true;
-                    world.bosses[index].vulnerable_time = VULNERABLE_TIME_BASE;
+                    world.bosses[index].vulnerable_time = world.balance.bosses.vulnerable_time;
                 }
-                world.bosses[index].vulnerable_time -= 1;
+                world.bosses[index].vulnerable_time =
+                    world.bosses[index].vulnerable_time.saturating_sub(1);
             }
         }
     }
 
-    pub fn generate_stun_well(world: &mut World, index: usize, rng: &mut ChaCha8Rng) {
-        if world.bosses[index].stun_well_cooldown == 0 {
-            world.bosses[index].stun_well_cooldown = STUN_WELL_COOLDOWN;
-            let mut well_size = 2;
-            if Self::coin_flip(rng) {
-                well_size = 3;
-            }
-            let mut pos = world.bosses[index].position;
-            while Self::pos_inside_boss(world, Position::new(pos.x + well_size, pos.y), world.world_position) ||
-                Self::pos_inside_boss(world, Position::new(pos.x - well_size, pos.y), world.world_position) ||
-                Self::pos_inside_boss(world, Position::new(pos.x, pos.y + well_size), world.world_position) ||
-                Self::pos_inside_boss(world, Position::new(pos.x, pos.y - well_size), world.world_position) {
-                let x = random::rand_range(rng, 5, WORLD_SIZE.0) as usize;
-                let y = random::rand_range(rng, 5, WORLD_SIZE.1) as usize;
-                pos = Position::new(x,y);
-            }
-            world.stun_wells.push((
-                pos,
-                tile::STUN_WELL_INDICATOR,
-                well_size,
-                STUN_WELL_LINGER_VALUE,
-                false,
-            ));
-        } else {
-            for index in (0..world.stun_wells.len()).rev() {
-                let well = world.stun_wells[index];
-                if well.1 == tile::STUN_WELL_INDICATOR {
-                    world.stun_wells[index].1 = tile::STUN_WELL_REAL;
-                } else {
-                    if well.3 == 0 {
-                        world.stun_wells.remove(index);
-                    } else {
-                        world.stun_wells[index].3 -= 1;
-                    }
-                }
-            }
-            world.bosses[index].stun_well_cooldown -= 1;
+    pub fn generate_stun_well(world: &mut World, index: usize, telegraph: usize, linger: usize, rng: &mut ChaCha8Rng) {
+        let mut well_size = 2;
+        if Self::coin_flip(rng) {
+            well_size = 3;
+        }
+        let mut pos = world.bosses[index].position;
+        while Self::area_touches_boss(world, pos, well_size) {
+            let x = random::rand_range(rng, 5, WORLD_SIZE.0) as usize;
+            let y = random::rand_range(rng, 5, WORLD_SIZE.1) as usize;
+            pos = Position::new(x,y);
         }
+        let telegraph = world.difficulty.telegraph(telegraph);
+        world.encounter_mut().stun_wells.push((pos, telegraph, well_size, linger, false));
     }
 
     pub fn draw_stun_wells(world: &mut World, canvas: &mut Canvas) {
-        for well in &world.stun_wells {
+        for well in &world.encounter().stun_wells {
             let len = well.2 as i32;
             let pos = well.0;
             for i in -len..=len {
@@ -781,41 +651,47 @@ impl Boss {
                                 TILE_SIZE.0 as i32,
                                 TILE_SIZE.1 as i32,
                             ))
-                            .color(well.1),
+                            .color(if well.1 == 0 {
+                                tile::STUN_WELL_REAL
+                            } else {
+                                tile::STUN_WELL_INDICATOR
+                            }),
                     )
                 }
             }
         }
     }
 
+    fn in_stun_well(well_pos: Position, size: usize, pos: Position) -> bool {
+        (pos.x as i32 - well_pos.x as i32).abs() <= size as i32
+            && (pos.y as i32 - well_pos.y as i32).abs() <= size as i32
+    }
+
     pub fn check_stun_well_stun(world: &mut World) {
         let pos = world.player.pos;
-        for index in 0..world.stun_wells.len() {
-            let size = world.stun_wells[index].2;
-            let well_pos = world.stun_wells[index].0;
-            if pos.x >= (well_pos.x - size) && pos.x <= (well_pos.x + size) &&
-            pos.y >= (well_pos.y - size) && pos.y <= (well_pos.y + size) {
-                if !world.stun_wells[index].4 {
-                    world.player.stun_timer = STUN_WELL_STUN_TIME;
-                    world.stun_wells[index].4 = true;
+        for index in 0..world.encounter().stun_wells.len() {
+            let size = world.encounter().stun_wells[index].2;
+            let well_pos = world.encounter().stun_wells[index].0;
+            if world.encounter().stun_wells[index].1 == 0 && Self::in_stun_well(well_pos, size, pos) {
+                if !world.encounter().stun_wells[index].4 {
+                    world.player.stun_timer = world.balance.bosses.stun_well_stun_time;
+                    world.events.push(GameEvent::Stunned {
+                        source: Actor::Hazard("a stun well"),
+                        turns: world.balance.bosses.stun_well_stun_time,
+                    });
+                    world.encounter_mut().stun_wells[index].4 = true;
                 }
             } else {
-                world.stun_wells[index].4 = false;
+                world.encounter_mut().stun_wells[index].4 = false;
             }
         }
     }
     
     pub fn draw_safe_spot(world: &mut World, canvas: &mut Canvas) {
-        if let Some(spot) = world.boss_safe_spot {
+        if let Some(spot) = world.encounter().safe_spot {
             let size = spot.1 as i32;
             let pos = spot.0;
-            let text_spot = Vec2::new((pos.x as f32 + 0.25) * TILE_SIZE.0 as f32,  (pos.y as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32);
-            // (pos.y as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32);
-            // bevy::math::Vec2::new((pos.x as f32 + 0.25) * TILE_SIZE.0 as f32, 
-            // (pos.y as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32);
-            let duration_left = format!("{}", spot.2);
-
-            if world.in_blackout {
+            if world.encounter().in_blackout {
                 for i in 0..WORLD_SIZE.0 {
                     for j in 0..WORLD_SIZE.1 {
                         canvas.draw(
@@ -848,327 +724,37 @@ impl Boss {
                     )
                 }
             }
-            if !world.in_blackout {
-                canvas.draw(
-                    &graphics::Text::new(duration_left),
-                    graphics::DrawParam::from(text_spot).color(graphics::Color::WHITE),
-                );
-            }
         }
     }
     
-    pub fn generate_safe_spot(world: &mut World, index: usize, rng: &mut ChaCha8Rng) {
-        if let Some(spot) = &mut world.boss_safe_spot {
-            if spot.2 == 0 {
-                world.boss_safe_spot = None;
-                world.bosses[index].safe_spot_cooldown = SAFE_SPOT_ATTACK_COOLDOWN;
-                world.in_blackout = false;
-            } else {
-                spot.2 -= 1;
-                if spot.2 == 0 {
-                    world.in_blackout = true;
-                }
-            }
-        } else if world.bosses[index].safe_spot_cooldown == 0 {
-            let mut size: usize = 1; //3x3
-            if Self::coin_flip(rng) {
-                size = 2; //5x5
-            }
-            let player_x = world.player.pos.x as i16;
-            let player_y = world.player.pos.y as i16;
-
-            let mut pos = world.bosses[index].position;
-            while Self::pos_inside_boss(world, Position::new(pos.x + size, pos.y), world.world_position) ||
-                Self::pos_inside_boss(world, Position::new(pos.x - size, pos.y), world.world_position) ||
-                Self::pos_inside_boss(world, Position::new(pos.x, pos.y + size), world.world_position) ||
-                Self::pos_inside_boss(world, Position::new(pos.x, pos.y - size), world.world_position){
-                let x = random::rand_range(rng, max(0, player_x - 5), player_x + 5) as usize;
-                let y = random::rand_range(rng, max(0, player_y - 5), player_y + 5) as usize;
-                pos = Position::new(x,y);
-            }
-        
-            world.boss_safe_spot = Some((pos, size, SAFE_SPOT_TIME));
-        } else {
-            world.bosses[index].safe_spot_cooldown -= 1;
-        }
-
-
-    }
-
     pub fn check_survive_black_out(world: &mut World) {
-        if world.in_blackout {
-            if let Some(spot) = world.boss_safe_spot {
+        if world.encounter().in_blackout {
+            if let Some(spot) = world.encounter().safe_spot {
                 let spot_pos = spot.0;
                 let pos = world.player.pos;
                 let size = spot.1;
                 if !(pos.x >= (spot_pos.x - size) && pos.x <= (spot_pos.x + size) &&
                 pos.y >= (spot_pos.y - size) && pos.y <= (spot_pos.y + size)) {
-                    world.player.damage(MAX_PLAYER_HEALTH);
-                }
-            }
-        }
-    }
-    
-    pub fn generate_vulnerable_spot(world: &mut World, index: usize, rng: &mut ChaCha8Rng) {
-        if let Some(spot) = world.boss_vulnerable_spot {
-            return;
-        } else {
-            if world.bosses[index].vulnerable_time == 0 {
-                let offset = world.bosses[index].offset - 1;
-                let on_vert = Self::coin_flip(rng); // On horizontal?
-                let pos_side = Self::coin_flip(rng); //On top or left?
-                let spot = random::rand_range(rng, 0, offset as i16 * 2 - 1) as usize;
-                let mut vulnerable_spot = Position::new(0,0);
-                if on_vert {
-                    vulnerable_spot.y = world.bosses[index].position.y - offset + spot;
-                    if pos_side {
-                        vulnerable_spot.x = world.bosses[index].position.x - offset;
-                    } else {
-                        vulnerable_spot.x = world.bosses[index].position.x + offset;
-
-                    }
-                } else {
-                    vulnerable_spot.x = world.bosses[index].position.x - offset + spot;
-                    if pos_side {
-                        vulnerable_spot.y = world.bosses[index].position.y - offset;
-                    } else {
-                        vulnerable_spot.y = world.bosses[index].position.y + offset;
-                    }
+                    world.player.damage(world.balance.player.max_health, Actor::Hazard("the blackout"));
                 }
-                world.boss_vulnerable_spot = Some(vulnerable_spot);
-
-                if world.bosses[index].shield_health == 0 {
-                    world.bosses[index].vulnerable_time = VULNERABLE_TIME_BASE;
-                    world.bosses[index].shield_health = SHIELD_HITS_NEEDED;
-                    world.boss_vulnerable_spot = None
-                } 
-                
-            } else {
-                world.bosses[index].vulnerable_time -= 1;
             }
         }
     }
     
-    pub fn chase_player(world: &mut World, index: usize) {
-        let boss_pos = world.bosses[index].position;
-        let boss_delta = (
-            boss_pos.x as i32 - world.player.pos.x as i32,
-            boss_pos.y as i32 - world.player.pos.y as i32,
-        );
-        if (world.bosses[index].rush_info.0 || (boss_delta.0.abs() <= 3 || boss_delta.1.abs() <= 3))
-            && world.bosses[index].chase_rush_cooldown == 0
-        {
-            Self::rush_player(world, index, boss_delta.0, boss_delta.1);
-        } else if world.bosses[index].boss_can_attack && world.bosses[index].speed_delay == 0 {
-            let mut boss_pos = world.bosses[index].position;
-            if boss_delta.0.abs() > boss_delta.1.abs() {
-                if boss_delta.0 < 0 {
-                    boss_pos.x += 1;
-                    Self::move_boss(world, index, boss_pos, Direction::East);
-                } else {
-                    boss_pos.x -= 1;
-                    Self::move_boss(world, index, boss_pos, Direction::West);
-                }
-            } else {
-                if boss_delta.1 < 0 {
-                    boss_pos.y += 1;
-                    Self::move_boss(world, index, boss_pos, Direction::South);
-                } else {
-                    boss_pos.y -= 1;
-                    Self::move_boss(world, index, boss_pos, Direction::North);
-                }
-            }
-            world.bosses[index].speed_delay = BOSS_3_MOVE_DELAY;
-            if (world.bosses[index].chase_rush_cooldown != 0) {
-                world.bosses[index].chase_rush_cooldown -= 1;
-            }
-        } else {
-            if world.bosses[index].vulnerable_time != 0 {
-                world.bosses[index].vulnerable_time -= 1;
-                if (world.bosses[index].vulnerable_time == 0) {
-                    world.bosses[index].boss_can_attack = true;
-                }
-            } else {
-                world.bosses[index].speed_delay -= 1;
-                if (world.bosses[index].chase_rush_cooldown != 0) {
-                    world.bosses[index].chase_rush_cooldown -= 1;
-                }
-            }
-        }
-    }
-
-    pub fn rush_player(world: &mut World, index: usize, x_delta: i32, y_delta: i32) {
-        if !world.bosses[index].rush_info.0 {
-            if x_delta.abs() > y_delta.abs() {
-                if x_delta < 0 {
-                    world.bosses[index].rush_info.1 = Some(Direction::East);
-                    world.bosses[index].rush_info.3 =
-                        WORLD_SIZE.0 as usize - 1 - world.bosses[index].position.x;
-                } else {
-                    world.bosses[index].rush_info.1 = Some(Direction::West);
-                    world.bosses[index].rush_info.3 = world.bosses[index].position.x;
-                }
-            } else {
-                if y_delta < 0 {
-                    world.bosses[index].rush_info.1 = Some(Direction::South);
-                    world.bosses[index].rush_info.3 =
-                        WORLD_SIZE.1 as usize - 1 - world.bosses[index].position.y;
-                } else {
-                    world.bosses[index].rush_info.1 = Some(Direction::North);
-                    world.bosses[index].rush_info.3 = world.bosses[index].position.y;
-                }
-            }
-            world.bosses[index].rush_info.0 = true;
-        } else {
-            let stage = world.bosses[index].rush_info.2;
-            let offset = world.bosses[index].offset;
-            let mut length: usize;
-            match stage {
-                tile::BOSS_LASER_STAGE_1 => {
-                    world.bosses[index].rush_info.2 = tile::BOSS_LASER_STAGE_2;
-                }
-
-                tile::BOSS_LASER_STAGE_2 => {
-                    world.bosses[index].rush_info.2 = tile::BOSS_LASER_REAL;
-                }
-
-                _ => {
-                    if let Some(direction) = world.bosses[index].rush_info.1 {
-                        let player_pos = world.player.pos;
-                        let boss_pos = world.bosses[index].position;
-                        let len = world.bosses[index].offset - 1;
-                        match direction {
-                            Direction::North => {
-                                if player_pos.x <= boss_pos.x + len && player_pos.x >= boss_pos.x - len &&
-                                player_pos.y <= boss_pos.y 
-                                {
-                                    world.player.damage(MAX_PLAYER_HEALTH);
-                                } else {
-                                    world.bosses[index].position.y = 0 + offset;
-                                }
-                            }
-
-                            Direction::South => {
-                                if player_pos.x <= boss_pos.x + len && player_pos.x >= boss_pos.x - len &&
-                                player_pos.y >= boss_pos.y 
-                                {
-                                    world.player.damage(MAX_PLAYER_HEALTH);
-                                } else {
-                                    world.bosses[index].position.y = WORLD_SIZE.1 as usize - offset - 1;
-                                }
-                            }
-
-                            Direction::West => {
-                                if player_pos.y <= boss_pos.y + len && player_pos.y >= boss_pos.y - len &&
-                                player_pos.x <= boss_pos.x
-                                {
-                                    world.player.damage(MAX_PLAYER_HEALTH);
-                                } else {
-                                    world.bosses[index].position.x = 0 + offset;
-                                }
-                            }
-
-                            Direction::East => {
-                                if player_pos.y <= boss_pos.y + len && player_pos.y >= boss_pos.y - len &&
-                                player_pos.x >= boss_pos.x
-                                {
-                                    world.player.damage(MAX_PLAYER_HEALTH);
-                                } else {
-                                    world.bosses[index].position.x = WORLD_SIZE.0 as usize - 1 - offset;
-                                }
-                            }
-                        }
-                    }
-                    world.bosses[index].chase_rush_cooldown = BOSS_3_RUSH_COOLDOWN;
-                    world.bosses[index].rush_info.2 = tile::BOSS_LASER_STAGE_1;
-                    world.bosses[index].vulnerable_time = VULNERABLE_TIME_BASE;
-                    world.bosses[index].boss_can_attack = false;
-                }
-            }
-        }
-    }
-
-    pub fn draw_rush(world: &mut World, index: usize, distance: usize, canvas: &mut Canvas) {
-        if let Some(direction) = world.bosses[index].rush_info.1 {
-            let mut rush_dist = distance;
-            let after_rush = !world.bosses[index].boss_can_attack;
-            let mut width: i32 = -3;
-            let mut color = world.bosses[index].rush_info.2;
-            if world.bosses[index].offset == 5 {
-                width = -3;
-            }
-            if after_rush {
-                world.bosses[index].rush_info.0 = false;
-                // rush_dist = WORLD_SIZE.0 as usize - world.bosses[index].offset;
-                color = tile::FIRE_TERTIARY;
-            }
-
-            for i in 0..rush_dist as i32 {
-                for j in width..=(-width) {
-                    let mut x = world.bosses[index].position.x as i32;
-                    let mut y = world.bosses[index].position.y as i32;
-                    match direction {
-                        Direction::North => {
-                            x = x + j;
-                            if after_rush {
-                                y = y + i;
-                            } else {
-                                y = y - i;
-                            }
-                        }
-
-                        Direction::South => {
-                            x = x + j;
-                            if after_rush {
-                                y = y - i;
-                            } else {
-                                y = y + i;
-                            }
-                        }
-
-                        Direction::West => {
-                            if after_rush {
-                                x = x + i;
-                            } else {
-                                x = x - i;
-                            }
-                            y = y + j;
-                        }
-
-                        Direction::East => {
-                            if after_rush {
-                                x = x - i;
-                            } else {
-                                x = x + i;
-                            }
-                            y = y + j;
-                        }
-                    }
-                    canvas.draw(
-                        &graphics::Quad,
-                        graphics::DrawParam::new()
-                            .dest_rect(graphics::Rect::new_i32(
-                                x * TILE_SIZE.0 as i32,
-                                (y + UNIVERSAL_OFFSET as i32) * TILE_SIZE.1 as i32,
-                                TILE_SIZE.0 as i32,
-                                TILE_SIZE.1 as i32,
-                            ))
-                            .color(color),
-                    )
-                }
-            }
-        }
-    }
-
     pub fn move_boss(world: &mut World, index: usize, new_pos: Position, direction: Direction) {
         world.bosses[index].position = new_pos;
+        let width = world.bosses[index].offset - 1;
+        Structure::damage_where(world, world.world_position, world.balance.bosses.crush_structure_damage, |pos| {
+            pos.x + width >= new_pos.x && pos.x <= new_pos.x + width &&
+            pos.y + width >= new_pos.y && pos.y <= new_pos.y + width
+        });
         let world_map = &world.terrain_map[world.world_position.y][world.world_position.x];
         if  Self::pos_inside_boss(world, world.player.pos, world.world_position) {
-            world.player.damage(BOSS_COLLISION_DAMAGE);
+            world.player.damage(world.difficulty.damage(world.balance.bosses.collision_damage), Actor::Boss(world.bosses[index].name()));
             match direction {
                 Direction::North => {
                     if world_map.contains_key(&Position::new(world.player.pos.x, world.player.pos.y - 1)) {
-                        world.player.damage(MAX_PLAYER_HEALTH);
+                        world.player.damage(world.balance.player.max_health, Actor::Boss(world.bosses[index].name()));
                     } else {
                         World::update_position(world, world.player.pos, 
                             (Position::new(world.player.pos.x, world.player.pos.y - 1), 
@@ -1178,7 +764,7 @@ impl Boss {
                 },
                 Direction::South => {
                     if world_map.contains_key(&Position::new(world.player.pos.x, world.player.pos.y + 1)) {
-                        world.player.damage(MAX_PLAYER_HEALTH);
+                        world.player.damage(world.balance.player.max_health, Actor::Boss(world.bosses[index].name()));
                     } else {
                         World::update_position(world, world.player.pos, 
                             (Position::new(world.player.pos.x, world.player.pos.y + 1), 
@@ -1188,7 +774,7 @@ impl Boss {
                 },
                 Direction::East => {
                     if world_map.contains_key(&Position::new(world.player.pos.x + 1, world.player.pos.y)) {
-                        world.player.damage(MAX_PLAYER_HEALTH);
+                        world.player.damage(world.balance.player.max_health, Actor::Boss(world.bosses[index].name()));
                     } else {
                         World::update_position(world, world.player.pos, 
                             (Position::new(world.player.pos.x + 1, world.player.pos.y), 
@@ -1198,7 +784,7 @@ impl Boss {
                 },
                 Direction::West => {
                     if world_map.contains_key(&Position::new(world.player.pos.x - 1, world.player.pos.y )) {
-                        world.player.damage(MAX_PLAYER_HEALTH);
+                        world.player.damage(world.balance.player.max_health, Actor::Boss(world.bosses[index].name()));
                     } else {
                         World::update_position(world, world.player.pos, 
                             (Position::new(world.player.pos.x - 1, world.player.pos.y), 
@@ -1221,47 +807,80 @@ impl Boss {
             }
         }
         let hit: bool = Self::pos_inside_boss(world, hit_pos, world_pos);
-        let can_hit: bool = world.bosses[index].vulnerable_time != 0;
-        
-        if let Some(spot) = world.boss_vulnerable_spot {
-            if hit_pos == spot {
-                world.bosses[index].shield_health -= 1;
-                world.boss_vulnerable_spot = None;
-            }
+        let can_hit: bool = world.bosses[index].kind.behavior().is_vulnerable(&world.bosses[index]);
+        if hit {
+            Self::with_behavior(world, index, |boss, world| boss.on_damaged(world, index, hit_pos));
         }
 
         return (hit, can_hit);
     }
     
-    pub fn damage(world: &mut World, damage: usize, world_pos: Position) {
+    pub fn damage(world: &mut World, damage: usize, world_pos: Position, source: Actor) {
         for index in 0..world.bosses.len() {
             let boss = &mut world.bosses[index];
             if boss.world_position == world_pos {
+                let was_alive = boss.health > 0;
                 boss.health = max(0, boss.health as i32 - damage as i32) as usize;
+                let center = DamageNumber::center(&[boss.position]);
+                world
+                    .damage_numbers
+                    .push(DamageNumber::new(center, world_pos, damage, true));
+                let target = Actor::Boss(boss.name());
+                world.events.push(GameEvent::Damaged {
+                    source,
+                    target,
+                    amount: damage,
+                });
+                if was_alive && boss.health == 0 {
+                    world.events.push(GameEvent::Killed { source, target });
+                }
             }
         }
     }
 
+    // What the log calls this boss
+    pub fn name(&self) -> &'static str {
+        self.kind.behavior().name()
+    }
+
+    // What the boss in the player's room is up to, only used to tell the player when it changes
+    fn phase(world: &World, index: usize) -> BossPhase {
+        if world.encounter().in_blackout {
+            BossPhase::Blackout
+        } else if world.bosses[index].vulnerable_time > 0 {
+            BossPhase::Vulnerable
+        } else {
+            BossPhase::Attacking
+        }
+    }
+
+    pub fn max_health(&self, difficulty: Difficulty, balance: &BossBalance) -> usize {
+        let health = if self.is_major {
+            balance.major_health
+        } else {
+            balance.health
+        };
+        difficulty.boss_health(health)
+    }
+
     pub fn kill(world: &mut World, index: usize) {
-        world.bosses.remove(index);
+        let world_pos = world.bosses.remove(index).world_position;
         // when kill is implemented this should reopen doors
         world.boss_defeated[world.world_position.y][world.world_position.x] = true;
-        world.boss_safe_spot = None;
-        world.boss_vulnerable_spot = None;
-        world.boss_column_laser = None;
-        world.boss_lasers.clear();
-        world.stun_wells.clear();
-        world.boss_asteroids.clear();
-        World::toggle_doors(
+        // whatever the fight left in the room goes with it
+        world.encounters[world_pos.y][world_pos.x] = Encounter::default();
+        if World::toggle_doors(
             &mut world.terrain_map,
             world.world_position,
             world.player.pos,
             world.boss_defeated,
-        );
+        ) {
+            world.terrain_changed();
+        }
     }
 }
 
-#[derive(Eq, Hash, PartialEq, Copy, Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Eq, Hash, PartialEq, PartialOrd, Ord, Copy, Clone, Debug, serde::Deserialize, serde::Serialize)]
 
 pub struct Position {
     pub x: usize,
@@ -1272,3 +891,35 @@ impl Position {
         Position { x, y }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{balance::Balance, boss_script::BossScripts, worldgen::Preset};
+    use rand::SeedableRng;
+
+    #[test]
+    fn bosses_from_old_saves_get_their_kind_back() {
+        // a boss the way saves wrote them before bosses kept their own state
+        let old = "(position: (x: 25, y: 25), color: (1.0, 0.0, 0.0, 1.0), \
+            world_position: (x: 1, y: 5), health: 100, is_major: false, offset: 4, \
+            boss_can_attack: true, vulnerable_time: 0, laser_amount: 3, first_enter: true)";
+        let boss: Boss = ron::from_str(old).unwrap();
+        assert!(boss.kind.is_missing());
+
+        let mut world = World::new(
+            &mut ChaCha8Rng::seed_from_u64(0),
+            Difficulty::default(),
+            &Balance::default(),
+            &BossScripts::default(),
+            &Preset { steps: Vec::new() },
+        );
+        world.bosses.push(boss);
+        Boss::restore_kinds(&mut world);
+        let script = world.boss_scripts.get("sliding_laser").unwrap();
+        assert_eq!(
+            world.bosses[0].kind,
+            BossKind::Scripted(ScriptedBoss::new("sliding_laser", script))
+        );
+    }
+}
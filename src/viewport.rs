@@ -0,0 +1,104 @@
+use crate::SCREEN_SIZE;
+use ggez::{
+    glam::Vec2,
+    graphics::{Canvas, Rect},
+    Context,
+};
+
+// Fits the game, which is always laid out as if the window were SCREEN_SIZE, into whatever size
+// the window actually is. The game is scaled up by a whole number so the pixels stay square, and
+// whatever is left over around it is letterboxed
+pub struct Viewport {
+    // size of the window in pixels
+    window: (f32, f32),
+    // None picks the biggest scale that fits the window
+    scale_setting: Option<u32>,
+}
+
+impl Viewport {
+    pub fn new(ctx: &Context) -> Self {
+        Viewport {
+            window: ctx.gfx.drawable_size(),
+            scale_setting: None,
+        }
+    }
+
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.window = (width, height);
+    }
+
+    // Biggest whole number scale the window can fit, 0 if the window is smaller than the game
+    fn fit(&self) -> u32 {
+        (self.window.0 / SCREEN_SIZE.0).min(self.window.1 / SCREEN_SIZE.1) as u32
+    }
+
+    pub fn scale(&self) -> f32 {
+        let fit = self.fit();
+        if fit == 0 {
+            // shrink the game rather than cut part of it off
+            return (self.window.0 / SCREEN_SIZE.0).min(self.window.1 / SCREEN_SIZE.1);
+        }
+        self.scale_setting.map_or(fit, |scale| scale.min(fit)) as f32
+    }
+
+    pub fn increase_scale(&mut self) {
+        self.scale_setting = Some((self.scale() as u32 + 1).min(self.fit().max(1)));
+    }
+
+    pub fn decrease_scale(&mut self) {
+        self.scale_setting = Some((self.scale() as u32).saturating_sub(1).max(1));
+    }
+
+    // Goes back to picking the biggest scale that fits
+    pub fn reset_scale(&mut self) {
+        self.scale_setting = None;
+    }
+
+    // Top left corner of the game inside the window, in window pixels
+    fn offset(&self) -> Vec2 {
+        let scale = self.scale();
+        Vec2::new(
+            ((self.window.0 - SCREEN_SIZE.0 * scale) / 2.0).floor(),
+            ((self.window.1 - SCREEN_SIZE.1 * scale) / 2.0).floor(),
+        )
+    }
+
+    // Screen coordinates to give the canvas so that the point `origin` of the game's own
+    // coordinates ends up in the top left corner of the game inside the window
+    pub fn screen_coordinates(&self, origin: Vec2) -> Rect {
+        let scale = self.scale();
+        let offset = self.offset();
+        Rect::new(
+            origin.x - offset.x / scale,
+            origin.y - offset.y / scale,
+            self.window.0 / scale,
+            self.window.1 / scale,
+        )
+    }
+
+    pub fn apply(&self, canvas: &mut Canvas) {
+        canvas.set_screen_coordinates(self.screen_coordinates(Vec2::ZERO));
+    }
+
+    // Keeps whatever is drawn next inside the game. The continuous camera puts parts of the board
+    // past the edges of the game, and they would show up in the letterboxing otherwise
+    pub fn clip(&self, canvas: &mut Canvas) {
+        let scale = self.scale();
+        let offset = self.offset();
+        let game = Rect::new(offset.x, offset.y, SCREEN_SIZE.0 * scale, SCREEN_SIZE.1 * scale);
+        // only fails for a window too small to show any of the game
+        if canvas.set_scissor_rect(game).is_err() {
+            canvas.set_default_scissor_rect();
+        }
+    }
+
+    // Turns a point in the window into the game's own coordinates, None if it is in the
+    // letterboxing around the game
+    pub fn to_screen(&self, x: f32, y: f32) -> Option<Vec2> {
+        let point = (Vec2::new(x, y) - self.offset()) / self.scale();
+        if point.x < 0.0 || point.y < 0.0 || point.x >= SCREEN_SIZE.0 || point.y >= SCREEN_SIZE.1 {
+            return None;
+        }
+        Some(point)
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{tile, utils::Position, WORLD_SIZE};
+use std::collections::{HashMap, HashSet};
+
+// How many tiles away the player can see
+pub const VISION_RADIUS: i32 = 12;
+// During the blackout boss's blackout the player can barely see around themselves
+pub const BLACKOUT_VISION_RADIUS: i32 = 2;
+
+// Multipliers that transform the first octant into each of the eight octants around the viewer.
+// Each row is (xx, xy, yx, yy)
+const OCTANTS: [[i32; 4]; 8] = [
+    [1, 0, 0, 1],
+    [0, 1, 1, 0],
+    [0, -1, 1, 0],
+    [-1, 0, 0, 1],
+    [-1, 0, 0, -1],
+    [0, -1, -1, 0],
+    [0, 1, -1, 0],
+    [1, 0, 0, -1],
+];
+
+// Mountains, walls and trees are the only things that can't be seen through
+pub fn blocks_vision(color: &[f32; 4]) -> bool {
+    tile::MOUNTAIN.contains(color)
+        || *color == tile::WALL
+        || *color == tile::TREE
+        || *color == tile::LOCKED_DOOR
+        || *color == tile::BOSS_DOOR
+}
+
+// Returns every tile of a world that can be seen from the origin, using recursive shadowcasting.
+// The terrain map passed in is the terrain of the world the origin is in
+pub fn field_of_view(
+    origin: Position,
+    radius: i32,
+    terrain: &HashMap<Position, [f32; 4]>,
+) -> HashSet<Position> {
+    let mut caster = Caster {
+        visible: HashSet::new(),
+        terrain,
+        origin,
+        radius,
+    };
+    caster.visible.insert(origin);
+    for octant in OCTANTS {
+        caster.cast_light(1, 1.0, 0.0, octant);
+    }
+    caster.visible
+}
+
+// Everything that stays the same while the light is cast through each octant
+struct Caster<'a> {
+    visible: HashSet<Position>,
+    terrain: &'a HashMap<Position, [f32; 4]>,
+    origin: Position,
+    radius: i32,
+}
+
+impl Caster<'_> {
+    // Scans one octant row by row, moving outwards from the origin. Whenever a blocking tile is
+    // found the part of the octant behind it is scanned separately with narrower slopes, which
+    // leaves the tiles hidden behind the blocker in shadow
+    fn cast_light(&mut self, row: i32, mut start: f32, end: f32, octant: [i32; 4]) {
+        if start < end {
+            return;
+        }
+        let [xx, xy, yx, yy] = octant;
+        let mut new_start = 0.0;
+        for distance in row..=self.radius {
+            let dy = -distance;
+            let mut dx = -distance - 1;
+            let mut blocked = false;
+            while dx <= 0 {
+                dx += 1;
+                let x = self.origin.x as i32 + dx * xx + dy * xy;
+                let y = self.origin.y as i32 + dx * yx + dy * yy;
+                // slopes of the left and right edges of the tile being looked at
+                let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+                let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+                if start < right_slope {
+                    continue;
+                } else if end > left_slope {
+                    break;
+                }
+
+                let in_world = x >= 0 && y >= 0 && x < WORLD_SIZE.0 as i32 && y < WORLD_SIZE.1 as i32;
+                let pos = Position::new(x.max(0) as usize, y.max(0) as usize);
+                if in_world && dx * dx + dy * dy <= self.radius * self.radius {
+                    self.visible.insert(pos);
+                }
+
+                let opaque = !in_world || self.terrain.get(&pos).is_some_and(blocks_vision);
+                if blocked {
+                    if opaque {
+                        new_start = right_slope;
+                        continue;
+                    } else {
+                        blocked = false;
+                        start = new_start;
+                    }
+                } else if opaque && distance < self.radius {
+                    blocked = true;
+                    self.cast_light(distance + 1, start, left_slope, octant);
+                    new_start = right_slope;
+                }
+            }
+            if blocked {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CENTER: Position = Position { x: 25, y: 25 };
+
+    fn in_range(pos: Position, origin: Position, radius: i32) -> bool {
+        let (dx, dy) = (
+            pos.x as i32 - origin.x as i32,
+            pos.y as i32 - origin.y as i32,
+        );
+        dx * dx + dy * dy <= radius * radius
+    }
+
+    #[test]
+    fn open_ground_is_seen_out_to_the_radius() {
+        for radius in [VISION_RADIUS, BLACKOUT_VISION_RADIUS] {
+            let visible = field_of_view(CENTER, radius, &HashMap::new());
+            for x in 0..WORLD_SIZE.0 as usize {
+                for y in 0..WORLD_SIZE.1 as usize {
+                    let pos = Position::new(x, y);
+                    assert_eq!(
+                        visible.contains(&pos),
+                        in_range(pos, CENTER, radius),
+                        "{:?} with a radius of {}",
+                        pos,
+                        radius
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn nothing_outside_the_world_is_seen() {
+        for origin in [
+            Position::new(0, 0),
+            Position::new(WORLD_SIZE.0 as usize - 1, 3),
+        ] {
+            let visible = field_of_view(origin, VISION_RADIUS, &HashMap::new());
+            assert!(visible.contains(&origin));
+            assert!(visible.iter().all(|pos| {
+                pos.x < WORLD_SIZE.0 as usize
+                    && pos.y < WORLD_SIZE.1 as usize
+                    && in_range(*pos, origin, VISION_RADIUS)
+            }));
+        }
+    }
+
+    #[test]
+    fn walls_cast_shadows() {
+        // a wall three tiles east of the player, from two tiles north of them to two south
+        let terrain: HashMap<Position, [f32; 4]> = (23..=27)
+            .map(|y| (Position::new(28, y), tile::WALL))
+            .collect();
+        let visible = field_of_view(CENTER, VISION_RADIUS, &terrain);
+        assert!(visible.contains(&Position::new(28, 25)));
+        for x in 29..=35 {
+            assert!(
+                !visible.contains(&Position::new(x, 25)),
+                "saw through to x {}",
+                x
+            );
+        }
+        // the other way is still open
+        assert!(visible.contains(&Position::new(15, 25)));
+    }
+
+    #[test]
+    fn only_some_terrain_blocks_the_view() {
+        for (color, blocks) in [
+            (tile::WALL, true),
+            (tile::TREE, true),
+            (tile::LOCKED_DOOR, true),
+            (tile::WATER, false),
+            (tile::MUD, false),
+        ] {
+            let terrain = HashMap::from([(Position::new(26, 25), color)]);
+            let visible = field_of_view(CENTER, VISION_RADIUS, &terrain);
+            assert_eq!(blocks_vision(&color), blocks);
+            assert_eq!(!visible.contains(&Position::new(30, 25)), blocks);
+        }
+    }
+}
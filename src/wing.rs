@@ -0,0 +1,648 @@
+use crate::{
+    combat_log::GameEvent,
+    direction::Direction,
+    enemy::Enemy,
+    entity::Entity,
+    random,
+    river::River,
+    tile,
+    tileset::TileBatch,
+    utils::Position,
+    world::{World, BOSS_ROOMS, FINAL_BOSS_ROOM},
+    BOARD_SIZE, TILE_SIZE, UNIVERSAL_OFFSET, WORLD_SIZE,
+};
+use ggez::{glam::Vec2, graphics};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+#[cfg(test)]
+use std::collections::HashSet;
+
+// The rooms of a wing sit on a 3x3 grid. Each one is built around the 2x2 tiles starting at these
+// coordinates, so the middle row and column line up with the gaps in the boss room walls
+const CELL_CENTERS: [usize; 3] = [7, 24, 41];
+// The lines between the cells of the grid, locked doors sit on them
+const CELL_WALLS: [usize; 2] = [16, 33];
+// How far a room reaches out past its center tiles on each side, at least and at most
+const ROOM_REACH: (i16, i16) = (2, 6);
+const WING_HUD_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+// The rooms leading up to a mini boss, built in the world next to its boss room. Keys lying around
+// the wing open its locked doors, and the boss key opens the door into the boss room, which is the
+// only way in until the boss is beaten
+pub struct Wing {
+    // the world the wing takes up, and the boss room it leads to
+    pub world_pos: Position,
+    pub boss_room: Position,
+    // which side of the wing the boss room is on
+    boss_side: Direction,
+    // keys still lying on the floor
+    keys: Vec<Position>,
+    boss_key: Option<Position>,
+    // both tiles of every door that is still locked
+    locked_doors: Vec<[Position; 2]>,
+    // the door in the boss room's wall, None once it has been opened
+    boss_door: Option<[Position; 2]>,
+    // keys picked up and not used yet, they only open doors in this wing
+    keys_held: usize,
+    has_boss_key: bool,
+}
+
+impl Wing {
+    // Builds a wing onto every mini boss room. Each wing gets its own rng seeded from the world's,
+    // so the same seed always builds the same wings
+    pub fn gen_wings(world: &mut World, rng: &mut ChaCha8Rng, locked_doors: usize, guards: usize) {
+        for boss_room in BOSS_ROOMS {
+            if boss_room == FINAL_BOSS_ROOM {
+                continue;
+            }
+            let mut wing_rng = ChaCha8Rng::seed_from_u64(rng.gen());
+            if let Some(wing) =
+                Self::gen_wing(world, &mut wing_rng, boss_room, locked_doors, guards)
+            {
+                world.wings.push(wing);
+            }
+        }
+    }
+
+    // The wing, if any, in the world the player is in
+    pub fn current(world: &World) -> Option<&Wing> {
+        world
+            .wings
+            .iter()
+            .find(|wing| wing.world_pos == world.world_position)
+    }
+
+    fn gen_wing(
+        world: &mut World,
+        rng: &mut ChaCha8Rng,
+        boss_room: Position,
+        locked_doors: usize,
+        guards: usize,
+    ) -> Option<Wing> {
+        // any world next to the boss room will do, as long as the wing can be walked into from
+        // somewhere that isn't part of another wing or boss room
+        let mut sides = [
+            Direction::North,
+            Direction::South,
+            Direction::East,
+            Direction::West,
+        ];
+        shuffle(rng, &mut sides);
+        // a wing would cut a river off, so worlds with one are only used if there's nowhere else
+        let mut choice = None;
+        for avoid_rivers in [true, false] {
+            choice = sides.into_iter().find_map(|side| {
+                let world_pos = neighbour(boss_room, side)?;
+                if BOSS_ROOMS.contains(&world_pos)
+                    || Self::is_taken(world, world_pos)
+                    || (avoid_rivers && River::flows_through(world, world_pos))
+                {
+                    return None;
+                }
+                let boss_side = opposite(side);
+                let entrance_side = Self::entrance_side(world, rng, world_pos, boss_side)?;
+                Some((world_pos, boss_side, entrance_side))
+            });
+            if choice.is_some() {
+                break;
+            }
+        }
+        let (world_pos, boss_side, entrance_side) = choice?;
+
+        // start from solid wall and dig the rooms out of it
+        world.enemies_map[world_pos.y][world_pos.x].clear();
+        world.entity_map[world_pos.y][world_pos.x].clear();
+        world.atmosphere_map[world_pos.y][world_pos.x].clear();
+        let terrain = &mut world.terrain_map[world_pos.y][world_pos.x];
+        terrain.clear();
+        for x in 0..WORLD_SIZE.0 as usize {
+            for y in 0..WORLD_SIZE.1 as usize {
+                terrain.insert(Position::new(x, y), tile::WALL);
+            }
+        }
+
+        let entrance = edge_cell(entrance_side);
+        let boss_cell = edge_cell(boss_side);
+        let parents = spanning_tree(rng, entrance);
+        let mut rooms = [[(Position::new(0, 0), Position::new(0, 0)); 3]; 3];
+        for cx in 0..3 {
+            for cy in 0..3 {
+                let left = CELL_CENTERS[cx] - random::rand_range(rng, ROOM_REACH.0, ROOM_REACH.1) as usize;
+                let right = CELL_CENTERS[cx] + 1 + random::rand_range(rng, ROOM_REACH.0, ROOM_REACH.1) as usize;
+                let top = CELL_CENTERS[cy] - random::rand_range(rng, ROOM_REACH.0, ROOM_REACH.1) as usize;
+                let bottom = CELL_CENTERS[cy] + 1 + random::rand_range(rng, ROOM_REACH.0, ROOM_REACH.1) as usize;
+                rooms[cy][cx] = (Position::new(left, top), Position::new(right, bottom));
+                dig(terrain, Position::new(left, top), Position::new(right, bottom));
+            }
+        }
+        for (cell, parent) in parents.iter().enumerate() {
+            if let Some(parent) = parent {
+                let (a, b) = (cell_center(cell_pos(cell)), cell_center(*parent));
+                dig(
+                    terrain,
+                    Position::new(a.x.min(b.x), a.y.min(b.y)),
+                    Position::new(a.x.max(b.x) + 1, a.y.max(b.y) + 1),
+                );
+            }
+        }
+        for (side, cell) in [(entrance_side, entrance), (boss_side, boss_cell)] {
+            let [edge, _] = gap_tiles(side);
+            let center = cell_center(cell);
+            dig(
+                terrain,
+                Position::new(edge.x.min(center.x), edge.y.min(center.y)),
+                Position::new(edge.x.max(center.x) + 1, edge.y.max(center.y) + 1),
+            );
+        }
+
+        // the locks go on the way from the entrance to the boss, so none of them can be walked
+        // around, and the doors between them are the order they have to be opened in
+        let mut path = vec![boss_cell];
+        while let Some(parent) = parents[cell_index(*path.last().unwrap())] {
+            path.push(parent);
+        }
+        path.reverse();
+        let lock_count = locked_doors.min(path.len() - 1);
+        let mut locked_steps: Vec<usize> = (0..path.len() - 1).collect();
+        shuffle(rng, &mut locked_steps);
+        locked_steps.truncate(lock_count);
+        let mut locked_doors = Vec::new();
+        for step in 0..path.len() - 1 {
+            if locked_steps.contains(&step) {
+                let door = door_tiles(path[step], path[step + 1]);
+                for pos in door {
+                    terrain.insert(pos, tile::LOCKED_DOOR);
+                }
+                locked_doors.push(door);
+            }
+        }
+
+        // every room belongs to the stretch between two locks, counting the locks on its way back
+        // to the entrance. The key for each lock lies somewhere before it, off to the side if it can
+        let mut stretch = [0; 9];
+        for cell in tree_order(&parents, entrance) {
+            if let Some(parent) = parents[cell] {
+                let locked = locked_doors.contains(&door_tiles(cell_pos(cell), parent));
+                stretch[cell] = stretch[cell_index(parent)] + locked as usize;
+            }
+        }
+        let mut keys = Vec::new();
+        let mut boss_key = None;
+        for lock in 0..=locked_doors.len() {
+            let cells: Vec<usize> = (0..9).filter(|cell| stretch[*cell] == lock).collect();
+            let off_path: Vec<usize> = cells
+                .iter()
+                .copied()
+                .filter(|cell| !path.contains(&cell_pos(*cell)))
+                .collect();
+            let choices = if off_path.is_empty() { &cells } else { &off_path };
+            let cell = cell_pos(choices[random::rand_range(rng, 0, choices.len() as i16) as usize]);
+            let (top_left, bottom_right) = rooms[cell.y][cell.x];
+            // two keys on the same tile would look like one
+            let pos = loop {
+                let pos = Position::new(
+                    random::rand_range(rng, top_left.x as i16, bottom_right.x as i16 + 1) as usize,
+                    random::rand_range(rng, top_left.y as i16, bottom_right.y as i16 + 1) as usize,
+                );
+                if !keys.contains(&pos) {
+                    break pos;
+                }
+            };
+            if lock < locked_doors.len() {
+                keys.push(pos);
+            } else {
+                boss_key = Some(pos);
+            }
+        }
+
+        // guards wait in every room but the first one
+        for cell in 0..9 {
+            if cell_pos(cell) == entrance {
+                continue;
+            }
+            let (top_left, bottom_right) = rooms[cell_pos(cell).y][cell_pos(cell).x];
+            for _ in 0..guards {
+                let pos = Position::new(
+                    random::rand_range(rng, top_left.x as i16, bottom_right.x as i16 + 1) as usize,
+                    random::rand_range(rng, top_left.y as i16, bottom_right.y as i16 + 1) as usize,
+                );
+                let entities = &mut world.entity_map[world_pos.y][world_pos.x];
+                if entities.contains_key(&pos) || keys.contains(&pos) || boss_key == Some(pos) {
+                    continue;
+                }
+                entities.insert(pos, (tile::CHASING_ENEMY, Entity::Enemy));
+                world.enemies_map[world_pos.y][world_pos.x].push(Enemy::chasing(
+                    pos.x,
+                    pos.y,
+                    world_pos,
+                    &world.balance.enemies,
+                ));
+            }
+        }
+
+        // the wing is the only way into the boss room, the other doors stay shut until the boss
+        // is beaten and toggle_doors opens them all
+        let boss_terrain = &mut world.terrain_map[boss_room.y][boss_room.x];
+        let boss_door = gap_tiles(opposite(boss_side));
+        for side in [
+            Direction::North,
+            Direction::South,
+            Direction::East,
+            Direction::West,
+        ] {
+            let color = if side == opposite(boss_side) {
+                tile::BOSS_DOOR
+            } else {
+                tile::WALL
+            };
+            for pos in gap_tiles(side) {
+                boss_terrain.insert(pos, color);
+            }
+        }
+
+        // and nothing grown outside should block the way in
+        let outside = neighbour(world_pos, entrance_side).unwrap();
+        for pos in gap_tiles(opposite(entrance_side)) {
+            world.terrain_map[outside.y][outside.x].remove(&pos);
+            world.terrain_map[outside.y][outside.x].remove(&step(pos, entrance_side));
+        }
+
+        Some(Wing {
+            world_pos,
+            boss_room,
+            boss_side,
+            keys,
+            boss_key,
+            locked_doors,
+            boss_door: Some(boss_door),
+            keys_held: 0,
+            has_boss_key: false,
+        })
+    }
+
+    // The side the player walks into the wing from, straight across from the boss room if it can
+    fn entrance_side(
+        world: &World,
+        rng: &mut ChaCha8Rng,
+        world_pos: Position,
+        boss_side: Direction,
+    ) -> Option<Direction> {
+        let open = |side: Direction| {
+            neighbour(world_pos, side).is_some_and(|outside| {
+                !BOSS_ROOMS.contains(&outside) && !Self::is_taken(world, outside)
+            })
+        };
+        if open(opposite(boss_side)) {
+            return Some(opposite(boss_side));
+        }
+        let mut sides = [turn(boss_side), opposite(turn(boss_side))];
+        shuffle(rng, &mut sides);
+        sides.into_iter().find(|side| open(*side))
+    }
+
+    fn is_taken(world: &World, world_pos: Position) -> bool {
+        world.wings.iter().any(|wing| wing.world_pos == world_pos)
+    }
+
+    // Picks up keys the player is standing on, and opens the locked door they are next to if they
+    // have a key for it
+    pub fn update(world: &mut World) {
+        let Some(index) = world
+            .wings
+            .iter()
+            .position(|wing| wing.world_pos == world.world_position)
+        else {
+            return;
+        };
+        let pos = world.player.pos;
+        let mut opened = false;
+        let wing = &mut world.wings[index];
+        if let Some(key) = wing.keys.iter().position(|key| *key == pos) {
+            wing.keys.remove(key);
+            wing.keys_held += 1;
+            world.events.push(GameEvent::KeyFound { boss_key: false });
+        }
+        if wing.boss_key == Some(pos) {
+            wing.boss_key = None;
+            wing.has_boss_key = true;
+            world.events.push(GameEvent::KeyFound { boss_key: true });
+        }
+        if wing.keys_held > 0 {
+            let next_to = |tile: &Position| pos.x.abs_diff(tile.x) + pos.y.abs_diff(tile.y) == 1;
+            if let Some(door) = wing
+                .locked_doors
+                .iter()
+                .position(|door| door.iter().any(next_to))
+            {
+                for tile in wing.locked_doors.remove(door) {
+                    world.terrain_map[wing.world_pos.y][wing.world_pos.x].remove(&tile);
+                }
+                wing.keys_held -= 1;
+                opened = true;
+                world.events.push(GameEvent::DoorUnlocked { boss_door: false });
+            }
+        }
+        // the boss door is in the boss room's wall, right across the edge from the wing's gap
+        if wing.has_boss_key && gap_tiles(wing.boss_side).contains(&pos) {
+            if let Some(door) = wing.boss_door.take() {
+                for tile in door {
+                    world.terrain_map[wing.boss_room.y][wing.boss_room.x].remove(&tile);
+                }
+                opened = true;
+                world.events.push(GameEvent::DoorUnlocked { boss_door: true });
+            }
+        }
+        if opened {
+            world.terrain_changed();
+        }
+    }
+
+    // Whether the wing can be played through, every lock opened with a key picked up on the
+    // player's side of it and the boss key reached after that. For the world generation tests
+    #[cfg(test)]
+    pub fn is_solvable(&self, world: &World) -> bool {
+        let terrain = &world.terrain_map[self.world_pos.y][self.world_pos.x];
+        let mut locked = self.locked_doors.clone();
+        let mut keys = self.keys.clone();
+        let mut held = 0;
+        loop {
+            // everywhere in the wing the player can get to with the doors opened so far
+            let is_open = |pos: &Position| match terrain.get(pos) {
+                None => true,
+                Some(&tile::LOCKED_DOOR) => !locked.iter().any(|door| door.contains(pos)),
+                Some(_) => false,
+            };
+            let mut reached: HashSet<Position> = [
+                Direction::North,
+                Direction::South,
+                Direction::East,
+                Direction::West,
+            ]
+            .into_iter()
+            .filter(|side| *side != self.boss_side)
+            .flat_map(gap_tiles)
+            .filter(is_open)
+            .collect();
+            let mut queue: Vec<Position> = reached.iter().copied().collect();
+            while let Some(pos) = queue.pop() {
+                for next in next_to(pos) {
+                    if is_open(&next) && reached.insert(next) {
+                        queue.push(next);
+                    }
+                }
+            }
+
+            held += keys.iter().filter(|key| reached.contains(key)).count();
+            keys.retain(|key| !reached.contains(key));
+            let door = locked.iter().position(|door| {
+                door.iter()
+                    .any(|tile| next_to(*tile).iter().any(|pos| reached.contains(pos)))
+            });
+            match door {
+                Some(door) if held > 0 => {
+                    locked.remove(door);
+                    held -= 1;
+                }
+                _ => {
+                    return locked.is_empty()
+                        && self.boss_key.is_some_and(|key| reached.contains(&key));
+                }
+            }
+        }
+    }
+
+    // Keys still on the floor, wherever the player can see them
+    pub fn draw_keys(world: &World, batch: &mut TileBatch) {
+        if let Some(wing) = Self::current(world) {
+            for key in &wing.keys {
+                if world.visible_tiles.contains(key) {
+                    batch.push(*key, tile::KEY);
+                }
+            }
+            if let Some(key) = wing.boss_key {
+                if world.visible_tiles.contains(&key) {
+                    batch.push(key, tile::BOSS_KEY);
+                }
+            }
+        }
+    }
+
+    // The keys the player is carrying, under the bar on top while they are in the wing
+    pub fn draw_hud(world: &World, canvas: &mut graphics::Canvas) {
+        let Some(wing) = Self::current(world) else {
+            return;
+        };
+        let mut text = format!("Keys: {}", wing.keys_held);
+        if wing.has_boss_key && wing.boss_door.is_some() {
+            text.push_str(", boss key");
+        }
+        canvas.draw(
+            &*(graphics::Text::new(text).set_scale(20.)),
+            graphics::DrawParam::from(Vec2::new(
+                WORLD_SIZE.0 as f32 * TILE_SIZE.0 as f32 - 140.0,
+                UNIVERSAL_OFFSET as f32 * TILE_SIZE.1 as f32 + 4.0,
+            ))
+            .color(WING_HUD_COLOR),
+        );
+    }
+}
+
+// The tiles right next to pos that are still in the world
+#[cfg(test)]
+fn next_to(pos: Position) -> Vec<Position> {
+    [(0, 1), (0, -1), (1, 0), (-1, 0)]
+        .into_iter()
+        .map(|(dx, dy)| (pos.x as i16 + dx, pos.y as i16 + dy))
+        .filter(|(x, y)| (0..WORLD_SIZE.0).contains(x) && (0..WORLD_SIZE.1).contains(y))
+        .map(|(x, y)| Position::new(x as usize, y as usize))
+        .collect()
+}
+
+// The world next to world_pos on the given side, if the board goes that far
+fn neighbour(world_pos: Position, side: Direction) -> Option<Position> {
+    let worlds = (BOARD_SIZE.0 / WORLD_SIZE.0) as usize;
+    match side {
+        Direction::North if world_pos.y > 0 => Some(Position::new(world_pos.x, world_pos.y - 1)),
+        Direction::South if world_pos.y + 1 < worlds => {
+            Some(Position::new(world_pos.x, world_pos.y + 1))
+        }
+        Direction::West if world_pos.x > 0 => Some(Position::new(world_pos.x - 1, world_pos.y)),
+        Direction::East if world_pos.x + 1 < worlds => {
+            Some(Position::new(world_pos.x + 1, world_pos.y))
+        }
+        _ => None,
+    }
+}
+
+fn opposite(side: Direction) -> Direction {
+    match side {
+        Direction::North => Direction::South,
+        Direction::South => Direction::North,
+        Direction::East => Direction::West,
+        Direction::West => Direction::East,
+    }
+}
+
+// A quarter turn clockwise
+fn turn(side: Direction) -> Direction {
+    match side {
+        Direction::North => Direction::East,
+        Direction::East => Direction::South,
+        Direction::South => Direction::West,
+        Direction::West => Direction::North,
+    }
+}
+
+// One tile further in from pos, going away from the given edge of the world
+fn step(pos: Position, side: Direction) -> Position {
+    match side {
+        Direction::North => Position::new(pos.x, pos.y - 1),
+        Direction::South => Position::new(pos.x, pos.y + 1),
+        Direction::East => Position::new(pos.x + 1, pos.y),
+        Direction::West => Position::new(pos.x - 1, pos.y),
+    }
+}
+
+// The two tiles in the middle of a world's edge, where gen_outer_boss_walls leaves the doors
+fn gap_tiles(side: Direction) -> [Position; 2] {
+    let (middle, last) = (WORLD_SIZE.0 as usize / 2, WORLD_SIZE.0 as usize - 1);
+    match side {
+        Direction::North => [Position::new(middle - 1, 0), Position::new(middle, 0)],
+        Direction::South => [Position::new(middle - 1, last), Position::new(middle, last)],
+        Direction::West => [Position::new(0, middle - 1), Position::new(0, middle)],
+        Direction::East => [Position::new(last, middle - 1), Position::new(last, middle)],
+    }
+}
+
+// The cell of the grid in the middle of the given edge
+fn edge_cell(side: Direction) -> Position {
+    match side {
+        Direction::North => Position::new(1, 0),
+        Direction::South => Position::new(1, 2),
+        Direction::West => Position::new(0, 1),
+        Direction::East => Position::new(2, 1),
+    }
+}
+
+fn cell_index(cell: Position) -> usize {
+    cell.y * 3 + cell.x
+}
+
+fn cell_pos(index: usize) -> Position {
+    Position::new(index % 3, index / 3)
+}
+
+// The top left of the 2x2 tiles every room and corridor of a cell goes through
+fn cell_center(cell: Position) -> Position {
+    Position::new(CELL_CENTERS[cell.x], CELL_CENTERS[cell.y])
+}
+
+// The two tiles of the door on the wall between two cells next to each other
+fn door_tiles(a: Position, b: Position) -> [Position; 2] {
+    let center = cell_center(a);
+    if a.y == b.y {
+        let x = CELL_WALLS[a.x.min(b.x)];
+        [Position::new(x, center.y), Position::new(x, center.y + 1)]
+    } else {
+        let y = CELL_WALLS[a.y.min(b.y)];
+        [Position::new(center.x, y), Position::new(center.x + 1, y)]
+    }
+}
+
+// Links up all the cells of the grid with a random walk from root, giving every cell the one it
+// was reached from. Being a tree, there is only ever one way from one cell to another
+fn spanning_tree(rng: &mut ChaCha8Rng, root: Position) -> [Option<Position>; 9] {
+    let mut parents = [None; 9];
+    let mut visited = [false; 9];
+    visited[cell_index(root)] = true;
+    let mut stack = vec![root];
+    while let Some(&cell) = stack.last() {
+        let mut next: Vec<Position> = [
+            Direction::North,
+            Direction::South,
+            Direction::East,
+            Direction::West,
+        ]
+        .into_iter()
+        .filter_map(|side| match side {
+            Direction::North if cell.y > 0 => Some(Position::new(cell.x, cell.y - 1)),
+            Direction::South if cell.y < 2 => Some(Position::new(cell.x, cell.y + 1)),
+            Direction::West if cell.x > 0 => Some(Position::new(cell.x - 1, cell.y)),
+            Direction::East if cell.x < 2 => Some(Position::new(cell.x + 1, cell.y)),
+            _ => None,
+        })
+        .filter(|next| !visited[cell_index(*next)])
+        .collect();
+        if next.is_empty() {
+            stack.pop();
+            continue;
+        }
+        shuffle(rng, &mut next);
+        visited[cell_index(next[0])] = true;
+        parents[cell_index(next[0])] = Some(cell);
+        stack.push(next[0]);
+    }
+    parents
+}
+
+// The cells of the tree with every cell after the one it was reached from
+fn tree_order(parents: &[Option<Position>; 9], root: Position) -> Vec<usize> {
+    let mut order = vec![cell_index(root)];
+    let mut i = 0;
+    while i < order.len() {
+        let cell = cell_pos(order[i]);
+        order.extend((0..9).filter(|child| parents[*child] == Some(cell)));
+        i += 1;
+    }
+    order
+}
+
+// Clears out every tile of the rectangle inside the world, corners included
+fn dig(
+    terrain: &mut HashMap<Position, [f32; 4]>,
+    top_left: Position,
+    bottom_right: Position,
+) {
+    for x in top_left.x..=bottom_right.x.min(WORLD_SIZE.0 as usize - 1) {
+        for y in top_left.y..=bottom_right.y.min(WORLD_SIZE.1 as usize - 1) {
+            terrain.remove(&Position::new(x, y));
+        }
+    }
+}
+
+// Fisher-Yates with the world's rng, so the shuffles come out the same for the same seed
+fn shuffle<T>(rng: &mut ChaCha8Rng, items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        items.swap(i, random::rand_range(rng, 0, i as i16 + 1) as usize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wing_keys_are_before_their_locks() {
+        for seed in [0, 1, 42, 1234] {
+            let mut world = World::empty();
+            Wing::gen_wings(&mut world, &mut ChaCha8Rng::seed_from_u64(seed), 2, 2);
+            assert_eq!(
+                world.wings.len(),
+                BOSS_ROOMS.len() - 1,
+                "a boss room has no wing with seed {}",
+                seed
+            );
+            for wing in &world.wings {
+                assert_ne!(wing.boss_room, FINAL_BOSS_ROOM);
+                assert!(
+                    wing.is_solvable(&world),
+                    "the wing at {:?} can't be played through with seed {}",
+                    wing.world_pos,
+                    seed
+                );
+            }
+        }
+    }
+}
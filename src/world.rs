@@ -1,25 +1,45 @@
 use crate::{
+    camera::Camera,
+    combat_log::{Actor, GameEvent},
+    balance::{Balance, EnemyBalance},
+    biome::Biome,
+    boss::{BossKind, ScriptedBoss},
+    boss_script::BossScripts,
+    damage_number::DamageNumber,
+    difficulty::Difficulty,
     direction::Direction,
+    encounter::Encounter,
     enemy::Enemy,
     entity::Entity,
+    mode::Challenge,
     player::Player,
     projectile::Projectile,
     random,
+    river::River,
+    structure::Structure,
     tile::{self, FLOOR, PLAYER, *},
+    render::Renderer,
+    tileset::TileBatch,
     utils::Boss,
     utils::Position,
+    vision,
+    wing::Wing,
+    worldgen::Preset,
     BOARD_SIZE, TILE_SIZE, UNIVERSAL_OFFSET, WORLD_SIZE,
-    player::PLAYER_PROJECTILE_DAMAGE,
 };
 use serde::ser::{Serialize, SerializeStruct, Serializer};
 
-use ggez::graphics;
+use ggez::{glam::Vec2, graphics, Context};
 use rand::prelude::*;
 use rand::rngs::ThreadRng;
 use rand_chacha::ChaCha8Rng;
 
-use std::cmp::min;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Counts every change to the terrain of any world, in this game or an earlier one, so a number
+// handed out here is never seen twice
+static TERRAIN_GENERATION: AtomicU64 = AtomicU64::new(0);
 
 pub const BOSS_ROOMS: [Position; 5] = [
     Position::new(1, 1),
@@ -29,9 +49,38 @@ pub const BOSS_ROOMS: [Position; 5] = [
     Position::new(5, 5),
 ];
 pub const FINAL_BOSS_ROOM: Position = Position::new(3, 3);
-const LAKES_PER_WORLD: i16 = 3;
-const TOTAL_MOUNTAINS: i16 = 60;
-const ENEMY_COUNT: usize = 500;
+// The script from bosses.ron of the boss waiting in each of the BOSS_ROOMS
+pub const BOSS_ROOM_SCRIPTS: [&str; 5] = [
+    "laser_grid",
+    "sliding_laser",
+    "final",
+    "chasing",
+    "blackout",
+];
+
+// Every tile of the board is shrunk down to this many pixels on the full screen world map
+const FULL_MAP_TILE_PIXELS: f32 = 2.0;
+// Top left corner of the full screen world map, centered horizontally under the title
+const FULL_MAP_ORIGIN: (f32, f32) = (
+    (WORLD_SIZE.0 as f32 * TILE_SIZE.0 as f32 - BOARD_SIZE.0 as f32 * FULL_MAP_TILE_PIXELS) / 2.0,
+    UNIVERSAL_OFFSET as f32 * TILE_SIZE.1 as f32 + 40.0,
+);
+const FULL_MAP_HIDDEN: [f32; 4] = [0.12, 0.12, 0.15, 1.0]; //Rooms that haven't been visited yet
+const FULL_MAP_UNEXPLORED: [f32; 4] = [0.0, 0.0, 0.0, 1.0]; //Parts of a visited room never seen
+const FULL_MAP_CLEARED: [f32; 4] = [0.3, 0.9, 0.3, 1.0]; //Border around rooms with no enemies left
+const FULL_MAP_BOSS: [f32; 4] = [0.9, 0.2, 0.2, 1.0];
+const FULL_MAP_BOSS_DEFEATED: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+
+// Health bars over enemies, in pixels
+const HEALTH_BAR_HEIGHT: f32 = 3.0;
+const HEALTH_BAR_BACKGROUND: [f32; 4] = [0.3, 0.0, 0.0, 0.8];
+const HEALTH_BAR_FILL: [f32; 4] = [0.2, 0.9, 0.2, 1.0];
+// Boss health bar along the bottom of the bar on top of the screen, under the ability bar
+const BOSS_BAR_Y: f32 = 121.0;
+const BOSS_BAR_HEIGHT: f32 = 5.0;
+const BOSS_BAR_MARGIN: f32 = 2.0;
+const BOSS_BAR_BACKGROUND: [f32; 4] = [0.2, 0.2, 0.2, 1.0];
+const BOSS_BAR_FILL: [f32; 4] = [0.85, 0.1, 0.1, 1.0];
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
 pub struct World {
@@ -67,73 +116,239 @@ pub struct World {
 
     // Hashmap of positions to colors
     // #[serde(with = "vectorize")]
+    #[serde(serialize_with = "sorted_maps")]
     pub entity_map: [[HashMap<Position, ([f32; 4], Entity)>;
         (BOARD_SIZE.1 / WORLD_SIZE.1) as usize];
         (BOARD_SIZE.0 / WORLD_SIZE.0) as usize],
     // #[serde(with = "vectorize")]
+    #[serde(serialize_with = "sorted_maps")]
     pub terrain_map: [[HashMap<Position, [f32; 4]>; (BOARD_SIZE.1 / WORLD_SIZE.1) as usize];
         (BOARD_SIZE.0 / WORLD_SIZE.0) as usize],
     // #[serde(with = "vectorize")]
+    #[serde(serialize_with = "sorted_maps")]
     pub atmosphere_map: [[HashMap<Position, [f32; 4]>; (BOARD_SIZE.1 / WORLD_SIZE.1) as usize];
         (BOARD_SIZE.0 / WORLD_SIZE.0) as usize],
     pub boss_defeated: [[bool; 7]; 7],
-    pub boss_lasers: Vec<(Position, [f32; 4], usize)>, //Position, color, duration left
-    pub boss_asteroids: Vec<(Position, [f32; 4], usize)>, //Position, color, duration left
-    pub boss_column_laser: Option<(Position, Direction)>, //Position and direction laser will move
-    pub boss_safe_spot: Option<(Position, usize, usize)>, //Center position, size of safe spot, safe spot time
-    pub boss_vulnerable_spot: Option<Position>,
-    pub in_blackout: bool,
-    pub stun_wells: Vec<(Position, [f32; 4], usize, usize, bool)>, //Position, color, size, duration left, currently in a well
+    // the attacks and hazards of the boss fight in each boss room
+    #[serde(default)]
+    pub encounters: [[Encounter; (BOARD_SIZE.0 / WORLD_SIZE.0) as usize];
+        (BOARD_SIZE.1 / WORLD_SIZE.1) as usize],
     pub bomber_explosions: [[Vec<(Position, [f32; 4])>; (BOARD_SIZE.1 / WORLD_SIZE.1) as usize];
         (BOARD_SIZE.0 / WORLD_SIZE.0) as usize],
+
+    // list of the structures the player has built in each world
+    #[serde(default)]
+    pub structures_map: [[Vec<Structure>; (BOARD_SIZE.0 / WORLD_SIZE.0) as usize];
+        (BOARD_SIZE.1 / WORLD_SIZE.1) as usize],
+
+    // scales enemies and bosses, picked when the game was started
+    #[serde(default)]
+    pub difficulty: Difficulty,
+
+    // how far a boss rush or arena game has got, adventures don't use it
+    #[serde(default)]
+    pub challenge: Challenge,
+
+    // the dungeon wings leading up to the mini bosses
+    #[serde(default)]
+    pub wings: Vec<Wing>,
+
+    // the rivers running across the board
+    #[serde(default)]
+    pub rivers: Vec<River>,
+
+    // what kind of land each world is. Saves from before biomes are all plains
+    #[serde(default)]
+    pub biomes: [[Biome; (BOARD_SIZE.0 / WORLD_SIZE.0) as usize];
+        (BOARD_SIZE.1 / WORLD_SIZE.1) as usize],
+
+    // the numbers the game is tuned with. Not saved, whatever balance.ron says now is used
+    #[serde(skip)]
+    pub balance: Balance,
+
+    // how every boss fights, from bosses.ron. Not saved either, bosses only remember their script
+    #[serde(skip)]
+    pub boss_scripts: BossScripts,
+
+    // tiles of each world the player has seen before, drawn dimmed while out of sight
+    #[serde(default, serialize_with = "sorted_sets")]
+    pub explored_map: [[HashSet<Position>; (BOARD_SIZE.0 / WORLD_SIZE.0) as usize];
+        (BOARD_SIZE.1 / WORLD_SIZE.1) as usize],
+    // tiles of the current world the player can see right now, recalculated every turn
+    #[serde(skip)]
+    pub visible_tiles: HashSet<Position>,
+    // changes whenever any terrain does, so the renderer knows when its batches are out of date.
+    // A loaded or rewound world gets a new one too
+    #[serde(skip, default = "next_terrain_generation")]
+    pub terrain_generation: u64,
+
+    // Damage numbers still floating over whatever got hit
+    #[serde(skip)]
+    pub damage_numbers: Vec<DamageNumber>,
+
+    // What happened during the current turn, for the combat log
+    #[serde(skip)]
+    pub events: Vec<GameEvent>,
+}
+
+fn next_terrain_generation() -> u64 {
+    TERRAIN_GENERATION.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+// HashMaps and HashSets list what's in them in a different order every time the game runs, so
+// the ones in a world are written out sorted. That way the same world always comes out as the
+// same text, which the undo history counts on to spot what a turn didn't change
+fn sorted_maps<S: Serializer, V: Serialize>(
+    maps: &[[HashMap<Position, V>; (BOARD_SIZE.0 / WORLD_SIZE.0) as usize];
+        (BOARD_SIZE.1 / WORLD_SIZE.1) as usize],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let sorted: [[BTreeMap<&Position, &V>; (BOARD_SIZE.0 / WORLD_SIZE.0) as usize];
+        (BOARD_SIZE.1 / WORLD_SIZE.1) as usize] =
+        std::array::from_fn(|y| std::array::from_fn(|x| maps[y][x].iter().collect()));
+    sorted.serialize(serializer)
+}
+
+fn sorted_sets<S: Serializer>(
+    sets: &[[HashSet<Position>; (BOARD_SIZE.0 / WORLD_SIZE.0) as usize];
+        (BOARD_SIZE.1 / WORLD_SIZE.1) as usize],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let sorted: [[BTreeSet<&Position>; (BOARD_SIZE.0 / WORLD_SIZE.0) as usize];
+        (BOARD_SIZE.1 / WORLD_SIZE.1) as usize] =
+        std::array::from_fn(|y| std::array::from_fn(|x| sets[y][x].iter().collect()));
+    sorted.serialize(serializer)
 }
 
 impl World {
-    pub fn new(rng: &mut ChaCha8Rng) -> Self {
+    // An empty board with only the player on it, filled in by the steps of the preset
+    pub fn new(
+        rng: &mut ChaCha8Rng,
+        difficulty: Difficulty,
+        balance: &Balance,
+        boss_scripts: &BossScripts,
+        preset: &Preset,
+    ) -> Self {
         let mut entity_map: [[HashMap<Position, ([f32; 4], Entity)>;
             (BOARD_SIZE.0 / WORLD_SIZE.0) as usize];
             (BOARD_SIZE.1 / WORLD_SIZE.1) as usize] = Default::default();
-        let mut terrain_map: [[HashMap<Position, [f32; 4]>; (BOARD_SIZE.0 / WORLD_SIZE.0) as usize];
-            (BOARD_SIZE.1 / WORLD_SIZE.1) as usize] = Default::default();
-        let mut boss_defeated = [[false; 7]; 7];
-        World::gen_boss(&mut terrain_map);
-        World::gen_outer_boss_walls(&mut terrain_map);
-        World::gen_mountain(rng, &mut terrain_map);
-        World::gen_lake(rng, &mut terrain_map);
-        // World::add_doors(&mut terrain_map);
-        let player = Player::new();
+        let player = Player::new(&balance.player);
         let starting_map = &mut entity_map[player.pos.y][player.pos.x];
         starting_map.insert(player.pos, (player.color, Entity::Player));
-        let mut enemies_map = Default::default();
-        let mut bosses = Vec::new();
-        let mut bomber_explosions: [[Vec<(Position, [f32; 4])>;
-            (BOARD_SIZE.0 / WORLD_SIZE.0) as usize];
-            (BOARD_SIZE.1 / WORLD_SIZE.1) as usize] = Default::default();
-        World::gen_enemies(rng, &mut terrain_map, &mut entity_map, &mut enemies_map);
-        World::gen_bosses(&mut terrain_map, &mut entity_map, &mut bosses);
-        World {
+        let mut world = World {
             world_position: Position::new(0, 0),
             top_left: (0, 0),
             bottom_right: (WORLD_SIZE.0 as usize, (WORLD_SIZE.1) as usize),
             board_top_left: (0, 0),
             board_bottom_right: (BOARD_SIZE.0 as usize, (BOARD_SIZE.1) as usize),
             player,
-            enemies_map,
-            bosses,
+            enemies_map: Default::default(),
+            bosses: Vec::new(),
             projectiles: Vec::new(),
             entity_map,
-            terrain_map,
+            terrain_map: Default::default(),
             atmosphere_map: Default::default(),
-            boss_defeated,
-            boss_lasers: Vec::new(),
-            boss_asteroids: Vec::new(),
-            stun_wells: Vec::new(),
-            boss_column_laser: None,
-            boss_safe_spot: None,
-            boss_vulnerable_spot: None,
-            in_blackout: false,
-            bomber_explosions,
+            boss_defeated: [[false; 7]; 7],
+            encounters: Default::default(),
+            bomber_explosions: Default::default(),
+            structures_map: Default::default(),
+            difficulty,
+            challenge: Challenge::default(),
+            wings: Vec::new(),
+            rivers: Vec::new(),
+            biomes: Default::default(),
+            balance: balance.clone(),
+            boss_scripts: boss_scripts.clone(),
+            explored_map: Default::default(),
+            visible_tiles: HashSet::new(),
+            terrain_generation: next_terrain_generation(),
+            damage_numbers: Vec::new(),
+            events: Vec::new(),
+        };
+        preset.generate(&mut world, rng);
+        world.update_vision();
+        world
+    }
+
+    // A board with nothing on it but the player, for tests to run world generation steps on
+    #[cfg(test)]
+    pub fn empty() -> Self {
+        Self::new(
+            &mut ChaCha8Rng::seed_from_u64(0),
+            Difficulty::default(),
+            &Balance::default(),
+            &BossScripts::default(),
+            &Preset { steps: Vec::new() },
+        )
+    }
+
+    // Turns the hits every enemy in the player's world and the player took since the last time
+    // into damage numbers and events for the log
+    pub fn collect_hits(&mut self) {
+        let world_pos = self.world_position;
+        for enemy in &mut self.enemies_map[world_pos.y][world_pos.x] {
+            let center = DamageNumber::center(&enemy.pos);
+            let target = Actor::Enemy(enemy.name());
+            for (source, amount) in enemy.recent_hits.drain(..) {
+                self.damage_numbers
+                    .push(DamageNumber::new(center, world_pos, amount, false));
+                self.events.push(GameEvent::Damaged {
+                    source,
+                    target,
+                    amount,
+                });
+            }
+            if let Some(source) = enemy.killed_by.take() {
+                self.events.push(GameEvent::Killed { source, target });
+            }
         }
+        for (source, amount) in self.player.recent_hits.drain(..) {
+            self.events.push(GameEvent::Damaged {
+                source,
+                target: Actor::Player,
+                amount,
+            });
+        }
+        if let Some(source) = self.player.killed_by.take() {
+            self.events.push(GameEvent::Killed {
+                source,
+                target: Actor::Player,
+            });
+        }
+    }
+
+    // The boss fight in the room the player is in, which is empty outside of boss rooms
+    pub fn encounter(&self) -> &Encounter {
+        &self.encounters[self.world_position.y][self.world_position.x]
+    }
+
+    pub fn encounter_mut(&mut self) -> &mut Encounter {
+        &mut self.encounters[self.world_position.y][self.world_position.x]
+    }
+
+    // Has to be called after changing terrain_map once the world is on screen, or the old terrain
+    // keeps getting drawn
+    pub fn terrain_changed(&mut self) {
+        self.terrain_generation = next_terrain_generation();
+    }
+
+    // Recalculates what the player can see from where they are standing, and remembers it
+    pub fn update_vision(&mut self) {
+        let radius = if self.encounter().in_blackout {
+            vision::BLACKOUT_VISION_RADIUS
+        } else if BOSS_ROOMS.contains(&self.world_position) {
+            // boss arenas are fully lit so their attacks can always be seen coming
+            WORLD_SIZE.0 as i32
+        } else {
+            vision::VISION_RADIUS
+        };
+        self.visible_tiles = vision::field_of_view(
+            self.player.pos,
+            radius,
+            &self.terrain_map[self.world_position.y][self.world_position.x],
+        );
+        self.explored_map[self.world_position.y][self.world_position.x]
+            .extend(self.visible_tiles.iter().copied());
     }
 
     pub fn gen_enemies(
@@ -145,8 +360,11 @@ impl World {
                  (BOARD_SIZE.1 / WORLD_SIZE.1) as usize],
 
         enemies_map: &mut [[Vec<Enemy>; (BOARD_SIZE.0/WORLD_SIZE.0) as usize]; (BOARD_SIZE.1/WORLD_SIZE.1) as usize],
+        enemy_count: usize,
+        balance: &EnemyBalance,
+        biomes: &[[Biome; (BOARD_SIZE.0 / WORLD_SIZE.0) as usize]; (BOARD_SIZE.1 / WORLD_SIZE.1) as usize],
     ) {
-        for _ in 0..ENEMY_COUNT {
+        for _ in 0..enemy_count {
             // the loop just generates new positions until it finds an open one, and it inserts an
             // enemy there
             loop {
@@ -175,20 +393,25 @@ impl World {
                         // y positions are greater than 5
                     && ((world_x, world_y) != (0, 0) || ((x > 5) && y > 5))
                 {
-                    if random::bernoulli(rng, 0.4) {
+                    // which kind of enemy it is depends on the biome
+                    let table = biomes[world_y as usize][world_x as usize].spawn_table();
+                    let total = (table.chasers + table.bombers + table.majors) as f32;
+                    if random::bernoulli(rng, table.chasers as f32 / total) {
                         world_map_entity.insert(random_loc, (tile::CHASING_ENEMY, Entity::Enemy));
                         enemies_map[world_y as usize][world_x as usize].push(Enemy::chasing(
                             x as usize,
                             y as usize,
                             Position::new(world_x as usize, world_y as usize),
+                            balance,
                         ));
                         break;
-                    } else if random::bernoulli(rng, 0.5){
+                    } else if random::bernoulli(rng, table.bombers as f32 / (table.bombers + table.majors) as f32) {
                         world_map_entity.insert(random_loc, (tile::BOMBER_ENEMY, Entity::Enemy));
                         enemies_map[world_y as usize][world_x as usize].push(Enemy::bomber(
                             x as usize,
                             y as usize,
                             Position::new(world_x as usize, world_y as usize),
+                            balance,
                         ));
                         break;
                     } else if x < WORLD_SIZE.0 - 2 && y < WORLD_SIZE.0 - 2 {
@@ -201,6 +424,7 @@ impl World {
                             x as usize,
                             y as usize,
                             Position::new(world_x as usize, world_y as usize),
+                            balance,
                         ));
                         break;
                     } 
@@ -217,41 +441,32 @@ impl World {
                  (BOARD_SIZE.1 / WORLD_SIZE.1) as usize],
 
         bosses: &mut Vec<Boss>,
+        difficulty: Difficulty,
+        balance: &Balance,
+        boss_scripts: &BossScripts,
     ) {
-        for room_coord in BOSS_ROOMS {
+        for (room_coord, script) in BOSS_ROOMS.into_iter().zip(BOSS_ROOM_SCRIPTS) {
+            let boss_script = boss_scripts
+                .get(script)
+                .expect("bosses.ron is checked for every boss room's script when it's loaded");
+            let kind = BossKind::Scripted(ScriptedBoss::new(script, boss_script));
             let world_map_terrain = &mut terrain_map[room_coord.y][room_coord.x];
             let x = WORLD_SIZE.0 as usize / 2;
             let y = WORLD_SIZE.1 as usize / 2;
-            if room_coord == Position::new(3, 3) {
-                bosses.push(Boss::new(
-                    x as usize,
-                    y as usize,
-                    tile::MAJOR_BOSS,
-                    room_coord,
-                    world_map_terrain,
-                ));
-            } else {
-                let mut boss_tile = tile::LASER_BOSS;
-                if room_coord == BOSS_ROOMS[1] {
-                    boss_tile = tile::COLUMN_BOSS;
-                } else if room_coord == BOSS_ROOMS[3] {
-                    boss_tile = tile::CHASING_BOSS;
-                } else if room_coord == BOSS_ROOMS[4] {
-                    boss_tile = tile::BLACK_OUT_BOSS;
-                } 
-                bosses.push(Boss::new(
-                    x as usize,
-                    y as usize,
-                    boss_tile,
-                    room_coord,
-                    world_map_terrain,
-                ));
-            }
+            bosses.push(Boss::new(
+                x,
+                y,
+                kind,
+                room_coord,
+                world_map_terrain,
+                difficulty,
+                &balance.bosses,
+            ));
         }
     }
 
     //Draws the map on the top right and corner of the world
-    pub fn draw_world_map(&self, canvas: &mut graphics::Canvas) {
+    pub fn draw_world_map(&self, hud: &mut TileBatch) {
         //Get number of cells on each x and y axis
         let mut x = BOARD_SIZE.0 as usize / WORLD_SIZE.0 as usize;
         let mut y = BOARD_SIZE.1 as usize / WORLD_SIZE.0 as usize;
@@ -266,17 +481,15 @@ impl World {
                    j % 5 == 0 || j == 0 || j == y * 6 - y
                 {
                     //See above comment but for vertical lines
-                    canvas.draw(
-                        &graphics::Quad,
-                        graphics::DrawParam::new()
-                            .dest_rect(graphics::Rect::new_i32(
-                                (i as i32 + 360) * 2 as i32,
-                                (j as i32 + 2) * 2 as i32,
-                                2,
-                                2,
-                            ))
-                            .color([0.0, 0.0, 0.0, 1.0]),
-                    )
+                    hud.push_rect(
+                        graphics::Rect::new_i32(
+                            (i as i32 + 360) * 2 as i32,
+                            (j as i32 + 2) * 2 as i32,
+                            2,
+                            2,
+                        ),
+                        [0.0, 0.0, 0.0, 1.0],
+                    );
                 }
             }
         }
@@ -287,17 +500,15 @@ impl World {
             y = 2 + (position.y as usize) * 5;
             for i in x..x + 2 {
                 for j in y..y + 2 {
-                    canvas.draw(
-                        &graphics::Quad,
-                        graphics::DrawParam::new()
-                            .dest_rect(graphics::Rect::new_i32(
-                                (i as i32 + 360) * 2 as i32,
-                                (j as i32 + 2) * 2 as i32,
-                                2,
-                                2,
-                            ))
-                            .color(dungeon_indicator),
-                    )
+                    hud.push_rect(
+                        graphics::Rect::new_i32(
+                            (i as i32 + 360) * 2 as i32,
+                            (j as i32 + 2) * 2 as i32,
+                            2,
+                            2,
+                        ),
+                        dungeon_indicator,
+                    );
                 }
             }
         }
@@ -309,76 +520,224 @@ impl World {
         //Make square at that specific position
         for i in x..x + 2 {
             for j in y..y + 2 {
-                canvas.draw(
-                    &graphics::Quad,
-                    graphics::DrawParam::new()
-                        .dest_rect(graphics::Rect::new_i32(
-                            (i as i32 + 360) * 2 as i32,
-                            (j as i32 + 2) * 2 as i32,
-                            2,
-                            2,
-                        ))
-                        .color(player_indicator),
-                )
+                hud.push_rect(
+                    graphics::Rect::new_i32(
+                        (i as i32 + 360) * 2 as i32,
+                        (j as i32 + 2) * 2 as i32,
+                        2,
+                        2,
+                    ),
+                    player_indicator,
+                );
             }
         }
     }
 
+    // The color of the ground in a world, which is whatever isn't covered by terrain
+    pub fn floor_color(&self, world_pos: Position) -> [f32; 4] {
+        if world_pos == FINAL_BOSS_ROOM {
+            tile::BOSS_FLOOR
+        } else if BOSS_ROOMS.contains(&world_pos) {
+            tile::FLOOR
+        } else {
+            self.biomes[world_pos.y][world_pos.x].floor()
+        }
+    }
+
+    // A world counts as visited once the player has seen any part of it
+    pub fn is_visited(&self, world_pos: Position) -> bool {
+        !self.explored_map[world_pos.y][world_pos.x].is_empty()
+    }
+
+    //Draws a full screen map of the board, where every tile is shrunk down to a couple of pixels.
+    //Only the parts of the visited rooms that the player has actually seen are shown
+    pub fn draw_full_map(&self, ctx: &mut Context, canvas: &mut graphics::Canvas) {
+        let pos = Vec2::new(FULL_MAP_ORIGIN.0, FULL_MAP_ORIGIN.1 - 32.0);
+        canvas.draw(
+            &*(graphics::Text::new("World Map [Tab]").set_scale(24.)),
+            graphics::DrawParam::from(pos).color(graphics::Color::WHITE),
+        );
+
+        // Build one pixel per board tile, then scale the whole image up in a single draw
+        let (width, height) = (BOARD_SIZE.0 as usize, BOARD_SIZE.1 as usize);
+        let mut pixels = vec![0u8; width * height * 4];
+        for board_y in 0..height {
+            for board_x in 0..width {
+                let world_pos = Position::new(
+                    board_x / WORLD_SIZE.0 as usize,
+                    board_y / WORLD_SIZE.1 as usize,
+                );
+                let loc = Position::new(
+                    board_x % WORLD_SIZE.0 as usize,
+                    board_y % WORLD_SIZE.1 as usize,
+                );
+                let color = if !self.is_visited(world_pos) {
+                    FULL_MAP_HIDDEN
+                } else if !self.explored_map[world_pos.y][world_pos.x].contains(&loc) {
+                    FULL_MAP_UNEXPLORED
+                } else {
+                    *self.terrain_map[world_pos.y][world_pos.x]
+                        .get(&loc)
+                        .unwrap_or(&self.floor_color(world_pos))
+                };
+                let index = (board_y * width + board_x) * 4;
+                for channel in 0..3 {
+                    pixels[index + channel] = (color[channel] * 255.0) as u8;
+                }
+                pixels[index + 3] = 255;
+            }
+        }
+        let image = graphics::Image::from_pixels(
+            ctx,
+            &pixels,
+            graphics::ImageFormat::Rgba8UnormSrgb,
+            width as u32,
+            height as u32,
+        );
+        canvas.set_sampler(graphics::Sampler::nearest_clamp());
+        canvas.draw(
+            &image,
+            graphics::DrawParam::new()
+                .dest(Vec2::new(FULL_MAP_ORIGIN.0, FULL_MAP_ORIGIN.1))
+                .scale(Vec2::splat(FULL_MAP_TILE_PIXELS)),
+        );
+
+        //Mark what is left to do in each visited room
+        let room_pixels = WORLD_SIZE.0 as f32 * FULL_MAP_TILE_PIXELS;
+        for world_y in 0..(BOARD_SIZE.1 / WORLD_SIZE.1) as usize {
+            for world_x in 0..(BOARD_SIZE.0 / WORLD_SIZE.0) as usize {
+                let world_pos = Position::new(world_x, world_y);
+                if !self.is_visited(world_pos) {
+                    continue;
+                }
+                let corner = Vec2::new(
+                    FULL_MAP_ORIGIN.0 + world_x as f32 * room_pixels,
+                    FULL_MAP_ORIGIN.1 + world_y as f32 * room_pixels,
+                );
+                let enemies_left = self.enemies_map[world_y][world_x].len();
+                let is_boss_room = BOSS_ROOMS.contains(&world_pos);
+                let boss_defeated = self.boss_defeated[world_y][world_x];
+                if enemies_left == 0 && (!is_boss_room || boss_defeated) {
+                    //Thin frame around the room
+                    for (x, y, w, h) in [
+                        (0.0, 0.0, room_pixels, 2.0), //top
+                        (0.0, room_pixels - 2.0, room_pixels, 2.0), //bottom
+                        (0.0, 0.0, 2.0, room_pixels), //left
+                        (room_pixels - 2.0, 0.0, 2.0, room_pixels), //right
+                    ] {
+                        canvas.draw(
+                            &graphics::Quad,
+                            graphics::DrawParam::new()
+                                .dest_rect(graphics::Rect::new(corner.x + x, corner.y + y, w, h))
+                                .color(FULL_MAP_CLEARED),
+                        )
+                    }
+                } else if enemies_left > 0 {
+                    canvas.draw(
+                        &*(graphics::Text::new(format!("{}", enemies_left)).set_scale(16.)),
+                        graphics::DrawParam::from(corner + Vec2::new(4.0, 4.0))
+                            .color(graphics::Color::WHITE),
+                    );
+                }
+                if is_boss_room {
+                    let (label, color) = if boss_defeated {
+                        ("Defeated", FULL_MAP_BOSS_DEFEATED)
+                    } else {
+                        ("Boss", FULL_MAP_BOSS)
+                    };
+                    canvas.draw(
+                        &*(graphics::Text::new(label).set_scale(16.)),
+                        graphics::DrawParam::from(corner + Vec2::new(4.0, room_pixels - 20.0))
+                            .color(color),
+                    );
+                } else {
+                    canvas.draw(
+                        &*(graphics::Text::new(self.biomes[world_y][world_x].name()).set_scale(16.)),
+                        graphics::DrawParam::from(corner + Vec2::new(4.0, room_pixels - 20.0))
+                            .color(graphics::Color::WHITE),
+                    );
+                }
+            }
+        }
+
+        //Player dot, slightly bigger than a tile so it stands out
+        let player_x = self.world_position.x as f32 * WORLD_SIZE.0 as f32 + self.player.pos.x as f32;
+        let player_y = self.world_position.y as f32 * WORLD_SIZE.1 as f32 + self.player.pos.y as f32;
+        canvas.draw(
+            &graphics::Quad,
+            graphics::DrawParam::new()
+                .dest_rect(graphics::Rect::new(
+                    FULL_MAP_ORIGIN.0 + player_x * FULL_MAP_TILE_PIXELS - FULL_MAP_TILE_PIXELS,
+                    FULL_MAP_ORIGIN.1 + player_y * FULL_MAP_TILE_PIXELS - FULL_MAP_TILE_PIXELS,
+                    FULL_MAP_TILE_PIXELS * 3.0,
+                    FULL_MAP_TILE_PIXELS * 3.0,
+                ))
+                .color([0.9, 0.1, 0.1, 1.0]),
+        );
+    }
+
     //This function draws the whole entire world that is seen by the player
-    pub fn draw(&mut self, canvas: &mut graphics::Canvas, rng: &mut ChaCha8Rng) {
+    pub fn draw(
+        &mut self,
+        ctx: &Context,
+        canvas: &mut graphics::Canvas,
+        camera: &Camera,
+        renderer: &mut Renderer,
+    ) {
+        renderer.viewport.clip(canvas);
+        //Neighbouring worlds only end up on screen with the continuous camera
+        for world_pos in camera.worlds_in_view() {
+            if world_pos != self.world_position {
+                canvas.set_screen_coordinates(
+                    renderer.viewport.screen_coordinates(camera.offset(world_pos)),
+                );
+                self.draw_neighbour(ctx, canvas, world_pos, renderer);
+            }
+        }
+        canvas.set_screen_coordinates(
+            renderer.viewport.screen_coordinates(camera.offset(self.world_position)),
+        );
+
         //Draw lasers if in boss room
         if BOSS_ROOMS.contains(&self.world_position) {
             for index in 0..self.bosses.len() {
                 if self.bosses[index].world_position == self.world_position {
-                    Boss::draw_boss_stuff(self, canvas, index, rng);
+                    Boss::draw_boss_stuff(self, ctx, canvas, index);
                 }
             }
         }
         Enemy::draw_bomber_explosion(self, canvas);
 
-        //Draw the black bar on top that has the health/energy indicators
-        for i in 0..WORLD_SIZE.0 {
-            for j in 0..UNIVERSAL_OFFSET {
-                canvas.draw(
-                    &graphics::Quad,
-                    graphics::DrawParam::new()
-                        .dest_rect(graphics::Rect::new_i32(
-                            (i) as i32 * TILE_SIZE.0 as i32,
-                            (j) as i32 * TILE_SIZE.1 as i32,
-                            TILE_SIZE.0 as i32,
-                            TILE_SIZE.1 as i32,
-                        ))
-                        .color([1.0, 1.0, 1.0, 1.0]),
-                )
-            }
-        }
-
-        //Draw health and energy indicators
-        self.player.draw_health(canvas);
-        self.player.draw_energy(canvas);
-        self.draw_world_map(canvas);
-
         //Draw every pixel that is contained in the terrain HashMap
-        if !self.in_blackout {
-            let curr_world_terrain_map =
-                &self.terrain_map[self.world_position.y][self.world_position.x];
-            for (loc, color) in curr_world_terrain_map {
-                canvas.draw(
-                    &graphics::Quad,
-                    graphics::DrawParam::new()
-                        .dest_rect(graphics::Rect::new_i32(
-                            loc.x as i32 * TILE_SIZE.0 as i32,
-                            (loc.y as i32 + UNIVERSAL_OFFSET as i32) * TILE_SIZE.1 as i32,
-                            TILE_SIZE.0 as i32,
-                            TILE_SIZE.1 as i32,
-                        ))
-                        .color(Self::related_color(rng, *color)),
-                )
-            }
+        if !self.encounter().in_blackout {
+            renderer.draw_terrain(
+                ctx,
+                canvas,
+                self.world_position,
+                &self.terrain_map[self.world_position.y][self.world_position.x],
+                self.terrain_generation,
+            );
         }
 
         //Draw every pixel that is contained in the entity HashMap
+        //Enemies and projectiles only know which way they face through their own lists
+        let mut facing = HashMap::new();
+        for enemy in &self.enemies_map[self.world_position.y][self.world_position.x] {
+            for pos in &enemy.pos {
+                facing.insert(*pos, enemy.direction);
+            }
+        }
+        for projectile in &self.projectiles {
+            if projectile.world_pos == self.world_position {
+                facing.insert(projectile.pos, projectile.direction);
+            }
+        }
+        facing.insert(self.player.pos, self.player.direction);
         let curr_world_entity_map = &self.entity_map[self.world_position.y][self.world_position.x];
+        let mut batch = TileBatch::new(ctx, renderer.tileset());
+        if !self.encounter().in_blackout {
+            Wing::draw_keys(self, &mut batch);
+        }
         for (loc, color) in curr_world_entity_map {
             let mut color = color.0;
             if color == tile::PLAYER {
@@ -390,51 +749,177 @@ impl World {
                     tile::PLAYER_INVISIBLE
                 }
             }
-            if !self.in_blackout || color == tile::PLAYER || color == tile::PLAYER_INVISIBLE {
-                canvas.draw(
-                    &graphics::Quad,
-                    graphics::DrawParam::new()
-                        .dest_rect(graphics::Rect::new_i32(
-                            (loc.x as i32 * TILE_SIZE.0 as i32) as i32,
-                            ((loc.y + UNIVERSAL_OFFSET as usize) as i32) * TILE_SIZE.1 as i32,
-                            // (loc.x - (self.world_position.x * WORLD_SIZE.0 as usize)) as i32
-                            //     * TILE_SIZE.0 as i32,
-                            // (loc.y - (self.world_position.y * WORLD_SIZE.1 as usize)
-                            //     + UNIVERSAL_OFFSET as usize) as i32
-                            //     * TILE_SIZE.1 as i32,
-                            TILE_SIZE.0 as i32,
-                            TILE_SIZE.1 as i32,
-                        ))
-                        .color(color),
-                )
+            let is_player = color == tile::PLAYER
+                || color == tile::PLAYER_INVISIBLE
+                || color == tile::PLAYER_STUNNED;
+            if is_player || (!self.encounter().in_blackout && self.visible_tiles.contains(loc)) {
+                let direction = *facing.get(loc).unwrap_or(&Direction::North);
+                batch.push_facing(*loc, color, direction);
             }
         }
+        batch.finish(canvas);
+        if renderer.show_health_bars && !self.encounter().in_blackout {
+            self.draw_health_bars(ctx, canvas);
+        }
 
         //Draw every pixel that is contained in the terrain HashMap
-        if !self.in_blackout {
+        if !self.encounter().in_blackout {
             let curr_world_atmosphere_map =
                 &self.atmosphere_map[self.world_position.y][self.world_position.x];
+            let mut batch = TileBatch::new(ctx, renderer.tileset());
             for (loc, color) in curr_world_atmosphere_map {
-                canvas.draw(
-                    &graphics::Quad,
-                    graphics::DrawParam::new()
-                        .dest_rect(graphics::Rect::new_i32(
-                            loc.x as i32 * TILE_SIZE.0 as i32,
-                            (loc.y as i32 + UNIVERSAL_OFFSET as i32) * TILE_SIZE.1 as i32,
-                            TILE_SIZE.0 as i32,
-                            TILE_SIZE.1 as i32,
-                        ))
-                        .color(*color),
-                )
+                batch.push(*loc, *color);
             }
+            batch.finish(canvas);
             if BOSS_ROOMS.contains(&self.world_position) {
                 for index in 0..self.bosses.len() {
                     if self.bosses[index].world_position == self.world_position {
-                        Boss::draw_boss(self, canvas, index);
+                        Boss::draw_boss(self, ctx, canvas, index, renderer.tileset());
+                        Boss::draw_telegraphs(self, canvas, index);
                     }
                 }
             }
-        }  
+            self.draw_fog(ctx, canvas, self.world_position);
+            Player::draw_targeting(self, ctx, canvas);
+            for number in &self.damage_numbers {
+                if number.world_pos == self.world_position
+                    && self.visible_tiles.contains(&number.tile())
+                {
+                    number.draw(canvas);
+                }
+            }
+        }
+
+        //The bar on top isn't part of the world, so it doesn't move with the camera. It is drawn
+        //last so it covers any of the world that scrolled up behind it
+        canvas.set_default_scissor_rect();
+        renderer.viewport.apply(canvas);
+        let mut hud = TileBatch::new(ctx, None);
+        //Draw the white bar on top that has the health/energy indicators
+        hud.push_rect(
+            graphics::Rect::new(
+                0.0,
+                0.0,
+                WORLD_SIZE.0 as f32 * TILE_SIZE.0 as f32,
+                UNIVERSAL_OFFSET as f32 * TILE_SIZE.1 as f32,
+            ),
+            [1.0, 1.0, 1.0, 1.0],
+        );
+
+        //Draw health and energy indicators
+        self.player.draw_health(&mut hud);
+        self.player.draw_energy(&mut hud);
+        self.draw_world_map(&mut hud);
+        self.draw_boss_health_bar(&mut hud);
+        hud.finish(canvas);
+        self.player.draw_ability_bar(ctx, canvas, &self.balance);
+        Wing::draw_hud(self, canvas);
+    }
+
+    //Draws a small bar over every enemy the player can see, as wide as the enemy, showing how much
+    //of its health is left
+    fn draw_health_bars(&self, ctx: &Context, canvas: &mut graphics::Canvas) {
+        let mut bars = TileBatch::new(ctx, None);
+        for enemy in &self.enemies_map[self.world_position.y][self.world_position.x] {
+            if !enemy.pos.iter().any(|pos| self.visible_tiles.contains(pos)) {
+                continue;
+            }
+            let left = enemy.pos.iter().map(|pos| pos.x).min().unwrap_or(0);
+            let right = enemy.pos.iter().map(|pos| pos.x).max().unwrap_or(0);
+            let top = enemy.pos.iter().map(|pos| pos.y).min().unwrap_or(0);
+            let x = left as f32 * TILE_SIZE.0 as f32;
+            let y = (top as f32 + UNIVERSAL_OFFSET as f32) * TILE_SIZE.1 as f32 - HEALTH_BAR_HEIGHT;
+            let width = (right - left + 1) as f32 * TILE_SIZE.0 as f32;
+            let fraction = enemy.health() as f32 / enemy.max_health().max(1) as f32;
+            bars.push_rect(
+                graphics::Rect::new(x, y, width, HEALTH_BAR_HEIGHT),
+                HEALTH_BAR_BACKGROUND,
+            );
+            bars.push_rect(
+                graphics::Rect::new(x, y, width * fraction, HEALTH_BAR_HEIGHT),
+                HEALTH_BAR_FILL,
+            );
+        }
+        bars.finish(canvas);
+    }
+
+    //Draws the health of the boss in the player's room across the whole bar on top
+    fn draw_boss_health_bar(&self, hud: &mut TileBatch) {
+        let boss = match self
+            .bosses
+            .iter()
+            .find(|boss| boss.world_position == self.world_position)
+        {
+            Some(boss) => boss,
+            None => return,
+        };
+        let width = WORLD_SIZE.0 as f32 * TILE_SIZE.0 as f32 - 2.0 * BOSS_BAR_MARGIN;
+        let fraction = boss.health as f32 / boss.max_health(self.difficulty, &self.balance.bosses).max(1) as f32;
+        hud.push_rect(
+            graphics::Rect::new(BOSS_BAR_MARGIN, BOSS_BAR_Y, width, BOSS_BAR_HEIGHT),
+            BOSS_BAR_BACKGROUND,
+        );
+        hud.push_rect(
+            graphics::Rect::new(BOSS_BAR_MARGIN, BOSS_BAR_Y, width * fraction, BOSS_BAR_HEIGHT),
+            BOSS_BAR_FILL,
+        );
+    }
+
+    //Draws a world next to the one the player is in. Nothing there can be seen right now, so it
+    //is only what the player remembers of it
+    fn draw_neighbour(
+        &self,
+        ctx: &Context,
+        canvas: &mut graphics::Canvas,
+        world_pos: Position,
+        renderer: &mut Renderer,
+    ) {
+        canvas.draw(
+            &graphics::Quad,
+            graphics::DrawParam::new()
+                .dest_rect(graphics::Rect::new_i32(
+                    0,
+                    UNIVERSAL_OFFSET as i32 * TILE_SIZE.1 as i32,
+                    WORLD_SIZE.0 as i32 * TILE_SIZE.0 as i32,
+                    WORLD_SIZE.1 as i32 * TILE_SIZE.1 as i32,
+                ))
+                .color(self.floor_color(world_pos)),
+        );
+        renderer.draw_terrain(
+            ctx,
+            canvas,
+            world_pos,
+            &self.terrain_map[world_pos.y][world_pos.x],
+            self.terrain_generation,
+        );
+        let mut batch = TileBatch::new(ctx, renderer.tileset());
+        for (loc, color) in &self.atmosphere_map[world_pos.y][world_pos.x] {
+            batch.push(*loc, *color);
+        }
+        batch.finish(canvas);
+        self.draw_fog(ctx, canvas, world_pos);
+    }
+
+    //Covers every tile the player can't see right now, tiles that have never been seen are
+    //covered completely and the ones the player remembers are just dimmed
+    fn draw_fog(&self, ctx: &Context, canvas: &mut graphics::Canvas, world_pos: Position) {
+        let explored = &self.explored_map[world_pos.y][world_pos.x];
+        let mut fog = TileBatch::new(ctx, None);
+        for x in 0..WORLD_SIZE.0 as usize {
+            for y in 0..WORLD_SIZE.1 as usize {
+                let loc = Position::new(x, y);
+                if world_pos == self.world_position && self.visible_tiles.contains(&loc) {
+                    continue;
+                }
+                let color = if explored.contains(&loc) {
+                    tile::FOG_REMEMBERED
+                } else {
+                    tile::FOG_UNEXPLORED
+                };
+                fog.push(loc, color);
+            }
+        }
+        fog.finish(canvas);
     }
 
     // this function just returns whether a set of coordinates are within the bounds of the dynamic
@@ -584,6 +1069,9 @@ impl World {
                                 world.player.pos = Position::new(new_position.0.x, 0);
                             }
                         }
+                        world.events.push(GameEvent::RoomEntered {
+                            world_pos: world.world_position,
+                        });
                     } else {
                         if Player::can_travel_to(world, new_position) {
                             Self::update_position(world, world.player.pos, new_position);
@@ -591,12 +1079,14 @@ impl World {
                         }
                     }
 
-                    Self::toggle_doors(
+                    if Self::toggle_doors(
                         &mut world.terrain_map,
                         world.world_position,
                         world.player.pos,
                         world.boss_defeated,
-                    );
+                    ) {
+                        world.terrain_changed();
+                    }
                     return true;
                 }
 
@@ -618,14 +1108,14 @@ impl World {
                         if world.enemies_map[world.world_position.y][world.world_position.x][index].pos.contains(&new_position.0)
                             && new_position.1 == world.enemies_map[world.world_position.y][world.world_position.x][index].world_pos
                         {
-                            world.enemies_map[world.world_position.y][world.world_position.x][index].damage(world.projectiles[i].damage);
+                            world.enemies_map[world.world_position.y][world.world_position.x][index].damage(world.projectiles[i].damage, Actor::PlayerAttack(world.projectiles[i].attack_name()));
                             return false; //Will delete the projectile that hits the enemy
                         }
                     }
                     if BOSS_ROOMS.contains(&new_position.1) {
                         let hit_info = Boss::can_hit_boss(world, new_position.0, new_position.1);
                         if hit_info.0 && hit_info.1 {
-                            Boss::damage(world, PLAYER_PROJECTILE_DAMAGE, new_position.1);
+                            Boss::damage(world, world.balance.player.boss_shot_damage, new_position.1, Actor::PlayerAttack(world.projectiles[i].attack_name()));
                             return false;
                         } else if hit_info.1 {
                             return false;
@@ -805,122 +1295,41 @@ impl World {
         }
     }
 
-    // generates water tiles around the map
-    pub fn gen_lake(
-        rng: &mut ChaCha8Rng,
-        terrain_map: &mut [[HashMap<Position, [f32; 4]>; (BOARD_SIZE.1 / WORLD_SIZE.1) as usize];
-                 (BOARD_SIZE.0 / WORLD_SIZE.0) as usize],
-    ) {
-        for i in 0..7 {
-            for j in 0..7 {
-                let mut lakes_added = 0;
-                while lakes_added < LAKES_PER_WORLD {
-                    let x = random::rand_range(rng, 5, WORLD_SIZE.0); // random x coordinate
-                    let y = random::rand_range(rng, 5, WORLD_SIZE.1); // random y coordinate
-
-                    let mut lake: HashMap<Position, [f32; 4]> = HashMap::new();
-                    Self::gen_lake_helper(
-                        rng,
-                        i * WORLD_SIZE.0 + x,
-                        j * WORLD_SIZE.1 + y,
-                        0,
-                        terrain_map,
-                        &mut lake,
-                    ); // new lake centered at (x, y)
-                    if lake.len() > 0 {
-                        Self::combine_into_terrain(terrain_map, &lake);
-                        lakes_added += 1;
-                    }
-                }
-            }
-        }
-    }
-
-    // Recursively generates lakes -- floodfill-esque idea around the center, but expansion is
-    // limited probabilistically (probability of expansion decreases as we range further from the
-    // center)
-    fn gen_lake_helper(
-        rng: &mut ChaCha8Rng,
-        x: i16,
-        y: i16,
-        dist: i16,
-        terrain_map: &mut [[HashMap<Position, [f32; 4]>; (BOARD_SIZE.1 / WORLD_SIZE.1) as usize];
-                 (BOARD_SIZE.0 / WORLD_SIZE.0) as usize],
-        lake: &mut HashMap<Position, [f32; 4]>,
-    ) {
-        let pos = Position::new(x as usize, y as usize);
-        if !Self::has_adjacent_terrain(x as usize, y as usize, &terrain_map) {
-            // sets curr tile to water
-            let world_loc = Position::new((x / WORLD_SIZE.0) as usize, (y / WORLD_SIZE.0) as usize);
-
-            let tile: [f32; 4];
-            if BOSS_ROOMS.contains(&world_loc) {
-                tile = tile::LAVA;
-            } else {
-                tile = tile::WATER;
-            }
-
-            if !lake.contains_key(&pos) {
-                lake.insert(pos, tile);
-            }
-        } else {
-            return;
-        }
-
-        const DIRECTIONS: [[i16; 2]; 4] = [[0, 1], [0, -1], [1, 0], [-1, 0]]; // orthogonal dirs
-        for dir in DIRECTIONS {
-            // for each tile in an orthogonal direction
-            // With certain probability, continue expanding lake in that direction
-            if Self::prob_expand_lake(rng, dist) {
-                let i = x + dir[0];
-                let j = y + dir[1];
-                // if in bounds, recursively call fn on adjacent tile (draws WATER at that tile)
-                if i >= 0 && i < BOARD_SIZE.0 && j >= 0 && j < BOARD_SIZE.1 {
-                    Self::gen_lake_helper(rng, i, j, dist + 1, terrain_map, lake);
-                }
-            }
-        }
-    }
-
-    // Gets probability of continuing to expand lake outwards
-    fn prob_expand_lake(rng: &mut ChaCha8Rng, dist: i16) -> bool {
-        random::bernoulli(rng, 1. - 0.15 * (dist as f32))
-    }
-
-    // adds a little variability to lake color
-    pub fn related_color(rng: &mut ChaCha8Rng, color: [f32; 4]) -> [f32; 4] {
+    // adds a little variability to lake color. The variation comes from the tile's position
+    // rather than the rng, so a tile always looks the same and drawing never changes the game
+    pub fn related_color(world_pos: Position, pos: Position, color: [f32; 4]) -> [f32; 4] {
         if color == tile::WATER {
             const MAX_DIFF: f32 = 0.05;
             return [
-                color[0] + random::rand_fraction(rng) * 2.0 * MAX_DIFF - MAX_DIFF,
-                color[1] + random::rand_fraction(rng) * 2.0 * MAX_DIFF - MAX_DIFF,
-                color[2] + random::rand_fraction(rng) * 2.0 * MAX_DIFF - MAX_DIFF,
+                color[0] + random::tile_noise(world_pos, pos, 0) * 2.0 * MAX_DIFF - MAX_DIFF,
+                color[1] + random::tile_noise(world_pos, pos, 1) * 2.0 * MAX_DIFF - MAX_DIFF,
+                color[2] + random::tile_noise(world_pos, pos, 2) * 2.0 * MAX_DIFF - MAX_DIFF,
                 color[3],
             ];
         } else if color == tile::LAVA {
             const MAX_DIFF_1: f32 = 0.01;
             const MAX_DIFF_2: f32 = 0.10;
             return [
-                color[0] + random::rand_fraction(rng) * 2.0 * MAX_DIFF_1 - MAX_DIFF_1,
-                color[1] + random::rand_fraction(rng) * 2.0 * MAX_DIFF_2 - MAX_DIFF_2,
-                color[2] + random::rand_fraction(rng) * 2.0 * MAX_DIFF_1 - MAX_DIFF_1,
+                color[0] + random::tile_noise(world_pos, pos, 0) * 2.0 * MAX_DIFF_1 - MAX_DIFF_1,
+                color[1] + random::tile_noise(world_pos, pos, 1) * 2.0 * MAX_DIFF_2 - MAX_DIFF_2,
+                color[2] + random::tile_noise(world_pos, pos, 2) * 2.0 * MAX_DIFF_1 - MAX_DIFF_1,
                 color[3],
             ];
         } else if color == tile::GRASS {
             const MAX_DIFF_1: f32 = 0.01;
             const MAX_DIFF_2: f32 = 0.10;
             return [
-                color[0] + random::rand_fraction(rng) * 2.0 * MAX_DIFF_1 - MAX_DIFF_1,
-                color[1] + random::rand_fraction(rng) * 2.0 * MAX_DIFF_1 - MAX_DIFF_1,
-                color[2] + random::rand_fraction(rng) * 2.0 * MAX_DIFF_2 - MAX_DIFF_2,
+                color[0] + random::tile_noise(world_pos, pos, 0) * 2.0 * MAX_DIFF_1 - MAX_DIFF_1,
+                color[1] + random::tile_noise(world_pos, pos, 1) * 2.0 * MAX_DIFF_1 - MAX_DIFF_1,
+                color[2] + random::tile_noise(world_pos, pos, 2) * 2.0 * MAX_DIFF_2 - MAX_DIFF_2,
                 color[3],
             ];
         }
         return color;
     }
 
-    //TODO: make faster, makes the game really slow rn
-    fn gen_outer_boss_walls(
+    // the ring of walls around every mini boss room, with a gap in the middle of each side
+    pub fn gen_outer_boss_walls(
         terrain_map: &mut [[HashMap<Position, [f32; 4]>; (BOARD_SIZE.1 / WORLD_SIZE.1) as usize];
                  (BOARD_SIZE.0 / WORLD_SIZE.0) as usize],
     ) {
@@ -990,82 +1399,13 @@ impl World {
         // terrain_positions.remove(&Position::new((WORLD_SIZE.1 - 1) as usize, (WORLD_SIZE.0 + WORLD_SIZE.0 / 2) as usize));
     }
 
-    pub fn gen_mountain(
-        rng: &mut ChaCha8Rng,
-        terrain_map: &mut [[HashMap<Position, [f32; 4]>; (BOARD_SIZE.1 / WORLD_SIZE.1) as usize];
-                 (BOARD_SIZE.0 / WORLD_SIZE.0) as usize],
-    ) {
-        let mut mountains_added = 0;
-        while mountains_added < TOTAL_MOUNTAINS {
-            let x = random::rand_range(rng, 5, BOARD_SIZE.0); // random x coordinate
-            let y = random::rand_range(rng, 5, BOARD_SIZE.1); // random y coordinate
-
-            let world_loc = Position::new((x / WORLD_SIZE.0) as usize, (y / WORLD_SIZE.0) as usize);
-
-            if BOSS_ROOMS.contains(&world_loc) {
-                continue;
-            }
-
-            let mut mountain: HashMap<Position, [f32; 4]> = HashMap::new();
-            Self::gen_mountain_helper(rng, x, y, 0, terrain_map, &mut mountain); // new lake centered at (x, y)
-            if mountain.len() > 0 {
-                Self::combine_into_terrain(terrain_map, &mountain);
-                mountains_added += 1;
-            }
-        }
-    }
-
-    // Recursively generates lakes -- floodfill-esque idea around the center, but expansion is
-    // limited probabilistically (probability of expansion decreases as we range further from the
-    // center)
-    fn gen_mountain_helper(
-        rng: &mut ChaCha8Rng,
-        x: i16,
-        y: i16,
-        dist: i16,
-        terrain_map: &mut [[HashMap<Position, [f32; 4]>; (BOARD_SIZE.1 / WORLD_SIZE.1) as usize];
-                 (BOARD_SIZE.0 / WORLD_SIZE.0) as usize],
-        mountain: &mut HashMap<Position, [f32; 4]>,
-    ) {
-        let pos = Position::new(x as usize, y as usize);
-        if !Self::has_adjacent_terrain(x as usize, y as usize, &terrain_map) {
-            // sets curr tile to water
-            let tile: [f32; 4] = tile::MOUNTAIN[min(4, (dist + 2) / 3) as usize];
-
-            if !mountain.contains_key(&pos) {
-                mountain.insert(pos, tile);
-            }
-        } else {
-            return;
-        }
-
-        const DIRECTIONS: [[i16; 2]; 4] = [[0, 1], [0, -1], [1, 0], [-1, 0]]; // orthogonal dirs
-        for dir in DIRECTIONS {
-            // for each tile in an orthogonal direction
-            // With certain probability, continue expanding lake in that direction
-            if Self::prob_expand_mountain(rng, dist) {
-                let i = x + dir[0];
-                let j = y + dir[1];
-                // if in bounds, recursively call fn on adjacent tile (draws WATER at that tile)
-                if i >= 0 && i < BOARD_SIZE.0 && j >= 0 && j < BOARD_SIZE.1 {
-                    Self::gen_mountain_helper(rng, i, j, dist + 1, terrain_map, mountain);
-                }
-            }
-        }
-    }
-
-    // Gets probability of continuing to expand lake outwards
-    fn prob_expand_mountain(rng: &mut ChaCha8Rng, dist: i16) -> bool {
-        random::bernoulli(rng, 1. - 0.10 * (dist as f32))
-    }
-
     pub fn toggle_doors(
         terrain_map: &mut [[HashMap<Position, [f32; 4]>; (BOARD_SIZE.0 / WORLD_SIZE.0) as usize];
                  (BOARD_SIZE.1 / WORLD_SIZE.1) as usize],
         world_loc: Position,
         loc: Position,
         boss_defeated: [[bool; 7]; 7],
-    ) {
+    ) -> bool {
         let positions: [[i16; 4]; 8] = [
             [1, 0, 0, WORLD_SIZE.1 / 2 - 1],
             [1, 0, 0, WORLD_SIZE.1 / 2],
@@ -1076,6 +1416,8 @@ impl World {
             [0, -1, WORLD_SIZE.0 / 2 - 1, WORLD_SIZE.1 - 1],
             [0, -1, WORLD_SIZE.0 / 2, WORLD_SIZE.1 - 1],
         ];
+        // whether any door was opened or closed
+        let mut changed = false;
 
         if BOSS_ROOMS.contains(&world_loc)
             && loc.x != 0
@@ -1092,9 +1434,11 @@ impl World {
                 let wall_pos = Position::new(y, x);
                 if !terrain_map[world_loc.y][world_loc.x].contains_key(&wall_pos) {
                     terrain_map[world_loc.y][world_loc.x].insert(wall_pos, tile::WALL);
+                    changed = true;
                 }
                 if !terrain_map[world_y][world_x].contains_key(&wall_pos) {
                     terrain_map[world_y][world_x].insert(wall_pos, tile::WALL);
+                    changed = true;
                 }
             }
         } else if boss_defeated[world_loc.y][world_loc.x] {
@@ -1106,9 +1450,11 @@ impl World {
                 let wall_pos = Position::new(y, x);
                 if terrain_map[world_loc.y][world_loc.x].contains_key(&wall_pos) {
                     terrain_map[world_loc.y][world_loc.x].remove(&wall_pos);
+                    changed = true;
                 }
                 if terrain_map[world_y][world_x].contains_key(&wall_pos) {
                     terrain_map[world_y][world_x].remove(&wall_pos);
+                    changed = true;
                 }
             }
                    } else if boss_defeated[1][1] && boss_defeated[1][5] && boss_defeated[5][1] && boss_defeated[5][5] {
@@ -1120,9 +1466,11 @@ impl World {
                            let wall_pos = Position::new(y, x);
                            if terrain_map[3][3].contains_key(&wall_pos) {
                                terrain_map[3][3].remove(&wall_pos);
+                               changed = true;
                            }
                            if terrain_map[world_x][world_y].contains_key(&wall_pos) {
                                terrain_map[world_x][world_y].remove(&wall_pos);
+                               changed = true;
                            }
                        }
                    } else {
@@ -1134,76 +1482,14 @@ impl World {
                            let wall_pos = Position::new(y, x);
                            if !terrain_map[3][3].contains_key(&wall_pos) {
                                terrain_map[3][3].insert(wall_pos, tile::WALL);
+                               changed = true;
                            }
                            if !terrain_map[world_x][world_y].contains_key(&wall_pos) {
                                terrain_map[world_x][world_y].insert(wall_pos, tile::WALL);
+                               changed = true;
                            }
                        }
         }
-    }
-
-    fn has_adjacent_terrain(
-        x: usize,
-        y: usize,
-        terrain_map: &[[HashMap<Position, [f32; 4]>; (BOARD_SIZE.0 / WORLD_SIZE.0) as usize];
-             (BOARD_SIZE.1 / WORLD_SIZE.1) as usize],
-    ) -> bool {
-        if x == 0 || x == BOARD_SIZE.0 as usize - 1 || y == 0 || y == BOARD_SIZE.1 as usize - 1 {
-            return true;
-        }
-
-        let directions: [[i16; 2]; 9] = [
-            [0, 0],
-            [1, 0],
-            [1, 1],
-            [0, 1],
-            [-1, 1],
-            [-1, 0],
-            [-1, -1],
-            [0, -1],
-            [1, -1],
-        ];
-
-        for dir in directions {
-            let other_x = x as i16 + dir[0];
-            let other_y = y as i16 + dir[1];
-
-            let world_loc = Position::new(
-                (other_x / WORLD_SIZE.0) as usize,
-                (other_y / WORLD_SIZE.0) as usize,
-            );
-            let loc = Position::new(
-                (other_x - (WORLD_SIZE.0 * world_loc.x as i16)) as usize,
-                (other_y - (WORLD_SIZE.0 * world_loc.y as i16)) as usize,
-            );
-
-            if let Some(tile) = terrain_map[world_loc.y][world_loc.x].get(&loc) {
-                if *tile == tile::WALL {
-                    return true;
-                }
-            }
-        }
-        return false;
-    }
-
-    fn combine_into_terrain(
-        terrain_map: &mut [[HashMap<Position, [f32; 4]>; (BOARD_SIZE.0 / WORLD_SIZE.0) as usize];
-                 (BOARD_SIZE.1 / WORLD_SIZE.1) as usize],
-        other: &HashMap<Position, [f32; 4]>,
-    ) {
-        for (pos, tile) in other {
-            let world_loc = Position::new(
-                (pos.x as i16 / WORLD_SIZE.0) as usize,
-                (pos.y as i16 / WORLD_SIZE.0) as usize,
-            );
-            let loc = Position::new(
-                (pos.x as i16 - (WORLD_SIZE.0 * world_loc.x as i16)) as usize,
-                (pos.y as i16 - (WORLD_SIZE.0 * world_loc.y as i16)) as usize,
-            );
-
-            if !terrain_map[world_loc.y][world_loc.x].contains_key(&loc) {
-                terrain_map[world_loc.y][world_loc.x].insert(loc, *tile);
-            }
-        }
+        changed
     }
 }
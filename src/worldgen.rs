@@ -0,0 +1,615 @@
+use crate::{biome::Biome, river::River, terrain, wing::Wing, world::World};
+use ggez::Context;
+use rand_chacha::ChaCha8Rng;
+use std::collections::BTreeMap;
+use std::io::Read;
+
+pub const WORLD_GEN_PATH: &str = "/worldgen.ron";
+// The presets the game ships with, used whenever worldgen.ron can't be read or is broken
+const DEFAULT_WORLD_GEN: &str = include_str!("../resources/worldgen.ron");
+// The preset a new game uses until another one is picked on the title screen
+pub const DEFAULT_PRESET: &str = "default";
+
+// One thing done to a freshly made world, like growing mountains or placing the bosses. Steps only
+// get the world and the rng, so each one can be run on its own from a seeded rng
+pub trait WorldGenStep {
+    // what the step is called in worldgen.ron
+    fn name(&self) -> &'static str;
+
+    fn generate(&self, world: &mut World, rng: &mut ChaCha8Rng);
+}
+
+// Every preset in worldgen.ron, by name, in the order the title screen goes through them
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct WorldGen(pub BTreeMap<String, Preset>);
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    // run in this order on an empty world
+    pub steps: Vec<Step>,
+}
+
+// Every step a preset can use. Adding a step means adding it here and to Step::step
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+pub enum Step {
+    Biomes(Biomes),
+    FinalBossRoom,
+    BossRoomWalls,
+    Mountains(Mountains),
+    Rivers(Rivers),
+    Lakes(Lakes),
+    BiomeFeatures(BiomeFeatures),
+    Enemies(Enemies),
+    Bosses,
+    BossWings(BossWings),
+    Connect,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+// Which biome every world is, see biome.rs. Worlds stay plains without it
+pub struct Biomes {
+    // how many random worlds biomes grow out of
+    pub regions: usize,
+}
+
+// The walls and lava around the final boss in the middle of the board
+pub struct FinalBossRoom;
+
+// The ring of walls around every mini boss room, with a door in the middle of each side
+pub struct BossRoomWalls;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+// Mountains spread out anywhere but the boss rooms
+pub struct Mountains {
+    pub count: usize,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+// Rivers from one edge of the board to the other, see river.rs
+pub struct Rivers {
+    pub count: usize,
+    // tiles across, odd numbers come out exact
+    pub width: usize,
+    // bridges and fords across each river in every world it flows through
+    pub bridges: usize,
+    pub fords: usize,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+// Lakes in every world, lava ones in the boss rooms
+pub struct Lakes {
+    pub per_world: usize,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+// Patches of trees, cacti, ice and mud over the biomes that have them
+pub struct BiomeFeatures {
+    pub per_world: usize,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+// Enemies anywhere but the boss rooms, more or fewer of them depending on the difficulty
+pub struct Enemies {
+    pub count: usize,
+}
+
+// A boss in every one of the BOSS_ROOMS, fighting the way bosses.ron says
+pub struct Bosses;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+// A dungeon wing in front of every mini boss room, see wing.rs
+pub struct BossWings {
+    // locked doors on the way through each wing, as many as the way is long at most
+    pub locked_doors: usize,
+    // chasers waiting in every room of a wing but the first
+    pub guards: usize,
+}
+
+// Fills in or digs out everything the player couldn't walk to otherwise, see terrain::connect
+pub struct Connect;
+
+impl WorldGenStep for Biomes {
+    fn name(&self) -> &'static str {
+        "Biomes"
+    }
+
+    fn generate(&self, world: &mut World, rng: &mut ChaCha8Rng) {
+        Biome::gen_biomes(world, rng, self.regions);
+    }
+}
+
+impl WorldGenStep for FinalBossRoom {
+    fn name(&self) -> &'static str {
+        "FinalBossRoom"
+    }
+
+    fn generate(&self, world: &mut World, _rng: &mut ChaCha8Rng) {
+        World::gen_boss(&mut world.terrain_map);
+    }
+}
+
+impl WorldGenStep for BossRoomWalls {
+    fn name(&self) -> &'static str {
+        "BossRoomWalls"
+    }
+
+    fn generate(&self, world: &mut World, _rng: &mut ChaCha8Rng) {
+        World::gen_outer_boss_walls(&mut world.terrain_map);
+    }
+}
+
+impl WorldGenStep for Mountains {
+    fn name(&self) -> &'static str {
+        "Mountains"
+    }
+
+    fn generate(&self, world: &mut World, rng: &mut ChaCha8Rng) {
+        terrain::gen_mountains(world, rng, self.count);
+    }
+}
+
+impl WorldGenStep for Rivers {
+    fn name(&self) -> &'static str {
+        "Rivers"
+    }
+
+    fn generate(&self, world: &mut World, rng: &mut ChaCha8Rng) {
+        River::gen_rivers(
+            world,
+            rng,
+            self.count,
+            self.width,
+            self.bridges,
+            self.fords,
+        );
+    }
+}
+
+impl WorldGenStep for Lakes {
+    fn name(&self) -> &'static str {
+        "Lakes"
+    }
+
+    fn generate(&self, world: &mut World, rng: &mut ChaCha8Rng) {
+        terrain::gen_lakes(world, rng, self.per_world);
+    }
+}
+
+impl WorldGenStep for BiomeFeatures {
+    fn name(&self) -> &'static str {
+        "BiomeFeatures"
+    }
+
+    fn generate(&self, world: &mut World, rng: &mut ChaCha8Rng) {
+        Biome::gen_features(world, rng, self.per_world);
+    }
+}
+
+impl WorldGenStep for Enemies {
+    fn name(&self) -> &'static str {
+        "Enemies"
+    }
+
+    fn generate(&self, world: &mut World, rng: &mut ChaCha8Rng) {
+        World::gen_enemies(
+            rng,
+            &mut world.terrain_map,
+            &mut world.entity_map,
+            &mut world.enemies_map,
+            world.difficulty.enemy_count(self.count),
+            &world.balance.enemies,
+            &world.biomes,
+        );
+    }
+}
+
+impl WorldGenStep for Bosses {
+    fn name(&self) -> &'static str {
+        "Bosses"
+    }
+
+    fn generate(&self, world: &mut World, _rng: &mut ChaCha8Rng) {
+        World::gen_bosses(
+            &mut world.terrain_map,
+            &mut world.entity_map,
+            &mut world.bosses,
+            world.difficulty,
+            &world.balance,
+            &world.boss_scripts,
+        );
+    }
+}
+
+impl WorldGenStep for BossWings {
+    fn name(&self) -> &'static str {
+        "BossWings"
+    }
+
+    fn generate(&self, world: &mut World, rng: &mut ChaCha8Rng) {
+        Wing::gen_wings(world, rng, self.locked_doors, self.guards);
+    }
+}
+
+impl WorldGenStep for Connect {
+    fn name(&self) -> &'static str {
+        "Connect"
+    }
+
+    fn generate(&self, world: &mut World, _rng: &mut ChaCha8Rng) {
+        terrain::connect(world);
+    }
+}
+
+impl Step {
+    pub fn step(&self) -> &dyn WorldGenStep {
+        match self {
+            Step::Biomes(step) => step,
+            Step::FinalBossRoom => &FinalBossRoom,
+            Step::BossRoomWalls => &BossRoomWalls,
+            Step::Mountains(step) => step,
+            Step::Rivers(step) => step,
+            Step::Lakes(step) => step,
+            Step::BiomeFeatures(step) => step,
+            Step::Enemies(step) => step,
+            Step::Bosses => &Bosses,
+            Step::BossWings(step) => step,
+            Step::Connect => &Connect,
+        }
+    }
+}
+
+impl Preset {
+    // Runs every step on a world that has nothing in it but the player
+    pub fn generate(&self, world: &mut World, rng: &mut ChaCha8Rng) {
+        for step in &self.steps {
+            step.step().generate(world, rng);
+        }
+    }
+
+    // Steps missing or in an order that would break the game, or never let it finish generating
+    fn validate(&self, name: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        let bosses = self
+            .steps
+            .iter()
+            .filter(|step| matches!(step, Step::Bosses))
+            .count();
+        if bosses != 1 {
+            errors.push(format!("{} needs exactly one Bosses step", name));
+        }
+        let wings: Vec<usize> = (0..self.steps.len())
+            .filter(|i| matches!(self.steps[*i], Step::BossWings(_)))
+            .collect();
+        if wings.len() > 1 {
+            errors.push(format!("{} can only have one BossWings step", name));
+        }
+        // a wing is dug out of solid wall, and lakes grown afterwards could block its rooms
+        let lakes = self
+            .steps
+            .iter()
+            .rposition(|step| matches!(step, Step::Lakes(_)));
+        if let (Some(wings), Some(lakes)) = (wings.first(), lakes) {
+            if *wings < lakes {
+                errors.push(format!(
+                    "{} has to run its {} step before its {} step",
+                    name,
+                    self.steps[lakes].step().name(),
+                    self.steps[*wings].step().name()
+                ));
+            }
+        }
+        // lakes, features and enemies all depend on the biome of the world they're in
+        if let Some(biomes) = self
+            .steps
+            .iter()
+            .rposition(|step| matches!(step, Step::Biomes(_)))
+        {
+            for step in &self.steps[..biomes] {
+                if matches!(
+                    step,
+                    Step::Lakes(_) | Step::BiomeFeatures(_) | Step::Enemies(_)
+                ) {
+                    errors.push(format!(
+                        "{} has to run its {} step before its {} step",
+                        name,
+                        self.steps[biomes].step().name(),
+                        step.step().name()
+                    ));
+                }
+            }
+        }
+        // anything that changes the terrain after the last Connect could cut the player off again
+        if let Some(connect) = self
+            .steps
+            .iter()
+            .rposition(|step| matches!(step, Step::Connect))
+        {
+            for step in &self.steps[connect + 1..] {
+                if matches!(
+                    step,
+                    Step::FinalBossRoom
+                        | Step::BossRoomWalls
+                        | Step::Mountains(_)
+                        | Step::Rivers(_)
+                        | Step::Lakes(_)
+                        | Step::BiomeFeatures(_)
+                        | Step::BossWings(_)
+                ) {
+                    errors.push(format!(
+                        "{} has to run its {} step before its {} step",
+                        name,
+                        step.step().name(),
+                        self.steps[connect].step().name()
+                    ));
+                }
+            }
+        }
+        errors
+    }
+}
+
+impl Default for WorldGen {
+    fn default() -> Self {
+        ron::from_str(DEFAULT_WORLD_GEN).expect("the built in worldgen.ron is broken")
+    }
+}
+
+impl WorldGen {
+    // Falls back to the presets the game was built with if worldgen.ron is missing or broken
+    pub fn load(ctx: &Context) -> WorldGen {
+        let text = match Self::read(ctx) {
+            Some(text) => text,
+            None => {
+                println!(
+                    "Couldn't open {}, using the default world presets",
+                    WORLD_GEN_PATH
+                );
+                return WorldGen::default();
+            }
+        };
+        match Self::parse(&text) {
+            Ok(world_gen) => world_gen,
+            Err(errors) => {
+                println!(
+                    "{} is invalid, using the default world presets:",
+                    WORLD_GEN_PATH
+                );
+                for error in errors {
+                    println!("    {}", error);
+                }
+                WorldGen::default()
+            }
+        }
+    }
+
+    pub fn read(ctx: &Context) -> Option<String> {
+        let mut text = String::new();
+        ctx.fs
+            .open(WORLD_GEN_PATH)
+            .and_then(|mut file| Ok(file.read_to_string(&mut text)?))
+            .ok()?;
+        Some(text)
+    }
+
+    pub fn parse(text: &str) -> Result<WorldGen, Vec<String>> {
+        let world_gen: WorldGen = ron::from_str(text).map_err(|e| vec![e.to_string()])?;
+        let errors = world_gen.validate();
+        if errors.is_empty() {
+            Ok(world_gen)
+        } else {
+            Err(errors)
+        }
+    }
+
+    // The preset with that name, or the default one if it's gone since it was picked
+    pub fn preset(&self, name: &str) -> &Preset {
+        self.0
+            .get(name)
+            .or_else(|| self.0.get(DEFAULT_PRESET))
+            .expect("worldgen.ron is checked for a default preset when it's loaded")
+    }
+
+    // The preset after the given one, for cycling through on the title screen
+    pub fn next(&self, name: &str) -> String {
+        self.0
+            .keys()
+            .find(|key| key.as_str() > name)
+            .or_else(|| self.0.keys().next())
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_PRESET.to_string())
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if !self.0.contains_key(DEFAULT_PRESET) {
+            errors.push(format!("there is no preset called {}", DEFAULT_PRESET));
+        }
+        for (name, preset) in &self.0 {
+            errors.extend(preset.validate(name));
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        balance::Balance,
+        boss_script::BossScripts,
+        difficulty::Difficulty,
+        world::BOSS_ROOMS,
+        BOARD_SIZE,
+    };
+    use rand::SeedableRng;
+    use std::time::{Duration, Instant};
+
+    const SEEDS: [u64; 4] = [0, 1, 42, 1234];
+    // New games made from every preset by the world generation benchmark
+    const BENCH_RUNS: u32 = 20;
+
+    // The default preset uses every step there is, with the settings the game ships with
+    fn default_steps() -> Vec<Step> {
+        WorldGen::default().preset(DEFAULT_PRESET).steps.clone()
+    }
+
+    // A world with only the player in it, with just the given steps run on it
+    fn run(steps: &[&Step], seed: u64) -> World {
+        let mut world = World::empty();
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        for step in steps {
+            step.step().generate(&mut world, &mut rng);
+        }
+        world
+    }
+
+    fn find(step: fn(&Step) -> bool) -> Step {
+        default_steps().into_iter().find(step).unwrap()
+    }
+
+    #[test]
+    fn default_preset_has_every_step() {
+        assert_eq!(default_steps().len(), 11);
+        assert!(WorldGen::default().validate().is_empty());
+    }
+
+    #[test]
+    fn same_seed_same_terrain() {
+        for step in default_steps() {
+            for seed in SEEDS {
+                let (first, second) = (run(&[&step], seed), run(&[&step], seed));
+                assert!(
+                    first.terrain_map == second.terrain_map
+                        && first.entity_map == second.entity_map,
+                    "{} came out different twice with seed {}",
+                    step.step().name(),
+                    seed
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn nothing_grows_in_boss_rooms() {
+        let biomes = find(|step| matches!(step, Step::Biomes(_)));
+        for step in default_steps() {
+            if !matches!(
+                step,
+                Step::Mountains(_) | Step::Enemies(_)
+            ) {
+                continue;
+            }
+            for seed in SEEDS {
+                // which enemies spawn depends on the biome
+                let world = run(&[&biomes, &step], seed);
+                for room in BOSS_ROOMS {
+                    assert!(
+                        world.terrain_map[room.y][room.x].is_empty()
+                            && world.enemies_map[room.y][room.x].is_empty(),
+                        "{} put something in the boss room at {:?} with seed {}",
+                        step.step().name(),
+                        room,
+                        seed
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wings_have_to_come_after_lakes() {
+        let preset = Preset {
+            steps: vec![
+                Step::Bosses,
+                find(|step| matches!(step, Step::BossWings(_))),
+                find(|step| matches!(step, Step::Lakes(_))),
+            ],
+        };
+        assert_eq!(
+            preset.validate("test"),
+            vec!["test has to run its Lakes step before its BossWings step"]
+        );
+    }
+
+    #[test]
+    fn biomes_come_before_what_depends_on_them() {
+        let preset = Preset {
+            steps: vec![
+                find(|step| matches!(step, Step::Enemies(_))),
+                Step::Bosses,
+                find(|step| matches!(step, Step::Biomes(_))),
+                find(|step| matches!(step, Step::Lakes(_))),
+            ],
+        };
+        assert_eq!(
+            preset.validate("test"),
+            vec!["test has to run its Biomes step before its Enemies step"]
+        );
+    }
+
+    #[test]
+    fn nothing_changes_the_terrain_after_connect() {
+        let preset = Preset {
+            steps: vec![
+                Step::Bosses,
+                Step::Connect,
+                find(|step| matches!(step, Step::Mountains(_))),
+                find(|step| matches!(step, Step::Enemies(_))),
+            ],
+        };
+        // enemies don't change the terrain, so they can come after it
+        assert_eq!(
+            preset.validate("test"),
+            vec!["test has to run its Mountains step before its Connect step"]
+        );
+    }
+
+    // Times making a whole new board with every preset, the way a new game does. Run it with
+    // `cargo test --release -- --ignored --nocapture bench_worldgen`. Uses the same seeds every
+    // time, so the numbers can be compared between builds
+    #[test]
+    #[ignore]
+    fn bench_worldgen() {
+        let world_gen = WorldGen::default();
+        let balance = Balance::default();
+        let boss_scripts = BossScripts::default();
+        println!(
+            "{} new {}x{} boards with each preset",
+            BENCH_RUNS, BOARD_SIZE.0, BOARD_SIZE.1
+        );
+        for (name, preset) in &world_gen.0 {
+            let mut times: Vec<Duration> = (0..BENCH_RUNS)
+                .map(|seed| {
+                    let mut rng = ChaCha8Rng::seed_from_u64(seed as u64);
+                    let start = Instant::now();
+                    World::new(
+                        &mut rng,
+                        Difficulty::default(),
+                        &balance,
+                        &boss_scripts,
+                        preset,
+                    );
+                    start.elapsed()
+                })
+                .collect();
+            times.sort();
+            let total: Duration = times.iter().sum();
+            println!(
+                "    {:<12} {:>10.2?} on average, {:>10.2?} fastest, {:>10.2?} slowest",
+                name,
+                total / BENCH_RUNS,
+                times[0],
+                times[times.len() - 1]
+            );
+        }
+    }
+}